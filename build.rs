@@ -0,0 +1,27 @@
+//! Records the git commit the server is built from, if it is built from a git
+//! checkout, so that `/version` can tell which build is running.
+use std::{path::Path, process::Command};
+
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=HIERONYMUS_GIT_HASH={}", hash.trim());
+    }
+
+    // builds again after a commit or a checkout
+    println!("cargo:rerun-if-changed=build.rs");
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Some(branch) = head.trim().strip_prefix("ref: ") {
+            let branch = Path::new(".git").join(branch);
+            if branch.exists() {
+                println!("cargo:rerun-if-changed={}", branch.display());
+            }
+        }
+    }
+}
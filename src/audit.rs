@@ -0,0 +1,238 @@
+//! A persistent record of players being disconnected.
+//!
+//! Every entry is written as a single line to the audit log file, and is also
+//! emitted as a tracing event under the `audit` target. Entries are tagged with
+//! a [`DisconnectReason`], so that e.g. timeouts can be counted apart from
+//! failed logins.
+//!
+//! The file is written by an [`AuditWriter`] on a thread of its own, so that
+//! the server loop never waits for the disk.
+use std::{
+    fmt,
+    io::{self, Write},
+    net::SocketAddr,
+    sync::mpsc,
+    thread,
+    time::SystemTime,
+};
+
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::server::Player;
+
+pub struct AuditLog<W: Write> {
+    sink: W,
+}
+
+impl<W: Write> AuditLog<W> {
+    pub fn new(sink: W) -> Self {
+        Self { sink }
+    }
+
+    pub fn record(&mut self, entry: &AuditEntry) -> io::Result<()> {
+        info!(
+            target: "audit",
+            action = ?entry.action,
+            username = ?entry.username,
+            uuid = ?entry.uuid,
+            addr = %entry.addr,
+            category = entry.category.as_str(),
+            reason = %entry.reason,
+        );
+        writeln!(self.sink, "{}", entry)?;
+        self.sink.flush()
+    }
+}
+
+/// A handle to an [`AuditLog`] that is written to on a thread of its own.
+#[derive(Debug, Clone)]
+pub struct AuditWriter {
+    tx: mpsc::Sender<AuditEntry>,
+}
+
+impl AuditWriter {
+    /// Starts the thread writing to `sink`, which stops once every handle to
+    /// it is dropped.
+    pub fn spawn<W: Write + Send + 'static>(sink: W) -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel::<AuditEntry>();
+        let mut log = AuditLog::new(sink);
+        thread::Builder::new()
+            .name("audit-log".into())
+            .spawn(move || {
+                for entry in rx {
+                    if let Err(e) = log.record(&entry) {
+                        warn!(?e, ?entry, "Failed to write to the audit log");
+                    }
+                }
+            })?;
+        Ok(Self { tx })
+    }
+
+    /// Queues an entry to be written, without waiting for it.
+    pub fn record(&self, entry: AuditEntry) {
+        // the thread only stops once every handle is gone
+        let _ = self.tx.send(entry);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// The server disconnected the client.
+    Kick,
+    /// The connection ended without the server asking for it.
+    Disconnect,
+}
+
+/// What got a client disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The client stopped sending anything.
+    Timeout,
+    /// The client sent something it wasn't supposed to.
+    ProtocolViolation,
+    /// The client couldn't be authenticated.
+    AuthFailed,
+    /// The server didn't let the player in, e.g. because it is full.
+    LoginRefused,
+    /// The player sent chat messages too quickly.
+    Spam,
+    /// The server is stopping.
+    ServerStopping,
+    /// The client closed the connection, or it got lost.
+    ClientClosed,
+    /// Something went wrong on the server's side.
+    Error,
+}
+impl DisconnectReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::ProtocolViolation => "protocol_violation",
+            Self::AuthFailed => "auth_failed",
+            Self::LoginRefused => "login_refused",
+            Self::Spam => "spam",
+            Self::ServerStopping => "server_stopping",
+            Self::ClientClosed => "client_closed",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub timestamp: SystemTime,
+    pub action: AuditAction,
+    pub category: DisconnectReason,
+    pub username: Option<String>,
+    pub uuid: Option<Uuid>,
+    pub addr: SocketAddr,
+    pub reason: String,
+}
+
+impl AuditEntry {
+    /// Creates an entry for a kick happening right now.
+    ///
+    /// `player` is [`None`] if the connection got kicked before it finished
+    /// logging in.
+    pub fn kick(
+        player: Option<&Player>,
+        addr: SocketAddr,
+        category: DisconnectReason,
+        reason: &str,
+    ) -> Self {
+        Self::new(AuditAction::Kick, player, addr, category, reason)
+    }
+
+    /// Creates an entry for a connection that ended without being kicked,
+    /// right now.
+    pub fn disconnect(
+        player: Option<&Player>,
+        addr: SocketAddr,
+        category: DisconnectReason,
+        reason: &str,
+    ) -> Self {
+        Self::new(AuditAction::Disconnect, player, addr, category, reason)
+    }
+
+    fn new(
+        action: AuditAction,
+        player: Option<&Player>,
+        addr: SocketAddr,
+        category: DisconnectReason,
+        reason: &str,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            action,
+            category,
+            username: player.map(|p| p.username.clone()),
+            uuid: player.map(|p| p.uuid),
+            addr,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            AuditAction::Kick => "kick",
+            AuditAction::Disconnect => "disconnect",
+        };
+        write!(
+            f,
+            "{} {}:{} ",
+            humantime::format_rfc3339_seconds(self.timestamp),
+            action,
+            self.category.as_str()
+        )?;
+        match (&self.username, &self.uuid) {
+            (Some(username), Some(uuid)) => write!(f, "{} ({})", username, uuid)?,
+            _ => write!(f, "<unknown>")?,
+        }
+        write!(f, " from {}: {}", self.addr, self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{AuditEntry, AuditLog, DisconnectReason};
+    use crate::server::Player;
+
+    #[test]
+    fn test_kick_entry() {
+        let player = Player {
+            username: "Notch".into(),
+            uuid: Uuid::nil(),
+        };
+        let mut log = AuditLog::new(vec![]);
+        log.record(&AuditEntry::kick(
+            Some(&player),
+            "127.0.0.1:25565".parse().unwrap(),
+            DisconnectReason::ProtocolViolation,
+            "Flying is not enabled on this server",
+        ))
+        .unwrap();
+        log.record(&AuditEntry::kick(
+            None,
+            "127.0.0.1:25566".parse().unwrap(),
+            DisconnectReason::AuthFailed,
+            "Failed to verify username!",
+        ))
+        .unwrap();
+
+        let log = String::from_utf8(log.sink).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(
+            "kick:protocol_violation Notch (00000000-0000-0000-0000-000000000000) from 127.0.0.1:25565: \
+             Flying is not enabled on this server"
+        ));
+        assert!(lines[1].ends_with(
+            "kick:auth_failed <unknown> from 127.0.0.1:25566: Failed to verify username!"
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+//! Chat components, the JSON-based rich text format used for chat messages,
+//! titles, kick reasons and the like.
+use nom_derive::Parse;
+use num_derive::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    net::{ResponseBuilder, ToResponseField},
+    nom::enum_varint,
+};
+
+/// A text component.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chat {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ChatColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    /// Components displayed after this one, inheriting its style.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<Chat>,
+}
+
+impl Chat {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+    pub fn color(mut self, color: ChatColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+    pub fn bold(mut self) -> Self {
+        self.bold = Some(true);
+        self
+    }
+    pub fn italic(mut self) -> Self {
+        self.italic = Some(true);
+        self
+    }
+    pub fn append(mut self, extra: Chat) -> Self {
+        self.extra.push(extra);
+        self
+    }
+    /// Returns the text of this component and its extra components, without
+    /// any styling.
+    pub fn to_plain_text(&self) -> String {
+        let mut text = self.text.clone();
+        for extra in &self.extra {
+            text.push_str(&extra.to_plain_text());
+        }
+        text
+    }
+}
+impl From<&str> for Chat {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+impl From<String> for Chat {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+impl ToResponseField for &Chat {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        // there is nothing in a component that can fail to serialize
+        let json = serde_json::to_string(self).expect("chat components are always valid JSON");
+        builder.add(&json);
+    }
+}
+
+/// The named colors of chat components, which are also the formatting codes
+/// used by teams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatColor {
+    Black = 0,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    Reset = 21,
+}
+
+/// Where a chat message is displayed by the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatPosition {
+    /// In the chat box, as sent by a player.
+    Chat = 0,
+    /// In the chat box, as feedback from the server.
+    System,
+    /// Above the hotbar.
+    GameInfo,
+}
+
+/// Which chat messages a player wants to see, from their client settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum ChatMode {
+    Enabled,
+    /// Only the feedback of the commands the player runs.
+    CommandsOnly,
+    /// Everything but what other players say.
+    Hidden,
+}
+impl ChatMode {
+    /// Whether a player with this chat mode wants to see messages at the given
+    /// position. Messages above the hotbar are always shown, and so is the
+    /// feedback of commands, which doesn't go through here.
+    pub fn accepts(self, position: ChatPosition) -> bool {
+        match self {
+            Self::Enabled => true,
+            Self::CommandsOnly => position == ChatPosition::GameInfo,
+            Self::Hidden => position != ChatPosition::Chat,
+        }
+    }
+}
+impl Parse<&[u8]> for ChatMode {
+    fn parse(i: &[u8]) -> nom::IResult<&[u8], Self> {
+        enum_varint(i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chat, ChatColor};
+    use crate::net::ResponseBuilder;
+
+    #[test]
+    fn test_serialize() {
+        let chat = Chat::text("Hello, ")
+            .color(ChatColor::Gold)
+            .append(Chat::text("world").bold().color(ChatColor::DarkRed));
+        assert_eq!(
+            serde_json::to_string(&chat).unwrap(),
+            r#"{"text":"Hello, ","color":"gold","extra":[{"text":"world","color":"dark_red","bold":true}]}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Chat::from("plain")).unwrap(),
+            r#"{"text":"plain"}"#
+        );
+    }
+
+    #[test]
+    fn test_disconnect_reason() {
+        let reason = Chat::text("Kicked: ")
+            .color(ChatColor::Red)
+            .append(Chat::text("\"flying\" is not allowed").italic());
+        assert_eq!(reason.to_plain_text(), "Kicked: \"flying\" is not allowed");
+
+        // written as a length-prefixed JSON string, escaping included
+        let json = serde_json::to_string(&reason).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["extra"][0]["text"], "\"flying\" is not allowed");
+        assert_eq!(
+            ResponseBuilder::fragment().add(&reason),
+            ResponseBuilder::fragment().add(&json)
+        );
+    }
+}
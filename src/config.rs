@@ -1,51 +1,228 @@
 use std::{
-    fs::{self, File},
-    io::Write,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    net::SocketAddr,
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use toml::{value::Table, Value};
 use tracing::{debug, warn};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::server::tracker::{MAX_VIEW_DISTANCE, MIN_VIEW_DISTANCE};
+
+/// The template of the default config file.
+const DEFAULT_CONFIG: &str = include_str!("config/default_config.toml");
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default = "Config::default_online_mode")]
     pub online_mode: bool,
+    #[serde(default)]
+    pub encryption: Option<bool>,
+    #[serde(default)]
+    pub prevent_proxy_connections: bool,
     #[serde(default = "Config::default_max_players")]
     pub max_players: usize,
     #[serde(default = "Config::default_motd")]
     pub motd: String,
     #[serde(default = "Config::default_favicon_path")]
     pub favicon_path: PathBuf,
+    #[serde(default = "Config::default_audit_log_path")]
+    pub audit_log_path: PathBuf,
+    #[serde(default)]
+    pub operators: Vec<String>,
+    #[serde(default = "Config::default_allowlist_path")]
+    pub allowlist_path: PathBuf,
+    #[serde(default)]
+    pub enforce_allowlist: bool,
+    #[serde(default)]
+    pub maintenance: bool,
+    #[serde(default = "Config::default_maintenance_message")]
+    pub maintenance_message: String,
+    #[serde(default)]
+    pub maintenance_motd: Option<String>,
+    #[serde(default)]
+    pub seed: Option<i64>,
+    #[serde(default)]
+    pub public_seed: bool,
+    #[serde(default)]
+    pub chat_filter: Vec<String>,
+    #[serde(default)]
+    pub block_filtered_chat: bool,
+    #[serde(default = "Config::default_chat_messages_per_second")]
+    pub chat_messages_per_second: f64,
+    #[serde(default = "Config::default_chat_burst")]
+    pub chat_burst: u32,
+    #[serde(default = "Config::default_view_distance")]
+    pub view_distance: u8,
+    #[serde(default)]
+    pub reduced_debug_info: bool,
+    #[serde(default = "Config::default_pvp")]
+    pub pvp: bool,
+    #[serde(default = "Config::default_blocks_report_path")]
+    pub blocks_report_path: PathBuf,
+    #[serde(default = "Config::default_registries_report_path")]
+    pub registries_report_path: PathBuf,
+    #[serde(default)]
+    pub capture_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub debug_commands: bool,
+    #[serde(default)]
+    pub health_check_address: Option<SocketAddr>,
+    #[serde(default)]
+    pub tui_mouse_capture: bool,
+    #[serde(default = "Config::default_event_channel_capacity")]
+    pub event_channel_capacity: usize,
+    #[serde(default = "Config::default_read_buffer_size")]
+    pub read_buffer_size: usize,
+    #[serde(default = "Config::default_send_batch_size")]
+    pub send_batch_size: usize,
+    #[serde(default = "Config::default_network_compression_threshold")]
+    pub network_compression_threshold: i32,
 }
 
 impl Config {
     pub const DEFAULT_PATH: &'static str = "./config.toml";
+    /// The prefix of the environment variables overriding config fields, which
+    /// are named after the field in upper case, like `HIERO_MAX_PLAYERS`.
+    pub const ENV_PREFIX: &'static str = "HIERO_";
+
+    /// Whether connections are encrypted, which they are by default in online
+    /// mode only.
+    ///
+    /// Players can't be authenticated without encryption, so it can't be
+    /// turned off in online mode.
+    pub fn encryption(&self) -> bool {
+        self.online_mode || self.encryption.unwrap_or(false)
+    }
 
     pub fn read_from_default_path() -> Result<Self, ConfigError> {
         Self::read_from(Self::DEFAULT_PATH)
     }
+    /// Reads the config file at the given path, creating a default one if
+    /// there is none.
+    ///
+    /// Fields set by [environment variables](Self::ENV_PREFIX) take precedence
+    /// over the file, which takes precedence over the defaults.
     pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
-        match fs::read_to_string(&path) {
-            Ok(s) => {
-                let config = toml::from_str(&s)?;
-                debug!(?config, "Read config");
-                Ok(config)
-            }
+        let file = match fs::read_to_string(&path) {
+            Ok(file) => file,
             Err(_) => {
                 warn!("Config file not found! Creating a default one...");
-                let default = include_str!("config/default_config.toml");
-                let mut file = File::create(&path)?;
-                let timestamp = format!("# {}\n", humantime::format_rfc3339(SystemTime::now()));
-                file.write(&timestamp.into_bytes())?;
-                file.write(default.as_bytes())?;
-                let default = toml::from_str(default)?;
-                Ok(default)
+                Self::write_default(&path, true)?;
+                Self::default_file()
             }
+        };
+        let config = Self::parse(&file, env::vars())?;
+        debug!(?config, "Read config");
+        config.validate()?;
+        if config.online_mode && config.encryption == Some(false) {
+            warn!("Encryption can't be turned off in online mode, ignoring");
+        }
+        Ok(config)
+    }
+
+    /// Parses a config file, overriding its fields with the environment
+    /// variables named after them.
+    ///
+    /// Values are read as TOML, falling back to a string if they aren't valid
+    /// TOML, so that `HIERO_MOTD=Hello` doesn't need quotes.
+    fn parse(
+        file: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut table: Table = toml::from_str(file)?;
+        for (var, value) in vars {
+            let field = match var.strip_prefix(Self::ENV_PREFIX) {
+                Some(field) => field.to_lowercase(),
+                None => continue,
+            };
+            let value = toml::from_str::<Table>(&format!("value = {}", value))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("value"))
+                .unwrap_or(Value::String(value));
+            debug!(%var, "Overriding config field from the environment");
+            table.insert(field, value);
         }
+        Ok(Value::Table(table).try_into()?)
     }
+
+    /// Writes a default config file to the given path, which is only
+    /// overwritten if it already exists when forced to.
+    pub fn write_default<P: AsRef<Path>>(path: P, force: bool) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .create_new(!force)
+            .open(path)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => ConfigError::AlreadyExists(path.to_owned()),
+                _ => e.into(),
+            })?;
+        file.write_all(Self::default_file().as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns the contents of a default config file, which has every field
+    /// set to its default and explained in a comment.
+    pub fn default_file() -> String {
+        format!(
+            "# {}\n{}",
+            humantime::format_rfc3339(SystemTime::now()),
+            DEFAULT_CONFIG
+        )
+    }
+    /// Checks that every value is within its range, reporting all the
+    /// problems found at once so that they can be fixed in one go.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = vec![];
+        if self.max_players == 0 {
+            problems.push("max_players must be at least 1".to_string());
+        }
+        if !(MIN_VIEW_DISTANCE..=MAX_VIEW_DISTANCE).contains(&self.view_distance) {
+            problems.push(format!(
+                "view_distance must be between {} and {}, got {}",
+                MIN_VIEW_DISTANCE, MAX_VIEW_DISTANCE, self.view_distance
+            ));
+        }
+        if !(self.chat_messages_per_second.is_finite() && self.chat_messages_per_second > 0.0) {
+            problems.push(format!(
+                "chat_messages_per_second must be positive, got {}",
+                self.chat_messages_per_second
+            ));
+        }
+        if self.chat_burst == 0 {
+            problems.push("chat_burst must be at least 1".to_string());
+        }
+        if self.chat_filter.iter().any(|word| word.trim().is_empty()) {
+            problems.push("chat_filter can't contain blank words".to_string());
+        }
+        if self.event_channel_capacity == 0 {
+            problems.push("event_channel_capacity must be at least 1".to_string());
+        }
+        if self.read_buffer_size == 0 {
+            problems.push("read_buffer_size must be at least 1".to_string());
+        }
+        if self.send_batch_size == 0 {
+            problems.push("send_batch_size must be at least 1".to_string());
+        }
+        if matches!(self.health_check_address, Some(addr) if addr.port() == 0) {
+            problems.push("health_check_address must have a port".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
     fn default_online_mode() -> bool {
         true
     }
@@ -53,11 +230,50 @@ impl Config {
         20
     }
     fn default_motd() -> String {
-        "Just another impostor Minecraft server".into()
+        "Just another hieronymus-based Minecraft server".into()
     }
     fn default_favicon_path() -> PathBuf {
         "favicon.png".into()
     }
+    fn default_audit_log_path() -> PathBuf {
+        "audit.log".into()
+    }
+    fn default_maintenance_message() -> String {
+        "The server is under maintenance, please come back later".into()
+    }
+    fn default_event_channel_capacity() -> usize {
+        100
+    }
+    fn default_read_buffer_size() -> usize {
+        8 * 1024
+    }
+    fn default_send_batch_size() -> usize {
+        16
+    }
+    fn default_network_compression_threshold() -> i32 {
+        256
+    }
+    fn default_allowlist_path() -> PathBuf {
+        "allowlist.txt".into()
+    }
+    fn default_chat_messages_per_second() -> f64 {
+        1.0
+    }
+    fn default_chat_burst() -> u32 {
+        10
+    }
+    fn default_view_distance() -> u8 {
+        10
+    }
+    fn default_pvp() -> bool {
+        false
+    }
+    fn default_blocks_report_path() -> PathBuf {
+        "generated/reports/blocks.json".into()
+    }
+    fn default_registries_report_path() -> PathBuf {
+        "generated/reports/registries.json".into()
+    }
 }
 
 #[derive(Debug, Error)]
@@ -65,5 +281,141 @@ pub enum ConfigError {
     #[error(transparent)]
     TomlError(#[from] toml::de::Error),
     #[error(transparent)]
-    IOError(#[from] std::io::Error),
+    IOError(#[from] io::Error),
+    #[error("{} already exists", .0.display())]
+    AlreadyExists(PathBuf),
+    #[error("Invalid config: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConfigError, DEFAULT_CONFIG};
+
+    #[test]
+    fn test_default_file() {
+        // the default file says the same as leaving everything out
+        let defaults: Config = toml::from_str("").unwrap();
+        let config: Config = toml::from_str(&Config::default_file()).unwrap();
+        assert_eq!(config, defaults);
+
+        // every field is explained, even those unset by default
+        let fields = serde_json::to_value(&defaults).unwrap();
+        for field in fields.as_object().unwrap().keys() {
+            let line = DEFAULT_CONFIG
+                .lines()
+                .position(|line| {
+                    line.trim_start_matches("# ")
+                        .starts_with(&format!("{} =", field))
+                })
+                .unwrap_or_else(|| panic!("{} is missing from the default config", field));
+            assert!(
+                line > 0
+                    && DEFAULT_CONFIG
+                        .lines()
+                        .nth(line - 1)
+                        .unwrap()
+                        .starts_with('#'),
+                "{} has no comment",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_default() {
+        let path = std::env::temp_dir().join("hieronymus-test-generated-config.toml");
+        let _ = std::fs::remove_file(&path);
+
+        Config::write_default(&path, false).unwrap();
+        let generated: Config = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(generated, toml::from_str("").unwrap());
+
+        // existing files are left alone unless forced
+        std::fs::write(&path, "max_players = 5").unwrap();
+        assert!(matches!(
+            Config::write_default(&path, false),
+            Err(ConfigError::AlreadyExists(p)) if p == path
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "max_players = 5");
+        Config::write_default(&path, true).unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("max_players = 20"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = |vars: &[(&str, &str)]| {
+            vars.iter()
+                .map(|(var, value)| (var.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let file = "max_players = 10\nmotd = 'From the file'";
+
+        let config = Config::parse(file, vars(&[])).unwrap();
+        assert_eq!(config.max_players, 10);
+
+        let config = Config::parse(
+            file,
+            vars(&[
+                ("HIERO_MAX_PLAYERS", "50"),
+                ("HIERO_MOTD", "From the environment"),
+                ("HIERO_OPERATORS", "['Notch', 'jeb_']"),
+                ("HIERO_HEALTH_CHECK_ADDRESS", "127.0.0.1:8080"),
+                ("MAX_PLAYERS", "1"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.max_players, 50);
+        assert_eq!(config.motd, "From the environment");
+        assert_eq!(config.operators, ["Notch", "jeb_"]);
+        assert_eq!(
+            config.health_check_address,
+            Some("127.0.0.1:8080".parse().unwrap())
+        );
+        // fields set nowhere keep their defaults
+        assert!(config.online_mode);
+
+        assert!(Config::parse(file, vars(&[("HIERO_MAX_PLAYERS", "lots")])).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let config: Config = toml::from_str("").unwrap();
+        config.validate().unwrap();
+
+        let config: Config = toml::from_str(
+            r#"
+            max_players = 0
+            view_distance = 64
+            chat_messages_per_second = -1.0
+            chat_filter = ["heck", " "]
+            event_channel_capacity = 0
+            health_check_address = "127.0.0.1:0"
+            "#,
+        )
+        .unwrap();
+        let problems = match config.validate() {
+            Err(ConfigError::Invalid(problems)) => problems,
+            other => panic!("expected the config to be invalid, got {:?}", other),
+        };
+        for field in [
+            "max_players",
+            "view_distance",
+            "chat_messages_per_second",
+            "chat_filter",
+            "event_channel_capacity",
+            "health_check_address",
+        ] {
+            assert!(
+                problems.iter().any(|p| p.starts_with(field)),
+                "{} wasn't reported in {:?}",
+                field,
+                problems
+            );
+        }
+        assert_eq!(problems.len(), 6);
+    }
 }
@@ -1,5 +1,10 @@
 //! Some data types used by Minecraft.
-use nom::combinator::{map_opt, map_res};
+use std::fmt;
+
+use nom::{
+    combinator::{map_opt, map_res},
+    number::streaming::be_u8,
+};
 use nom_derive::{Nom, Parse};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
@@ -7,32 +12,37 @@ use serde::Serialize;
 use smol_str::SmolStr;
 use thiserror::Error;
 
-use crate::{net::TryToResponseField, nom::var_str, varint::varint};
+use crate::{
+    math::BlockPos,
+    net::{ResponseBuilder, ToResponseField, TryToResponseField},
+    nom::{enum_varint, var_str},
+    varint::varint,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Nom)]
 pub struct Position(u64);
 
 impl Position {
+    /// Packs the coordinates into a position.
+    ///
+    /// X and Z are truncated to 26 bits, and Y to 12 bits.
+    pub fn new(x: i32, y: i16, z: i32) -> Self {
+        Self((x as u64 & 0x3ffffff) << 38 | (z as u64 & 0x3ffffff) << 12 | (y as u64 & 0xfff))
+    }
     pub fn x(self) -> i32 {
-        let mut x = (self.0 & 0x3ffffff) as i32;
-        if x >= (1 << 25) {
-            x -= 1 << 26;
-        }
-        x
+        (self.0 as i64 >> 38) as i32
     }
     pub fn y(self) -> i16 {
-        let mut x = (self.0 << 26 & 0xfff) as i16;
-        if x >= (1 << 11) {
-            x -= 1 << 12;
-        }
-        x
+        ((self.0 as i64) << 52 >> 52) as i16
     }
     pub fn z(self) -> i32 {
-        let mut x = (self.0 << 38 & 0x3ffffff) as i32;
-        if x >= (1 << 25) {
-            x -= 1 << 26;
-        }
-        x
+        ((self.0 as i64) << 26 >> 38) as i32
+    }
+}
+
+impl ToResponseField for Position {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(self.0);
     }
 }
 
@@ -45,13 +55,31 @@ pub struct SlotData {
     nbt: Option<SlotNbt>,
 }
 
+impl SlotData {
+    pub fn new(id: u32, count: u8) -> Self {
+        Self {
+            id,
+            count,
+            nbt: None,
+        }
+    }
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+    pub fn set_count(&mut self, count: u8) {
+        self.count = count;
+    }
+}
 impl TryToResponseField for SlotData {
     type Err = nbt::Error;
     fn try_to_request_field(
         &self,
         builder: &mut crate::net::ResponseBuilder,
     ) -> Result<(), Self::Err> {
-        builder.add(self.id).add(self.count);
+        builder.varint(self.id).add(self.count);
         match &self.nbt {
             Some(nbt) => builder.nbt(nbt)?,
             None => builder.add(0u8), // TAG_End
@@ -59,6 +87,16 @@ impl TryToResponseField for SlotData {
         Ok(())
     }
 }
+impl TryToResponseField for &Slot {
+    type Err = nbt::Error;
+    fn try_to_request_field(&self, builder: &mut ResponseBuilder) -> Result<(), Self::Err> {
+        builder.add(self.is_some());
+        match self {
+            Some(data) => data.try_to_request_field(builder),
+            None => Ok(()),
+        }
+    }
+}
 impl Parse<&[u8]> for SlotData {
     fn parse(i: &[u8]) -> nom::IResult<&[u8], Self, nom::error::Error<&[u8]>> {
         use nom::{combinator::peek, number::streaming::be_u8};
@@ -83,8 +121,67 @@ pub struct SlotNbt {
     // TODO
 }
 
+/// The extra data some particles need, like the block of block particles.
+#[derive(Clone, Debug)]
+pub enum ParticleData {
+    None,
+    /// For `block`, `block_marker` and `falling_dust`.
+    Block(u32),
+    /// For `dust`. The color components go from 0 to 1, and the scale from
+    /// 0.01 to 4.
+    Dust {
+        red: f32,
+        green: f32,
+        blue: f32,
+        scale: f32,
+    },
+    /// For `dust_color_transition`.
+    DustColorTransition {
+        from: [f32; 3],
+        scale: f32,
+        to: [f32; 3],
+    },
+    /// For `item`.
+    Item(Slot),
+}
+
+impl TryToResponseField for &ParticleData {
+    type Err = nbt::Error;
+    fn try_to_request_field(&self, builder: &mut ResponseBuilder) -> Result<(), Self::Err> {
+        match self {
+            ParticleData::None => {}
+            ParticleData::Block(state) => {
+                builder.varint(*state);
+            }
+            ParticleData::Dust {
+                red,
+                green,
+                blue,
+                scale,
+            } => {
+                builder.add(*red).add(*green).add(*blue).add(*scale);
+            }
+            ParticleData::DustColorTransition { from, scale, to } => {
+                let [r, g, b] = *from;
+                builder.add(r).add(g).add(b).add(*scale);
+                let [r, g, b] = *to;
+                builder.add(r).add(g).add(b);
+            }
+            ParticleData::Item(slot) => {
+                builder.try_add(slot)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Nom)]
 pub struct Angle(pub u8);
+impl ToResponseField for Angle {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(self.0);
+    }
+}
 
 pub trait Angular {
     fn from_angle_degrees(angle: Angle) -> Self;
@@ -132,13 +229,31 @@ angular_impl!(
 );
 
 // TODO: implement some kind of intern system/arena memory management
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Identifier {
     pub namespace: SmolStr,
     pub path: SmolStr,
 }
 
 impl Identifier {
+    pub const DEFAULT_NAMESPACE: &'static str = "minecraft";
+
+    /// Creates an identifier in the `minecraft` namespace.
+    pub fn minecraft(path: &str) -> Self {
+        Self {
+            namespace: Self::DEFAULT_NAMESPACE.into(),
+            path: path.into(),
+        }
+    }
+    /// Parses an identifier, defaulting to the `minecraft` namespace if there
+    /// is none, like the vanilla client does for command arguments.
+    pub fn parse_with_default_namespace(value: &str) -> Result<Self, ParseIdentifierError> {
+        if value.contains(':') {
+            Self::try_from(value)
+        } else {
+            Self::try_from(format!("{}:{}", Self::DEFAULT_NAMESPACE, value).as_str())
+        }
+    }
     pub fn as_ref<'a>(&'a self) -> IdentifierRef<'a> {
         IdentifierRef {
             namespace: &self.namespace,
@@ -159,6 +274,11 @@ impl Parse<&[u8]> for Identifier {
         map_res(var_str, Self::try_from)(i)
     }
 }
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IdentifierRef<'a> {
@@ -228,7 +348,7 @@ pub enum Arm {
 }
 impl Parse<&[u8]> for Arm {
     fn parse(i: &[u8]) -> nom::IResult<&[u8], Self> {
-        map_opt(varint::<u32>, Self::from_u32)(i)
+        enum_varint(i)
     }
 }
 
@@ -239,12 +359,132 @@ pub enum Hand {
 }
 impl Parse<&[u8]> for Hand {
     fn parse(i: &[u8]) -> nom::IResult<&[u8], Self> {
-        map_opt(varint::<u32>, Self::from_u32)(i)
+        enum_varint(i)
     }
 }
 
-#[derive(Debug, Nom)]
-#[repr(u8)]
+/// An animation played by an entity, as sent by Entity Animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Animation {
+    SwingMainArm = 0,
+    TakeDamage,
+    LeaveBed,
+    SwingOffhand,
+    Critical,
+    MagicCritical,
+}
+impl Animation {
+    /// Returns the animation of swinging the arm holding the given hand.
+    pub fn swing(hand: Hand) -> Self {
+        match hand {
+            Hand::Mainhand => Self::SwingMainArm,
+            Hand::Offhand => Self::SwingOffhand,
+        }
+    }
+}
+impl ToResponseField for Animation {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(*self as u8);
+    }
+}
+
+/// Some of the events of Entity Status. What a status means depends on the
+/// type of the entity, and these are the ones of living entities and players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityStatus {
+    /// Plays the death animation and sound.
+    Death = 3,
+    /// Hides the coordinates and other details from the debug screen of a
+    /// player.
+    EnableReducedDebugInfo = 22,
+    DisableReducedDebugInfo = 23,
+    /// Plays the sound of a shield blocking an attack.
+    ShieldBlock = 29,
+    /// Plays the sound of a shield being disabled by an axe.
+    ShieldBreak = 30,
+    /// Plays the totem of undying animation, particles and sound.
+    TotemOfUndying = 35,
+}
+impl ToResponseField for EntityStatus {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(*self as u8);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum Gamemode {
+    Survival = 0,
+    Creative,
+    Adventure,
+    Spectator,
+}
+impl Gamemode {
+    /// Returns the value Change Game State uses to change gamemodes.
+    pub fn game_state_value(self) -> f32 {
+        f32::from(self as u8)
+    }
+}
+impl Parse<&[u8]> for Gamemode {
+    fn parse(i: &[u8]) -> nom::IResult<&[u8], Self> {
+        map_opt(be_u8, Self::from_u8)(i)
+    }
+}
+impl ToResponseField for Gamemode {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(*self as u8);
+    }
+}
+/// The previous gamemode, as sent by Join Game and Respawn, where -1 means
+/// there is none.
+impl ToResponseField for Option<Gamemode> {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(self.map_or(-1, |gamemode| gamemode as i8));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum Difficulty {
+    Peaceful = 0,
+    Easy,
+    Normal,
+    Hard,
+}
+impl Parse<&[u8]> for Difficulty {
+    fn parse(i: &[u8]) -> nom::IResult<&[u8], Self> {
+        map_opt(be_u8, Self::from_u8)(i)
+    }
+}
+impl ToResponseField for Difficulty {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.add(*self as u8);
+    }
+}
+
+/// The volume slider a sound is played with on the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundCategory {
+    Master = 0,
+    Music,
+    Record,
+    Weather,
+    Blocks,
+    Hostile,
+    Neutral,
+    Players,
+    Ambient,
+    Voice,
+}
+impl ToResponseField for SoundCategory {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        builder.varint(*self as u32);
+    }
+}
+
+/// The face of a block.
+///
+/// It is sent as a single byte by Player Digging, which is what [`Parse`]
+/// reads, but as a [varint](enum_varint) by Player Block Placement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 pub enum Direction {
     Bottom,
     Top,
@@ -253,3 +493,124 @@ pub enum Direction {
     West,
     East,
 }
+impl Parse<&[u8]> for Direction {
+    fn parse(i: &[u8]) -> nom::IResult<&[u8], Self> {
+        map_opt(be_u8, Self::from_u8)(i)
+    }
+}
+impl Direction {
+    /// Returns the offset to the neighbouring block in this direction.
+    pub fn offset(self) -> BlockPos {
+        match self {
+            Self::Bottom => BlockPos::new(0, -1, 0),
+            Self::Top => BlockPos::new(0, 1, 0),
+            Self::North => BlockPos::new(0, 0, -1),
+            Self::South => BlockPos::new(0, 0, 1),
+            Self::West => BlockPos::new(-1, 0, 0),
+            Self::East => BlockPos::new(1, 0, 0),
+        }
+    }
+    /// Returns the name used by block state properties like `facing`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Bottom => "down",
+            Self::Top => "up",
+            Self::North => "north",
+            Self::South => "south",
+            Self::West => "west",
+            Self::East => "east",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom_derive::Parse;
+
+    use super::{Animation, Difficulty, Direction, Gamemode, Hand, SoundCategory};
+    use crate::net::ResponseBuilder;
+
+    #[test]
+    fn test_direction() {
+        use Direction::*;
+        for (byte, face) in [Bottom, Top, North, South, West, East]
+            .into_iter()
+            .enumerate()
+        {
+            assert_eq!(
+                Direction::parse(&[byte as u8, 0xff]).unwrap(),
+                (&[0xff][..], face)
+            );
+        }
+        assert!(Direction::parse(&[6]).is_err());
+        assert!(Direction::parse(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn test_animation() {
+        use Animation::*;
+        let animations = [
+            SwingMainArm,
+            TakeDamage,
+            LeaveBed,
+            SwingOffhand,
+            Critical,
+            MagicCritical,
+        ];
+        for (id, animation) in animations.into_iter().enumerate() {
+            let mut expected = ResponseBuilder::fragment();
+            expected.add(id as u8);
+            assert_eq!(*ResponseBuilder::fragment().add(animation), expected);
+        }
+        assert_eq!(Animation::swing(Hand::Offhand), SwingOffhand);
+    }
+
+    #[test]
+    fn test_gamemode() {
+        use Gamemode::*;
+        for (id, gamemode) in [Survival, Creative, Adventure, Spectator]
+            .into_iter()
+            .enumerate()
+        {
+            assert_eq!(Gamemode::parse(&[id as u8]).unwrap().1, gamemode);
+            let mut expected = ResponseBuilder::fragment();
+            expected.add(id as u8);
+            assert_eq!(*ResponseBuilder::fragment().add(gamemode), expected);
+            assert_eq!(gamemode.game_state_value(), id as f32);
+        }
+        assert!(Gamemode::parse(&[4]).is_err());
+
+        let mut expected = ResponseBuilder::fragment();
+        expected.add(3i8);
+        assert_eq!(*ResponseBuilder::fragment().add(Some(Spectator)), expected);
+        // no previous gamemode
+        let mut expected = ResponseBuilder::fragment();
+        expected.add(-1i8);
+        assert_eq!(*ResponseBuilder::fragment().add(None::<Gamemode>), expected);
+    }
+
+    #[test]
+    fn test_difficulty() {
+        use Difficulty::*;
+        for (id, difficulty) in [Peaceful, Easy, Normal, Hard].into_iter().enumerate() {
+            assert_eq!(Difficulty::parse(&[id as u8]).unwrap().1, difficulty);
+            let mut expected = ResponseBuilder::fragment();
+            expected.add(id as u8);
+            assert_eq!(*ResponseBuilder::fragment().add(difficulty), expected);
+        }
+        assert!(Difficulty::parse(&[4]).is_err());
+    }
+
+    #[test]
+    fn test_sound_category() {
+        use SoundCategory::*;
+        let categories = [
+            Master, Music, Record, Weather, Blocks, Hostile, Neutral, Players, Ambient, Voice,
+        ];
+        for (index, category) in categories.into_iter().enumerate() {
+            let mut expected = ResponseBuilder::fragment();
+            expected.varint(index as u32);
+            assert_eq!(*ResponseBuilder::fragment().add(category), expected);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! A tiny HTTP endpoint telling whether the server is alive, for the health
+//! checks of container orchestrators.
+//!
+//! `GET /health` is answered with `200 OK` and the [stats](crate::server::ServerStats) of the
+//! server as JSON, which only happens as long as the server still handles
+//! events. Anything else gets a `404 Not Found`.
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::debug;
+
+use crate::server::ServerHook;
+
+/// Answers health checks until the listener fails.
+pub async fn serve(listener: TcpListener, server: ServerHook) -> eyre::Result<()> {
+    loop {
+        let (socket, addr) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer(socket, &server).await {
+                debug!(%addr, ?e, "Failed to answer a health check");
+            }
+        });
+    }
+}
+
+async fn answer(mut socket: TcpStream, server: &ServerHook) -> eyre::Result<()> {
+    // health checks are small enough to fit in a single read
+    let mut buf = [0; 1024];
+    let read = socket.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..read]);
+    let mut request_line = request.split_whitespace();
+
+    let response = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/health")) => {
+            let stats = server.get_stats().await?;
+            response("200 OK", &serde_json::to_string(&stats)?)
+        }
+        _ => response("404 Not Found", ""),
+    };
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+    Ok(())
+}
+
+fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    use super::serve;
+    use crate::server::Server;
+
+    #[tokio::test]
+    async fn test_health() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Server::spawn_for_tests()));
+
+        let response = reqwest::get(format!("http://{}/health", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let stats: Value = response.json().await.unwrap();
+        assert!(stats["uptime_secs"].is_u64());
+        assert_eq!(stats["online_players"], 0);
+        assert_eq!(stats["max_players"], 20);
+
+        let response = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
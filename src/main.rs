@@ -4,20 +4,25 @@ use color_eyre::Help;
 use eyre::Context;
 use log::LevelFilter;
 use net::auth::Keys;
-use server::{Server, ServerHook};
+use server::{Server, ServerEvent, ServerHook};
 use tokio::{net::TcpListener, spawn, sync::mpsc};
 use tracing::{info, instrument};
 
 use crate::{
-    config::Config,
+    config::{Config, ConfigError},
     net::Connection,
     tui::{ControlFlow, Tui},
 };
 
+mod audit;
+mod chat;
 mod config;
 mod data;
+mod health;
+pub mod math;
 pub mod net;
 mod nom;
+pub mod registry;
 pub mod server;
 mod tui;
 pub mod varint;
@@ -25,24 +30,60 @@ pub mod varint;
 #[tokio::main]
 #[instrument]
 async fn main() -> eyre::Result<()> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--generate-config") {
+        return generate_config(&args[1..]);
+    }
+
     setup()?;
-    spawn(server_main());
+    let config = Arc::new(Config::read_from_default_path()?);
+    let (hook, rx) = ServerHook::channel(config.event_channel_capacity);
+    let server = spawn(server_main(config.clone(), hook.clone(), rx));
 
-    let mut tui = Tui::new()?;
+    let mut tui = Tui::new(config.tui_mouse_capture, hook)?;
 
-    info!("hieronymus v2");
+    info!("{}", server::build_info());
 
-    loop {
-        match tui.tick()? {
-            ControlFlow::Halt => break,
+    let server_stopped = loop {
+        match tui.tick().await? {
+            ControlFlow::Halt => break false,
             ControlFlow::Continue => continue,
+            ControlFlow::ServerStopped => break true,
         }
-    }
+    };
     tui.cleanup()?;
+    if server_stopped {
+        // tells why, if it failed
+        server.await??;
+    }
 
     Ok(())
 }
 
+/// Writes a default config to the given path, or to stdout if there is none,
+/// for `--generate-config [--force] [PATH]`.
+fn generate_config(args: &[String]) -> eyre::Result<()> {
+    let force = args.iter().any(|a| a == "--force");
+    let mut paths = args.iter().filter(|a| *a != "--force");
+    match (paths.next(), paths.next()) {
+        (None, _) => print!("{}", Config::default_file()),
+        (Some(path), None) => {
+            match Config::write_default(path, force) {
+                Err(ConfigError::AlreadyExists(path)) => {
+                    eyre::bail!(
+                        "{} already exists, use --force to overwrite it",
+                        path.display()
+                    )
+                }
+                result => result?,
+            }
+            eprintln!("Wrote a default config to {}", path);
+        }
+        (Some(_), Some(_)) => eyre::bail!("Usage: hieronymusv2 --generate-config [--force] [PATH]"),
+    }
+    Ok(())
+}
+
 fn setup() -> eyre::Result<()> {
     dotenv::dotenv().ok();
     let log = dotenv::var("RUST_LOG").ok()
@@ -57,24 +98,39 @@ fn setup() -> eyre::Result<()> {
     Ok(())
 }
 
-#[instrument]
-async fn server_main() -> eyre::Result<()> {
-    let (tx, rx) = mpsc::channel(100);
-
+#[instrument(skip_all)]
+async fn server_main(
+    config: Arc<Config>,
+    hook: ServerHook,
+    rx: mpsc::Receiver<ServerEvent>,
+) -> eyre::Result<()> {
     let keys = Keys::new()?;
-    let config = Arc::new(Config::read_from_default_path()?);
     let server = Server::new(rx, config.clone()).await?;
-    let hook = ServerHook(tx);
 
     let listener = TcpListener::bind("127.0.0.1:25565")
         .await
         .wrap_err("Failed to listen on address; is the port occupied?")
         .suggestion("Please use a different address to listen on")?;
 
-    spawn(listener_thread(listener, hook, keys, config));
-
-    server.server_loop().await?;
-    Ok(())
+    let health = match config.health_check_address {
+        Some(addr) => {
+            let listener = TcpListener::bind(addr)
+                .await
+                .wrap_err("Failed to listen on the health check address")?;
+            info!(%addr, "Answering health checks");
+            Some(spawn(health::serve(listener, hook.clone())))
+        }
+        None => None,
+    };
+    let listener = spawn(listener_thread(listener, hook, keys, config));
+
+    let result = server.server_loop().await;
+    // stop taking new connections once the server is gone
+    listener.abort();
+    if let Some(health) = health {
+        health.abort();
+    }
+    result
 }
 
 #[instrument(skip_all)]
@@ -84,8 +140,8 @@ async fn listener_thread(
     keys: Keys,
     config: Arc<Config>,
 ) -> eyre::Result<()> {
-    while let Ok((socket, _addr)) = listener.accept().await {
-        let conn = Connection::new(socket, tx.clone(), keys.clone(), config.clone());
+    while let Ok((socket, addr)) = listener.accept().await {
+        let conn = Connection::new(socket, addr, tx.clone(), keys.clone(), config.clone());
         spawn(async move { conn.connection_loop().await.unwrap() });
     }
     Ok(())
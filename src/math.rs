@@ -0,0 +1,221 @@
+//! Math utilities for working with positions and coordinates.
+use std::ops::{Add, Sub};
+
+use crate::{
+    data::Position,
+    net::{coords, ResponseBuilder, ToResponseField},
+};
+
+/// A point or vector in the world, e.g. the position of an entity.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+impl Vec3 {
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+        Self { x, y, z }
+    }
+    pub fn length_squared(self) -> f64 {
+        self.x * self.x + self.y * self.y + self.z * self.z
+    }
+    pub fn length(self) -> f64 {
+        self.length_squared().sqrt()
+    }
+    pub fn distance_squared(self, other: Self) -> f64 {
+        (self - other).length_squared()
+    }
+    pub fn distance(self, other: Self) -> f64 {
+        (self - other).length()
+    }
+    /// Returns the position of the block this point is in.
+    pub fn block_pos(self) -> BlockPos {
+        BlockPos::new(
+            self.x.floor() as i32,
+            self.y.floor() as i32,
+            self.z.floor() as i32,
+        )
+    }
+}
+impl Add for Vec3 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+impl Sub for Vec3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+impl ToResponseField for Vec3 {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        coords::add_vec3(builder, *self);
+    }
+}
+impl From<BlockPos> for Vec3 {
+    fn from(pos: BlockPos) -> Self {
+        Self::new(pos.x.into(), pos.y.into(), pos.z.into())
+    }
+}
+impl From<Position> for Vec3 {
+    fn from(pos: Position) -> Self {
+        BlockPos::from(pos).into()
+    }
+}
+impl From<Vec3> for Position {
+    fn from(v: Vec3) -> Self {
+        v.block_pos().into()
+    }
+}
+
+/// The position of a block in the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+impl BlockPos {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+    /// Returns the point at the center of the block.
+    pub fn center(self) -> Vec3 {
+        Vec3::from(self) + Vec3::new(0.5, 0.5, 0.5)
+    }
+    pub fn distance(self, other: Self) -> f64 {
+        Vec3::from(self).distance(other.into())
+    }
+}
+impl Add for BlockPos {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+impl Sub for BlockPos {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+impl ToResponseField for BlockPos {
+    fn to_request_field(&self, builder: &mut ResponseBuilder) {
+        coords::add_block_pos(builder, *self);
+    }
+}
+impl From<Position> for BlockPos {
+    fn from(pos: Position) -> Self {
+        Self::new(pos.x(), pos.y().into(), pos.z())
+    }
+}
+impl From<BlockPos> for Position {
+    fn from(pos: BlockPos) -> Self {
+        Position::new(pos.x, pos.y as i16, pos.z)
+    }
+}
+
+/// A value that is either absolute, or relative to some other value.
+///
+/// Used by teleports and command coordinates, for example.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbsOrRel<T> {
+    Absolute(T),
+    Relative(T),
+}
+impl<T: Add<Output = T>> AbsOrRel<T> {
+    /// Returns the absolute value, with relative values being offsets from
+    /// `base`.
+    pub fn resolve(self, base: T) -> T {
+        match self {
+            Self::Absolute(t) => t,
+            Self::Relative(t) => base + t,
+        }
+    }
+}
+impl<T> AbsOrRel<T> {
+    /// Unwraps the inner value, setting `flag` in `flags` if it is relative.
+    ///
+    /// Used by packets that mark relative fields in a bitfield, like Player
+    /// Position And Look.
+    pub fn unwrap_and_set_flag(self, flag: u8, flags: &mut u8) -> T {
+        match self {
+            Self::Absolute(t) => t,
+            Self::Relative(t) => {
+                *flags |= flag;
+                t
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AbsOrRel::{self, Absolute, Relative},
+        BlockPos, Vec3,
+    };
+    use nom_derive::Parse;
+
+    use crate::data::Position;
+
+    #[test]
+    fn test_position_conversions() {
+        // example from wiki.vg
+        let pos = BlockPos::new(18357644, 831, -20882616);
+        let (_, packed) = Position::parse(&0x4607632c15b4833fu64.to_be_bytes()).unwrap();
+        assert_eq!(Position::from(pos), packed);
+        assert_eq!(
+            (packed.x(), packed.y(), packed.z()),
+            (18357644, 831, -20882616)
+        );
+        assert_eq!(BlockPos::from(packed), pos);
+
+        let pos = BlockPos::new(-1, -64, 0);
+        assert_eq!(BlockPos::from(Position::from(pos)), pos);
+
+        let v = Vec3::new(-0.5, 64.9, 12.0);
+        assert_eq!(v.block_pos(), BlockPos::new(-1, 64, 12));
+        assert_eq!(BlockPos::from(Position::from(v)), BlockPos::new(-1, 64, 12));
+        assert_eq!(Vec3::from(Position::from(v)), Vec3::new(-1.0, 64.0, 12.0));
+    }
+
+    #[test]
+    fn test_vector_ops() {
+        let a = Vec3::new(1.0, 2.0, 3.0);
+        let b = Vec3::new(4.0, 6.0, 3.0);
+        assert_eq!(a + b, Vec3::new(5.0, 8.0, 6.0));
+        assert_eq!(b - a, Vec3::new(3.0, 4.0, 0.0));
+        assert_eq!(a.distance(b), 5.0);
+        assert_eq!(b.distance(a), 5.0);
+        assert_eq!(a.distance_squared(b), 25.0);
+        assert_eq!(a.distance(a), 0.0);
+
+        let a = BlockPos::new(0, 0, 0);
+        let b = BlockPos::new(0, 3, 4);
+        assert_eq!(a.distance(b), 5.0);
+        assert_eq!(a.center(), Vec3::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_unwrap_and_set_flag() {
+        // mirrors how Player Position And Look builds its flags
+        let mut flags = 0;
+        let x: AbsOrRel<f64> = Absolute(69.0);
+        let y: AbsOrRel<f64> = Relative(0.0);
+        let yaw: AbsOrRel<f32> = Relative(90.0);
+        assert_eq!(x.unwrap_and_set_flag(0b00001, &mut flags), 69.0);
+        assert_eq!(y.unwrap_and_set_flag(0b00010, &mut flags), 0.0);
+        assert_eq!(yaw.unwrap_and_set_flag(0b01000, &mut flags), 90.0);
+        assert_eq!(flags, 0b01010);
+    }
+}
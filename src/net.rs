@@ -1,96 +1,333 @@
 pub mod auth;
+pub mod capture;
+pub mod compression;
+pub mod coords;
 mod handshake;
 mod login;
+pub mod login_plugin;
+pub mod packets;
 mod play;
+pub mod rate_limit;
 mod status;
 
-use std::sync::Arc;
+pub use login::is_valid_username;
+
+use std::{
+    borrow::Cow,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter},
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use aes::{cipher::AsyncStreamCipher, Aes128};
 use cfb8::Cfb8;
 use eyre::bail;
-use nom::{multi::length_data, HexDisplay, IResult};
+use nom::{HexDisplay, IResult};
 use serde::Serialize;
+use thiserror::Error;
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+    time,
 };
-use tracing::{debug, instrument, trace, warn};
+use tracing::{debug, info, instrument, trace, warn};
 use uuid::Uuid;
 
 use crate::{
+    audit::{AuditEntry, DisconnectReason},
+    chat::Chat,
     config::Config,
-    net::auth::{AuthSession, Keys},
-    server::ServerHook,
+    math::AbsOrRel,
+    net::{
+        auth::{AuthSession, AuthenticationError, Keys},
+        capture::{Capture, FrameDirection},
+        compression::PreparedPacket,
+        login_plugin::LoginPlugins,
+        rate_limit::RateLimiter,
+    },
+    server::{Player, ServerHook},
     varint::{self, varint, VarInt},
 };
 use async_trait::async_trait;
 
 #[async_trait]
 pub trait Packet: std::fmt::Debug {
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()>;
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()>;
+}
+
+pub type NetResult<T> = Result<T, NetError>;
+
+/// Why a connection failed, which decides whether the client gets kicked or
+/// the error is only logged.
+#[derive(Debug, Error)]
+pub enum NetError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    /// The client sent something it wasn't supposed to.
+    #[error("Protocol violation: {0}")]
+    ProtocolViolation(String),
+    #[error(transparent)]
+    AuthError(#[from] AuthenticationError),
+    /// The client closed the connection.
+    #[error("Connection closed")]
+    Closed,
+    /// The client didn't send anything for too long.
+    #[error("Timed out")]
+    TimedOut,
+    /// The server tried to send packets of another state than the one the
+    /// connection is in, which is a bug of the server.
+    #[error("Expected the connection to be in state {expected:?}, but it is in state {actual:?}")]
+    WrongState {
+        expected: ConnectionState,
+        actual: ConnectionState,
+    },
+    /// Anything else, which is most likely a bug of the server.
+    #[error("{0:?}")]
+    Other(eyre::Report),
+}
+impl NetError {
+    /// Returns why the client is disconnected and the message it is kicked
+    /// with, if the error is its fault.
+    pub fn disconnect_reason(&self) -> Option<(DisconnectReason, String)> {
+        match self {
+            Self::ProtocolViolation(_) => {
+                Some((DisconnectReason::ProtocolViolation, self.to_string()))
+            }
+            Self::AuthError(_) => Some((
+                DisconnectReason::AuthFailed,
+                "Failed to verify username!".into(),
+            )),
+            Self::TimedOut => Some((DisconnectReason::Timeout, self.to_string())),
+            Self::IoError(_) | Self::Closed | Self::WrongState { .. } | Self::Other(_) => None,
+        }
+    }
+}
+impl From<eyre::Report> for NetError {
+    fn from(report: eyre::Report) -> Self {
+        // errors that went through eyre on their way here keep their kind
+        let report = match report.downcast::<Self>() {
+            Ok(e) => return e,
+            Err(report) => report,
+        };
+        match report.downcast::<io::Error>() {
+            Ok(e) => Self::IoError(e),
+            Err(report) => Self::Other(report),
+        }
+    }
 }
 
 type BoxedPacket<'a> = Box<dyn Packet + Send + Sync + 'a>;
 type AesCipher = Cfb8<Aes128>;
+
+/// The stream a [`Connection`] talks to its client over, usually a TCP socket.
+pub trait Socket: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Socket for T {}
+
+/// Where packets built with a [`ResponseBuilder`] are sent.
+///
+/// [`Connection`] sends them to its client, unless it was created with
+/// another sink to send them to instead, so that packet handlers can be
+/// tested without a socket. Prepared packets end up in that sink too, but
+/// [streamed responses](ResponseStream) are always written to the socket.
+#[async_trait]
+pub trait PacketSink: Send {
+    async fn send_packet(&mut self, packet: &ResponseBuilder) -> eyre::Result<()>;
+}
+
+/// How long a client can go without sending anything, like vanilla servers.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct Connection {
-    socket: TcpStream,
+    socket: Box<dyn Socket>,
+    addr: SocketAddr,
     server: ServerHook,
     state: ConnectionState,
     config: Arc<Config>,
+    player: Option<Player>,
 
     keys: Keys,
     auth_session: Option<AuthSession>,
     encrypt_cipher: Option<AesCipher>,
     decrypt_cipher: Option<AesCipher>,
+    /// The size from which packets are compressed, once compression is on.
+    compression_threshold: Option<usize>,
+    /// The login plugin requests waiting for an answer from the client.
+    login_plugins: LoginPlugins,
+
+    events: mpsc::UnboundedReceiver<ClientEvent>,
+    client: ClientHook,
+    /// Where the packets of this connection are recorded, if enabled.
+    capture: Option<Capture<BufWriter<File>>>,
+    /// Limits how often chat messages and commands can be sent.
+    chat_limiter: RateLimiter,
+    /// The yaw and pitch the player is looking at, in degrees.
+    look: (f32, f32),
+    /// Whether the connection should be closed once the current packet has
+    /// been handled.
+    closed: bool,
+    /// How long the client can go without sending anything before it is
+    /// disconnected.
+    timeout: Duration,
+    /// Where packets are sent instead of the socket, if anywhere.
+    #[cfg(test)]
+    sink: Option<Box<dyn PacketSink>>,
 }
 
 impl Connection {
-    pub const fn new(
-        socket: TcpStream,
+    pub fn new(
+        socket: impl Socket + 'static,
+        addr: SocketAddr,
         server: ServerHook,
         keys: Keys,
         config: Arc<Config>,
     ) -> Self {
+        let (client, events) = ClientHook::new();
+        let capture = config.capture_dir.as_ref().and_then(|dir| {
+            // colons aren't allowed in file names on Windows
+            let path = dir.join(format!("{}.capture", addr).replace(':', "_"));
+            // a client may well reconnect from the same address, so append
+            // instead of overwriting the capture of its previous connection
+            let file = std::fs::create_dir_all(dir)
+                .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+            match file {
+                Ok(file) => {
+                    debug!(?path, "Capturing packets");
+                    Some(Capture::new(BufWriter::new(file)))
+                }
+                Err(e) => {
+                    warn!(?e, ?path, "Failed to create the capture file");
+                    None
+                }
+            }
+        });
+        let chat_limiter = RateLimiter::new(
+            config.chat_messages_per_second,
+            config.chat_burst,
+            Instant::now(),
+        );
         Self {
-            socket,
+            socket: Box::new(socket),
+            addr,
             server,
             state: ConnectionState::Handshake,
             config,
+            player: None,
 
             keys,
             auth_session: None,
             encrypt_cipher: None,
             decrypt_cipher: None,
+            compression_threshold: None,
+            login_plugins: LoginPlugins::new(),
+
+            events,
+            client,
+            capture,
+            chat_limiter,
+            look: (0.0, 0.0),
+            closed: false,
+            timeout: TIMEOUT,
+            #[cfg(test)]
+            sink: None,
         }
     }
 
+    /// Handles the connection until it ends, kicking the client if it was at
+    /// fault.
     #[instrument(skip_all)]
     pub async fn connection_loop(mut self) -> eyre::Result<()> {
-        let mut buf = vec![0u8; 1024];
-        loop {
-            let read = self.socket.read(&mut buf).await?;
-            if read == 0 {
-                debug!("Connection reset");
+        let e = match self.run().await {
+            // the server closed it, after kicking the client if it did
+            Ok(()) => {
+                debug!("Connection closed");
                 return Ok(());
             }
-            if let Some(cipher) = &mut self.decrypt_cipher {
-                trace!("encrypted:\n{}", buf.to_hex(16));
-                cipher.decrypt(&mut buf);
-                trace!("decrypted:\n{}", buf.to_hex(16));
-            }
+            Err(e) => e,
+        };
+        if let Some((reason, message)) = e.disconnect_reason() {
+            let _ = self.send_disconnect(reason, &Chat::text(message)).await;
+            return Ok(());
+        }
+        let reason = match e {
+            NetError::Closed | NetError::IoError(_) => DisconnectReason::ClientClosed,
+            _ => DisconnectReason::Error,
+        };
+        let entry = AuditEntry::disconnect(self.player.as_ref(), self.addr, reason, &e.to_string());
+        let _ = self.log_disconnect(entry).await;
+        match reason {
+            DisconnectReason::Error => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
 
-            use ::nom::Err;
-            match self.read_packet(&buf[..read]).await {
-                Ok(_) => {}
-                Err(Err::Error(e) | Err::Failure(e)) => {
-                    bail!("Parsing error: {:?}", e);
+    async fn run(&mut self) -> NetResult<()> {
+        let mut buf = self.read_buffer();
+        // received data that doesn't make up a whole packet yet
+        let mut pending = vec![];
+        let mut deadline = time::Instant::now() + self.timeout;
+        loop {
+            let read = tokio::select! {
+                read = self.socket.read(&mut buf) => Some(read?),
+                Some(event) = self.events.recv() => {
+                    self.handle_client_event(event).await?;
+                    None
                 }
-                Err(Err::Incomplete(n)) => {
-                    debug!(?n, "needed more data!");
-                    // ignore
-                    continue;
+                _ = time::sleep_until(deadline) => return Err(NetError::TimedOut),
+            };
+            if let Some(read) = read {
+                if read == 0 {
+                    return Err(NetError::Closed);
+                }
+                deadline = time::Instant::now() + self.timeout;
+                let data = &mut buf[..read];
+                if let Some(cipher) = &mut self.decrypt_cipher {
+                    trace!("encrypted:\n{}", data.to_hex(16));
+                    cipher.decrypt(data);
+                    trace!("decrypted:\n{}", data.to_hex(16));
+                }
+                pending.extend_from_slice(data);
+
+                let rest = self.read_packet(&pending).await?;
+                let consumed = pending.len() - rest.len();
+                pending.drain(..consumed);
+            }
+            if self.closed {
+                debug!("Closing connection");
+                self.socket.shutdown().await?;
+                return Ok(());
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn handle_client_event(&mut self, event: ClientEvent) -> eyre::Result<()> {
+        match event {
+            ClientEvent::Teleport { x, y, z } => {
+                use AbsOrRel::Relative;
+                self.player_position_and_look(x, y, z, Relative(0.0), Relative(0.0), false)
+                    .await
+            }
+            ClientEvent::SystemMessage(message) => self.send_system_message(&message).await,
+            ClientEvent::Packet(mut packet) => packet.send(self).await,
+            ClientEvent::Batch(packets) => self.send_batched(packets).await,
+            ClientEvent::Prepared(packet) => {
+                #[cfg(test)]
+                if let Some(sink) = &mut self.sink {
+                    let packet = ResponseBuilder {
+                        data: packet.data().to_vec(),
+                    };
+                    return sink.send_packet(&packet).await;
                 }
+                self.record(FrameDirection::Outbound, packet.data());
+                let mut frame = packet.frame(self.compression_threshold)?.into_owned();
+                self.write_frame(&mut frame).await
+            }
+            ClientEvent::Kick(reason, message) => {
+                self.send_disconnect(reason, &message).await?;
+                self.closed = true;
+                Ok(())
             }
         }
     }
@@ -100,61 +337,401 @@ impl Connection {
     // `skip_all` errors out, sometimes `skip(self, input)` errors out, it makes
     // no freaking sense. Someone help.
     //#[instrument(skip(self, input))]
+
+    /// Handles every whole packet at the start of `input`, returning the data
+    /// left over.
     #[instrument(skip_all)]
-    pub async fn read_packet<'data>(&mut self, mut input: &'data [u8]) -> IResult<&'data [u8], ()> {
+    pub async fn read_packet<'data>(&mut self, mut input: &'data [u8]) -> NetResult<&'data [u8]> {
         loop {
             if matches!(self.state, ConnectionState::Handshake) && input.starts_with(b"\xfe\x01") {
-                // legacy ping
-                todo!("legacy ping")
+                return Err(NetError::ProtocolViolation(
+                    "Legacy server list pings aren't supported".into(),
+                ));
             }
 
             trace!(?input);
-            let (i, data) = length_data(varint::<u32>)(input)?;
+            let (i, len) = match varint::<u32>(input) {
+                Ok(len) => len,
+                Err(::nom::Err::Incomplete(_)) => return Ok(input),
+                Err(e) => {
+                    return Err(NetError::ProtocolViolation(format!(
+                        "Invalid length: {}",
+                        e
+                    )))
+                }
+            };
+            // checked before the rest of the packet is even received
+            let len = len as usize;
+            if len > self.state.max_packet_len() {
+                return Err(NetError::ProtocolViolation(format!(
+                    "Packet of {} bytes is too large for the {} state",
+                    len,
+                    self.state.as_str()
+                )));
+            }
+            if i.len() < len {
+                // the rest of the packet hasn't been received yet
+                return Ok(input);
+            }
+            let (data, i) = i.split_at(len);
             input = i;
             trace!(?input, ?data);
-            let (rem, packet) = match self.state {
-                ConnectionState::Handshake => handshake::read_packet(data),
-                ConnectionState::Status => status::read_packet(data),
-                ConnectionState::Login => login::read_packet(data),
-                ConnectionState::Play => play::read_packet(data),
-            }?;
+            let frame = data;
+            let data = match self.compression_threshold {
+                Some(threshold) => {
+                    match compression::decompress(frame, threshold, self.state.max_packet_len()) {
+                        Ok(data) => data,
+                        Err(e) => return Err(NetError::ProtocolViolation(e.to_string())),
+                    }
+                }
+                None => Cow::Borrowed(frame),
+            };
+            self.record(FrameDirection::Inbound, &data);
+            let (rem, packet) = match parse_packet(self.state, &data) {
+                Ok(packet) => packet,
+                Err(::nom::Err::Incomplete(n)) => {
+                    // the packet was whole, but its body ended too early
+                    debug!(?n, "Dropping truncated packet");
+                    if input.is_empty() {
+                        return Ok(input);
+                    }
+                    // the packets after it are still whole
+                    continue;
+                }
+                Err(::nom::Err::Error(e) | ::nom::Err::Failure(e)) => {
+                    return Err(NetError::ProtocolViolation(format!(
+                        "Invalid packet: {:?}",
+                        e.code
+                    )));
+                }
+            };
             trace!(?rem, ?packet);
-            assert!(rem.is_empty());
+            if !rem.is_empty() {
+                return Err(NetError::ProtocolViolation(format!(
+                    "{} bytes left after packet",
+                    rem.len()
+                )));
+            }
 
             debug!(?packet, "Got packet");
-            //todo
-            packet.handle(self).await.unwrap();
+            packet.handle(self).await?;
 
-            if input.is_empty() {
-                return Ok((input, ()));
+            // anything sent after the packet that closed the connection is
+            // ignored
+            if input.is_empty() || self.closed {
+                return Ok(input);
             }
         }
     }
 
-    pub async fn kick(&mut self, reason: &str) -> eyre::Result<()> {
+    /// Allocates the buffer data from the socket is read into.
+    fn read_buffer(&self) -> Vec<u8> {
+        // an empty buffer would look like the end of the stream
+        vec![0u8; self.config.read_buffer_size.max(1)]
+    }
+
+    /// Sends many packets in a row, like chunks, yielding to the runtime
+    /// after every [batch](Config::send_batch_size) so that other connections
+    /// get to run in the meantime.
+    pub async fn send_batched<I>(&mut self, packets: I) -> eyre::Result<()>
+    where
+        I: IntoIterator<Item = ResponseBuilder>,
+        I::IntoIter: Send,
+    {
+        let mut batcher = Batcher::new(self.config.send_batch_size);
+        for mut packet in packets {
+            packet.send(self).await?;
+            if batcher.sent() {
+                let _ = tokio::task::yield_now().await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes the connection once the packet being handled is done.
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    /// Disconnects the client with a message, which is only shown to it in the
+    /// Login and Play states, as the others have no packet for it.
+    ///
+    /// The reason is logged and recorded in the audit log either way.
+    pub async fn send_disconnect(
+        &mut self,
+        reason: DisconnectReason,
+        message: &Chat,
+    ) -> eyre::Result<()> {
+        let detail = message.to_plain_text();
+        let entry = AuditEntry::kick(self.player.as_ref(), self.addr, reason, &detail);
+        self.log_disconnect(entry).await?;
+
         let packet_id = match self.state {
-            ConnectionState::Login => 0x00,
+            ConnectionState::Login => login::LOGIN_CB_DISCONNECT,
             ConnectionState::Play => 0x1a,
-            _ => bail!("kick packets cannot be issued in state {:?}", self.state),
+            ConnectionState::Handshake | ConnectionState::Status => return Ok(()),
         };
         ResponseBuilder::new(packet_id)
-            .var_data(reason)
+            .add(message)
             .send(self)
             .await?;
+        Ok(())
+    }
+
+    /// Logs why the connection ends, recording it in the audit log as well
+    /// unless the client just left after pinging the server, which happens
+    /// all the time.
+    async fn log_disconnect(&mut self, entry: AuditEntry) -> eyre::Result<()> {
+        info!(
+            category = entry.category.as_str(),
+            detail = %entry.reason,
+            "Disconnecting client"
+        );
+        let pinged = matches!(
+            self.state,
+            ConnectionState::Handshake | ConnectionState::Status
+        );
+        if pinged && entry.category == DisconnectReason::ClientClosed {
+            return Ok(());
+        }
+        self.server.audit(entry).await
+    }
+
+    /// Moves the connection to another state, failing if the protocol doesn't
+    /// allow going there from the current one.
+    pub fn transition_to(&mut self, next: ConnectionState) -> NetResult<()> {
+        if !self.state.can_transition_to(next) {
+            warn!(current = ?self.state, ?next, "Illegal connection state transition");
+            return Err(NetError::ProtocolViolation(format!(
+                "cannot transition from state {:?} to {:?}",
+                self.state, next
+            )));
+        }
+        debug!(current = ?self.state, ?next, "Changing connection state");
+        self.state = next;
+        Ok(())
+    }
+
+    /// Fails unless the connection is in the given state, for sending packets
+    /// that only make sense in that state.
+    pub fn ensure_state(&self, expected: ConnectionState) -> NetResult<()> {
+        if self.state != expected {
+            return Err(NetError::WrongState {
+                expected,
+                actual: self.state,
+            });
+        }
+        Ok(())
+    }
+
+    /// Records a packet to the capture of this connection, if there is one.
+    fn record(&mut self, direction: FrameDirection, data: &[u8]) {
+        if let Some(capture) = &mut self.capture {
+            if let Err(e) = capture.record(direction, self.state, data) {
+                warn!(?e, "Failed to record a packet, disabling capture");
+                self.capture = None;
+            }
+        }
+    }
+
+    /// Encrypts a whole frame if needed, and writes it to the socket.
+    async fn write_frame(&mut self, frame: &mut [u8]) -> eyre::Result<()> {
+        if let Some(cipher) = &mut self.encrypt_cipher {
+            cipher.encrypt(frame);
+        }
+        self.socket.write_all(frame).await?;
+        Ok(())
+    }
+
+    /// Begins a [streamed response](ResponseStream) with a body of `body_len`
+    /// bytes, not counting the packet ID.
+    pub async fn stream_response(
+        &mut self,
+        packet_id: u32,
+        body_len: usize,
+    ) -> eyre::Result<ResponseStream<'_, Box<dyn Socket>>> {
+        ResponseStream::new(
+            &mut self.socket,
+            self.encrypt_cipher.as_mut(),
+            self.compression_threshold.is_some(),
+            packet_id,
+            body_len,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl PacketSink for Connection {
+    async fn send_packet(&mut self, packet: &ResponseBuilder) -> eyre::Result<()> {
+        #[cfg(test)]
+        if let Some(sink) = &mut self.sink {
+            return sink.send_packet(packet).await;
+        }
+        trace!("\n{}", packet.data.to_hex(16));
+        self.record(FrameDirection::Outbound, &packet.data);
+        let mut frame = compression::frame(&packet.data, self.compression_threshold)?;
+        self.write_frame(&mut frame).await
+    }
+}
 
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // however the connection ended, even by an error or a panic, the
+        // server has to forget about the player, or give back the slot they
+        // were allowed to log in with
+        if let Some(player) = &self.player {
+            self.server.player_left(player.uuid);
+        }
+    }
+}
+
+/// Counts the packets sent in a row, to know when to yield.
+#[derive(Debug)]
+struct Batcher {
+    size: usize,
+    in_batch: usize,
+}
+
+impl Batcher {
+    fn new(size: usize) -> Self {
+        Self {
+            size: size.max(1),
+            in_batch: 0,
+        }
+    }
+
+    /// Counts a sent packet, returning whether the batch is full.
+    fn sent(&mut self) -> bool {
+        self.in_batch += 1;
+        if self.in_batch == self.size {
+            self.in_batch = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A handle to a [`Connection`], through which the server can make it send
+/// packets to its client.
+#[derive(Clone, Debug)]
+pub struct ClientHook(mpsc::UnboundedSender<ClientEvent>);
+
+impl ClientHook {
+    /// Creates a hook, along with the receiving end its events are sent to.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ClientEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self(tx), rx)
+    }
+    pub fn teleport(
+        &self,
+        x: AbsOrRel<f64>,
+        y: AbsOrRel<f64>,
+        z: AbsOrRel<f64>,
+    ) -> eyre::Result<()> {
+        self.0.send(ClientEvent::Teleport { x, y, z })?;
+        Ok(())
+    }
+    pub fn send_system_message(&self, message: String) -> eyre::Result<()> {
+        self.0.send(ClientEvent::SystemMessage(message))?;
+        Ok(())
+    }
+    pub fn send_packet(&self, packet: ResponseBuilder) -> eyre::Result<()> {
+        self.0.send(ClientEvent::Packet(packet))?;
+        Ok(())
+    }
+    /// Sends many packets at once, letting other connections run in between
+    /// batches of them.
+    pub fn send_batch(&self, packets: Vec<ResponseBuilder>) -> eyre::Result<()> {
+        self.0.send(ClientEvent::Batch(packets))?;
         Ok(())
     }
+    pub fn send_prepared(&self, packet: Arc<PreparedPacket>) -> eyre::Result<()> {
+        self.0.send(ClientEvent::Prepared(packet))?;
+        Ok(())
+    }
+    /// Kicks the player, closing the connection.
+    pub fn kick(&self, reason: DisconnectReason, message: Chat) -> eyre::Result<()> {
+        self.0.send(ClientEvent::Kick(reason, message))?;
+        Ok(())
+    }
+    /// Waits for the connection to be gone.
+    pub async fn closed(&self) {
+        self.0.closed().await
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientEvent {
+    Teleport {
+        x: AbsOrRel<f64>,
+        y: AbsOrRel<f64>,
+        z: AbsOrRel<f64>,
+    },
+    SystemMessage(String),
+    Packet(ResponseBuilder),
+    /// Many packets sent in a row, see [`Connection::send_batched`].
+    Batch(Vec<ResponseBuilder>),
+    Prepared(Arc<PreparedPacket>),
+    Kick(DisconnectReason, Chat),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ConnectionState {
     Handshake,
     Status,
     Login,
     Play,
 }
+impl ConnectionState {
+    /// Returns whether a connection can go from this state to `next`.
+    ///
+    /// The handshake decides between the status and login states, and only a
+    /// successful login leads to the play state.
+    pub fn can_transition_to(self, next: Self) -> bool {
+        use ConnectionState::*;
+        matches!(
+            (self, next),
+            (Handshake, Status) | (Handshake, Login) | (Login, Play)
+        )
+    }
+    /// Returns the largest packet a client can send in this state, in bytes,
+    /// not counting its length.
+    ///
+    /// Clients can only send large packets once they're logged in, which
+    /// bounds how much memory unauthenticated ones can make the server use.
+    pub fn max_packet_len(self) -> usize {
+        match self {
+            Self::Handshake | Self::Status => 2 * 1024,
+            // leaves room for login plugin responses, like player info
+            // forwarded by proxies
+            Self::Login => 8 * 1024,
+            // the largest length that fits in 3 bytes, like vanilla
+            Self::Play => (1 << 21) - 1,
+        }
+    }
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Handshake => "handshake",
+            Self::Status => "status",
+            Self::Login => "login",
+            Self::Play => "play",
+        }
+    }
+}
 
-#[derive(Debug)]
+/// Parses a packet sent by the client, given its ID and body.
+fn parse_packet(state: ConnectionState, data: &[u8]) -> IResult<&[u8], BoxedPacket<'_>> {
+    match state {
+        ConnectionState::Handshake => handshake::read_packet(data),
+        ConnectionState::Status => status::read_packet(data),
+        ConnectionState::Login => login::read_packet(data),
+        ConnectionState::Play => play::read_packet(data),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResponseBuilder {
     data: Vec<u8>,
 }
@@ -165,6 +742,37 @@ impl ResponseBuilder {
             data: varint::serialize_to_bytes(packet_id as u32),
         }
     }
+    /// Creates a builder with room for `capacity` bytes of packet ID and body
+    /// before it has to reallocate, for packets whose size is roughly known
+    /// upfront.
+    pub fn with_capacity(packet_id: u32, capacity: usize) -> Self {
+        let mut builder = Self {
+            data: Vec::with_capacity(capacity),
+        };
+        builder.varint(packet_id);
+        builder
+    }
+    /// Makes room for at least `additional` more bytes.
+    pub fn reserve(&mut self, additional: usize) -> &mut Self {
+        self.data.reserve(additional);
+        self
+    }
+    /// Returns the number of bytes the builder can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+    /// Creates a builder without a packet ID, used to build up a part of a
+    /// [streamed response](ResponseStream).
+    pub fn fragment() -> Self {
+        Self { data: vec![] }
+    }
+    /// Returns the number of bytes written so far, including the packet ID.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
     #[instrument(skip_all)]
     pub fn add<'builder, T: ToResponseField>(&'builder mut self, t: T) -> &'builder mut Self {
         t.to_request_field(self);
@@ -260,18 +868,89 @@ impl ResponseBuilder {
     }
 
     #[instrument(skip_all)]
-    pub async fn send(&mut self, conn: &mut Connection) -> eyre::Result<()> {
-        let mut header = varint::serialize_to_bytes(self.data.len() as u32);
-        let data = &mut self.data;
+    pub async fn send<S: PacketSink + ?Sized>(&mut self, sink: &mut S) -> eyre::Result<()> {
+        sink.send_packet(self).await
+    }
+}
+
+/// A response that is written out fragment by fragment, instead of being built
+/// up in memory all at once like with [`ResponseBuilder`].
+///
+/// Useful for very large packets (chunk data, recipes, etc.), where only one
+/// fragment has to be held in memory at a time. However, as the length prefix
+/// comes first, the length of the whole body has to be known upfront; callers
+/// usually compute it in a first pass over their data, and then build and write
+/// each fragment in a second one.
+///
+/// Streamed responses are never compressed, even when compression is on.
+pub struct ResponseStream<'conn, W> {
+    writer: &'conn mut W,
+    cipher: Option<&'conn mut AesCipher>,
+    remaining: usize,
+}
+
+impl<'conn, W: AsyncWrite + Unpin> ResponseStream<'conn, W> {
+    #[instrument(skip(writer, cipher))]
+    pub async fn new(
+        writer: &'conn mut W,
+        cipher: Option<&'conn mut AesCipher>,
+        compression: bool,
+        packet_id: u32,
+        body_len: usize,
+    ) -> eyre::Result<ResponseStream<'conn, W>> {
+        let mut stream = Self {
+            writer,
+            cipher,
+            remaining: 0,
+        };
+        let mut packet_id = varint::serialize_to_bytes(packet_id);
+        if compression {
+            // a data length of 0 marks the packet as uncompressed
+            packet_id.insert(0, 0);
+        }
+        let mut header = varint::serialize_to_bytes((packet_id.len() + body_len) as u32);
+        header.extend_from_slice(&packet_id);
         trace!(?header);
-        trace!("\n{}", data.to_hex(16));
 
-        if let Some(cipher) = &mut conn.encrypt_cipher {
-            cipher.encrypt(&mut header);
+        stream.write_raw(&mut header).await?;
+        stream.remaining = body_len;
+        Ok(stream)
+    }
+
+    /// Writes a [fragment](ResponseBuilder::fragment) of the body, and clears
+    /// it so that it can be reused for the next one.
+    pub async fn write(&mut self, fragment: &mut ResponseBuilder) -> eyre::Result<()> {
+        let len = fragment.len();
+        if len > self.remaining {
+            bail!(
+                "fragment is longer than the rest of the body ({} > {})",
+                len,
+                self.remaining
+            );
+        }
+        self.remaining -= len;
+        self.write_raw(&mut fragment.data).await?;
+        fragment.data.clear();
+        Ok(())
+    }
+
+    /// Finishes the response, checking that the whole body has been written.
+    pub async fn finish(self) -> eyre::Result<()> {
+        if self.remaining != 0 {
+            bail!(
+                "response ended with {} bytes of the body unwritten",
+                self.remaining
+            );
+        }
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    async fn write_raw(&mut self, data: &mut [u8]) -> eyre::Result<()> {
+        if let Some(cipher) = &mut self.cipher {
             cipher.encrypt(data);
         }
-        conn.socket.write(&header).await?;
-        conn.socket.write(data).await?;
+        self.writer.write_all(data).await?;
         Ok(())
     }
 }
@@ -319,3 +998,423 @@ pub trait TryToResponseField {
 
     fn try_to_request_field(&self, builder: &mut ResponseBuilder) -> Result<(), Self::Err>;
 }
+
+#[cfg(test)]
+impl Connection {
+    /// Creates a connection over an in-memory stream, returning the client's
+    /// end of it.
+    pub(crate) fn for_tests(server: ServerHook) -> (Self, tokio::io::DuplexStream) {
+        let (socket, client) = tokio::io::duplex(64 * 1024);
+        let conn = Self::new(
+            socket,
+            "127.0.0.1:25565".parse().unwrap(),
+            server,
+            Keys::new().unwrap(),
+            Arc::new(toml::from_str("").unwrap()),
+        );
+        (conn, client)
+    }
+
+    /// Creates a connection whose packets are captured instead of being sent,
+    /// returning the sink they end up in.
+    pub(crate) fn capturing(server: ServerHook) -> (Self, CapturingSink) {
+        let (mut conn, _) = Self::for_tests(server);
+        let sink = CapturingSink::default();
+        conn.sink = Some(Box::new(sink.clone()));
+        (conn, sink)
+    }
+}
+
+/// A [`PacketSink`] keeping every packet sent to it, to check what a handler
+/// sent.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CapturingSink(Arc<std::sync::Mutex<Vec<ResponseBuilder>>>);
+#[cfg(test)]
+impl CapturingSink {
+    /// Returns the packets sent so far.
+    pub(crate) fn packets(&self) -> Vec<ResponseBuilder> {
+        self.0.lock().unwrap().clone()
+    }
+}
+#[cfg(test)]
+#[async_trait]
+impl PacketSink for CapturingSink {
+    async fn send_packet(&mut self, packet: &ResponseBuilder) -> eyre::Result<()> {
+        self.0.lock().unwrap().push(packet.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl ResponseBuilder {
+    /// Returns the packet prefixed with its length, as sent over the wire.
+    pub(crate) fn to_frame(&self) -> Vec<u8> {
+        let mut frame = varint::serialize_to_bytes(self.data.len() as u32);
+        frame.extend_from_slice(&self.data);
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use nom::multi::length_data;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{
+        compression::PreparedPacket, Batcher, ClientEvent, Connection, ConnectionState, NetError,
+        ResponseBuilder, ResponseStream,
+    };
+    use crate::{
+        audit::DisconnectReason,
+        chat::Chat,
+        server::{Player, Server, ServerHook},
+        varint::varint,
+    };
+
+    #[tokio::test]
+    async fn test_streamed_response() {
+        const FRAGMENTS: usize = 64;
+        const FRAGMENT_LEN: usize = 8192;
+
+        let mut out = vec![];
+        let mut stream = ResponseStream::new(&mut out, None, false, 0x22, FRAGMENTS * FRAGMENT_LEN)
+            .await
+            .unwrap();
+        let mut fragment = ResponseBuilder::fragment();
+        for i in 0..FRAGMENTS {
+            fragment.raw_data([i as u8; FRAGMENT_LEN]);
+            stream.write(&mut fragment).await.unwrap();
+        }
+        stream.finish().await.unwrap();
+
+        let (rest, data) = length_data(varint::<u32>)(&out[..]).unwrap();
+        assert!(rest.is_empty());
+        let (body, id) = varint::<u32>(data).unwrap();
+        assert_eq!(id, 0x22);
+        assert_eq!(body.len(), FRAGMENTS * FRAGMENT_LEN);
+        for (i, chunk) in body.chunks(FRAGMENT_LEN).enumerate() {
+            assert!(chunk.iter().all(|&b| b == i as u8));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streamed_response_length_mismatch() {
+        let mut out = vec![];
+        let mut stream = ResponseStream::new(&mut out, None, false, 0, 4)
+            .await
+            .unwrap();
+        let mut fragment = ResponseBuilder::fragment();
+        fragment.add(0u64);
+        assert!(stream.write(&mut fragment).await.is_err());
+
+        let stream = ResponseStream::new(&mut out, None, false, 0, 4)
+            .await
+            .unwrap();
+        assert!(stream.finish().await.is_err());
+    }
+
+    #[test]
+    fn test_state_transitions() {
+        use ConnectionState::*;
+        assert!(Handshake.can_transition_to(Status));
+        assert!(Handshake.can_transition_to(Login));
+        assert!(Login.can_transition_to(Play));
+
+        assert!(!Handshake.can_transition_to(Play));
+        assert!(!Handshake.can_transition_to(Handshake));
+        assert!(!Status.can_transition_to(Login));
+        assert!(!Login.can_transition_to(Status));
+        assert!(!Play.can_transition_to(Login));
+        assert!(!Play.can_transition_to(Handshake));
+    }
+
+    #[tokio::test]
+    async fn test_kick() {
+        let (server, mut events) = ServerHook::stub();
+        let (mut conn, mut client) = Connection::for_tests(server);
+        conn.state = ConnectionState::Login;
+        let reason = Chat::text("Bye!");
+        conn.send_disconnect(DisconnectReason::LoginRefused, &reason)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        let mut expected = ResponseBuilder::new(0x00);
+        expected.add(&reason);
+        assert_eq!(received, expected.to_frame());
+
+        // the kick is recorded in the audit log
+        let event = format!("{:?}", events.recv().await.unwrap());
+        assert!(event.contains("Audit") && event.contains("Bye!"));
+        assert!(event.contains("category: LoginRefused"));
+    }
+
+    #[tokio::test]
+    async fn test_read_buffer_size() {
+        let (mut conn, mut client) = Connection::for_tests(Server::spawn_for_tests());
+        conn.config = Arc::new(toml::from_str("read_buffer_size = 3").unwrap());
+        assert_eq!(conn.read_buffer().len(), 3);
+
+        // packets longer than the buffer are put back together
+        let mut handshake = ResponseBuilder::new(0x00);
+        handshake
+            .varint(756u32)
+            .add("localhost")
+            .add(25565u16)
+            .varint(1u32);
+        let mut ping = ResponseBuilder::new(0x01);
+        ping.add(42u64);
+        let conn = tokio::spawn(conn.connection_loop());
+        client.write_all(&handshake.to_frame()).await.unwrap();
+        client.write_all(&ping.to_frame()).await.unwrap();
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        conn.await.unwrap().unwrap();
+        assert_eq!(received, ping.to_frame());
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let build = |builder: &mut ResponseBuilder| {
+            builder.add(1u64).add("hello").varint(300u32);
+        };
+        let mut expected = ResponseBuilder::new(0x26);
+        build(&mut expected);
+
+        let mut builder = ResponseBuilder::with_capacity(0x26, 64);
+        let capacity = builder.capacity();
+        assert!(capacity >= 64);
+        build(&mut builder);
+        // everything fit without reallocating
+        assert_eq!(builder.capacity(), capacity);
+        assert_eq!(builder, expected);
+
+        let mut builder = ResponseBuilder::new(0x26);
+        builder.reserve(1000);
+        assert!(builder.capacity() >= 1001);
+        build(&mut builder);
+        assert_eq!(builder, expected);
+    }
+
+    #[tokio::test]
+    async fn test_split_frame() {
+        let (conn, mut client) = Connection::for_tests(Server::spawn_for_tests());
+        let conn = tokio::spawn(conn.connection_loop());
+        let mut handshake = ResponseBuilder::new(0x00);
+        handshake
+            .varint(756u32)
+            .add("localhost")
+            .add(25565u16)
+            .varint(1u32);
+        let mut ping = ResponseBuilder::new(0x01);
+        ping.add(42u64);
+        let mut packets = handshake.to_frame();
+        packets.extend(ping.to_frame());
+
+        // the ping is cut in half, and only completed by a later read
+        let (first, second) = packets.split_at(packets.len() - 4);
+        client.write_all(first).await.unwrap();
+        // let the connection read the first half on its own
+        for _ in 0..10 {
+            let _ = tokio::task::yield_now().await;
+        }
+        client.write_all(second).await.unwrap();
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        conn.await.unwrap().unwrap();
+        assert_eq!(received, ping.to_frame());
+    }
+
+    #[tokio::test]
+    async fn test_send_batched() {
+        // yields after every 4 packets
+        let mut batcher = Batcher::new(4);
+        let yields: Vec<_> = (1..=10).filter(|_| batcher.sent()).collect();
+        assert_eq!(yields, [4, 8]);
+        assert!(Batcher::new(0).sent());
+
+        let (mut conn, mut client) = Connection::for_tests(Server::spawn_for_tests());
+        conn.config = Arc::new(toml::from_str("send_batch_size = 4").unwrap());
+        let packets: Vec<_> = (0..10u8)
+            .map(|i| {
+                let mut packet = ResponseBuilder::new(0x22);
+                packet.add(i);
+                packet
+            })
+            .collect();
+        let expected: Vec<_> = packets.iter().flat_map(|p| p.to_frame()).collect();
+        conn.handle_client_event(ClientEvent::Batch(packets))
+            .await
+            .unwrap();
+        drop(conn);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_on_error() {
+        let (server, mut events) = ServerHook::stub();
+        let (mut conn, mut client) = Connection::for_tests(server);
+        let uuid = uuid::Uuid::new_v4();
+        conn.player = Some(Player {
+            username: "player".into(),
+            uuid,
+        });
+        conn.state = ConnectionState::Play;
+        let conn = tokio::spawn(conn.connection_loop());
+
+        // there is no play packet with this ID
+        client
+            .write_all(&ResponseBuilder::new(0x7f).to_frame())
+            .await
+            .unwrap();
+        // the client gets kicked for it
+        conn.await.unwrap().unwrap();
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received[1], 0x1a);
+
+        // the server still hears about the player leaving
+        loop {
+            let event = format!("{:?}", events.recv().await.unwrap());
+            if event.contains("PlayerLeft") {
+                assert!(event.contains(&uuid.to_string()));
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_before_joining() {
+        let (server, mut events) = ServerHook::stub();
+        let (mut conn, _client) = Connection::for_tests(server);
+        // the login was accepted, but the player never made it into the game
+        let uuid = uuid::Uuid::new_v4();
+        conn.player = Some(Player {
+            username: "player".into(),
+            uuid,
+        });
+        conn.state = ConnectionState::Login;
+        drop(conn);
+
+        let event = format!("{:?}", events.recv().await.unwrap());
+        assert!(event.contains("PlayerLeft") && event.contains(&uuid.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_capturing_prepared() {
+        let (mut conn, sink) = Connection::capturing(ServerHook::stub().0);
+        let mut packet = ResponseBuilder::new(0x22);
+        packet.add(42u8);
+        let prepared = Arc::new(PreparedPacket::new(packet.clone()));
+        conn.handle_client_event(ClientEvent::Prepared(prepared))
+            .await
+            .unwrap();
+        assert_eq!(sink.packets(), [packet]);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (server, mut events) = ServerHook::stub();
+        let (mut conn, mut client) = Connection::for_tests(server);
+        conn.state = ConnectionState::Play;
+        conn.timeout = Duration::from_millis(50);
+
+        // the client never sends anything, so it gets disconnected
+        conn.connection_loop().await.unwrap();
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received[1], 0x1a);
+
+        // the audit log tells it apart from other kicks
+        let event = format!("{:?}", events.recv().await.unwrap());
+        assert!(event.contains("Audit") && event.contains("category: Timeout"));
+    }
+
+    #[tokio::test]
+    async fn test_client_closed() {
+        let (server, mut events) = ServerHook::stub();
+        let (mut conn, client) = Connection::for_tests(server);
+        conn.state = ConnectionState::Play;
+
+        // the client leaving isn't an error, but is still recorded
+        drop(client);
+        conn.connection_loop().await.unwrap();
+        let event = format!("{:?}", events.recv().await.unwrap());
+        assert!(event.contains("Audit") && event.contains("category: ClientClosed"));
+    }
+
+    #[tokio::test]
+    async fn test_max_packet_len() {
+        let (mut conn, _client) = Connection::for_tests(ServerHook::stub().0);
+        conn.state = ConnectionState::Login;
+        // only the length is needed to reject a packet
+        let mut login_start = ResponseBuilder::new(0x00);
+        login_start.add("a".repeat(16 * 1024).as_str());
+        let frame = login_start.to_frame();
+        assert!(matches!(
+            conn.read_packet(&frame[..16]).await,
+            Err(NetError::ProtocolViolation(_))
+        ));
+
+        // a plugin message, which can be large once logged in
+        conn.state = ConnectionState::Play;
+        let mut plugin_message = ResponseBuilder::new(0x0a);
+        plugin_message
+            .add("minecraft:brand")
+            .raw_data([0x22; 64 * 1024]);
+        let frame = plugin_message.to_frame();
+        assert!(conn.read_packet(&frame).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_truncated_packet() {
+        let (mut conn, mut client) = Connection::for_tests(ServerHook::stub().0);
+        conn.state = ConnectionState::Status;
+        // a ping whose payload is cut short, followed by a whole one
+        let mut truncated = ResponseBuilder::new(0x01);
+        truncated.add(42u32);
+        let mut ping = ResponseBuilder::new(0x01);
+        ping.add(42u64);
+        let mut frames = truncated.to_frame();
+        frames.extend(ping.to_frame());
+
+        assert!(conn.read_packet(&frames).await.unwrap().is_empty());
+        drop(conn);
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, ping.to_frame());
+    }
+
+    #[tokio::test]
+    async fn test_protocol_violation() {
+        let (mut conn, _client) = Connection::for_tests(ServerHook::stub().0);
+        conn.state = ConnectionState::Status;
+        // a handshake, which is only valid in the Handshake state
+        let mut handshake = ResponseBuilder::new(0x00);
+        handshake
+            .varint(756u32)
+            .add("localhost")
+            .add(25565u16)
+            .varint(2u32);
+        match conn.read_packet(&handshake.to_frame()).await {
+            Err(e @ NetError::ProtocolViolation(_)) => assert!(e.disconnect_reason().is_some()),
+            result => panic!("expected a protocol violation, got {:?}", result),
+        }
+
+        // errors keep their kind when they go through eyre
+        let e = eyre::Report::new(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+        assert!(matches!(NetError::from(e), NetError::IoError(_)));
+        let e = eyre::Report::new(NetError::Closed);
+        assert!(matches!(NetError::from(e), NetError::Closed));
+    }
+}
@@ -1,5 +1,6 @@
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
+use async_trait::async_trait;
 use rand::rngs::OsRng;
 use rsa::{PublicKeyParts, RsaPrivateKey};
 use serde::{Deserialize, Serialize};
@@ -8,7 +9,7 @@ use thiserror::Error;
 use tracing::trace;
 use uuid::Uuid;
 
-use crate::net::Connection;
+use crate::nom::fixed_bytes;
 
 #[derive(Clone)]
 pub struct Keys {
@@ -44,48 +45,106 @@ impl AuthSession {
             verify_token: rand::random(),
         }
     }
+
+    /// Checks that the client sent back the verify token it was given.
+    pub fn check_verify_token(&self, verify_token: &[u8]) -> Result<(), AuthenticationError> {
+        match fixed_bytes(verify_token) {
+            Ok((_, token)) if token == self.verify_token => Ok(()),
+            _ => Err(AuthenticationError::MismatchedVerifyToken),
+        }
+    }
 }
 
 pub const SERVER_ID: &[u8] = b"hiero|rejectnormalcy";
 
+/// The length of the shared secret, which is the AES key of the connection.
+pub const SHARED_SECRET_LEN: usize = 16;
+
+/// Checks that the shared secret sent by the client can be used as a key.
+pub fn check_shared_secret(shared_secret: &[u8]) -> Result<(), AuthenticationError> {
+    if shared_secret.len() == SHARED_SECRET_LEN {
+        Ok(())
+    } else {
+        Err(AuthenticationError::InvalidSharedSecretLength(
+            shared_secret.len(),
+        ))
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AuthenticationError {
     #[error("Not in auth session! This is a bug!")]
     NotInAuthSession,
     #[error("Mismatched verify token – client is either malicious or hilariously non-compliant!")]
     MismatchedVerifyToken,
+    #[error("Shared secret is {0} bytes long instead of 16")]
+    InvalidSharedSecretLength(usize),
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
 }
 
+/// Where players are checked to have joined the server they claim to.
+#[async_trait]
+pub trait SessionServer {
+    /// Asks whether `username` told the session server they were joining the
+    /// server with the given hash, optionally from a given IP.
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<AuthResponse, AuthenticationError>;
+}
+
+/// The session server of Mojang, which vanilla clients talk to.
+pub struct MojangSessionServer;
+
+#[async_trait]
+impl SessionServer for MojangSessionServer {
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+        ip: Option<IpAddr>,
+    ) -> Result<AuthResponse, AuthenticationError> {
+        let mut url = format!(
+            "https://sessionserver.mojang.com/session/minecraft/hasJoined?username={}&serverId={}",
+            username, server_hash
+        );
+        if let Some(ip) = ip {
+            url += &format!("&ip={}", ip);
+        }
+        trace!(?url);
+        Ok(reqwest::get(url).await?.json().await?)
+    }
+}
+
+/// Checks that the client of `auth_session` is who it claims to be, given
+/// the shared secret and verify token it sent back, once decrypted.
+///
+/// If `ip` is set, the session server also checks that the client joined
+/// from there.
 pub async fn authenticate(
-    conn: &mut Connection,
+    session_server: &(impl SessionServer + Sync),
+    auth_session: &AuthSession,
+    keys: &Keys,
+    ip: Option<IpAddr>,
     shared_secret: &[u8],
     verify_token: &[u8],
 ) -> Result<AuthResponse, AuthenticationError> {
-    let auth_session = conn
-        .auth_session
-        .as_ref()
-        .ok_or(AuthenticationError::NotInAuthSession)?;
-
-    if !verify_token.starts_with(&auth_session.verify_token) {
-        return Err(AuthenticationError::MismatchedVerifyToken);
-    }
+    auth_session.check_verify_token(verify_token)?;
 
     let mut sha1 = Sha1::new();
     sha1.update(SERVER_ID);
     sha1.update(shared_secret);
-    sha1.update(&conn.keys.pub_key_der);
+    sha1.update(&keys.pub_key_der);
     let hash = sha1.digest();
     let hash = minecraft_style_crappy_hash(&hash.bytes());
     trace!(?hash);
 
-    let url = format!(
-        "https://sessionserver.mojang.com/session/minecraft/hasJoined?username={}&serverId={}",
-        auth_session.username, hash
-    );
-    trace!(?url);
-    let auth_response: AuthResponse = reqwest::get(url).await?.json().await?;
+    let auth_response = session_server
+        .has_joined(&auth_session.username, &hash, ip)
+        .await?;
     trace!(?auth_response);
     Ok(auth_response)
 }
@@ -110,22 +169,88 @@ fn minecraft_style_crappy_hash(input: &[u8]) -> String {
         for i in input.iter_mut().rev() {
             *i = !*i;
             if carry {
+                // 0xff + 1 carries over to the next byte
                 carry = *i == 0xff;
-                *i += 1;
+                *i = i.wrapping_add(1);
             }
         }
 
-        format!("-{}", hex::encode(input).trim_start_matches('0'))
+        format!("-{}", trim_leading_zeros(&hex::encode(input)))
     } else {
-        hex::encode(input).trim_start_matches('0').to_string()
+        trim_leading_zeros(&hex::encode(input)).to_string()
+    }
+}
+
+/// Trims the leading zeros of a hex number, keeping the last one of zero.
+fn trim_leading_zeros(hex: &str) -> &str {
+    match hex.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use async_trait::async_trait;
     use sha1::Sha1;
+    use uuid::Uuid;
+
+    use super::{
+        authenticate, minecraft_style_crappy_hash, AuthResponse, AuthSession, AuthenticationError,
+        Keys, SessionServer,
+    };
+
+    /// A session server that only knows about a single player, who joined
+    /// from localhost.
+    struct MockSessionServer;
+
+    #[async_trait]
+    impl SessionServer for MockSessionServer {
+        async fn has_joined(
+            &self,
+            username: &str,
+            server_hash: &str,
+            ip: Option<IpAddr>,
+        ) -> Result<AuthResponse, AuthenticationError> {
+            assert_eq!(username, "player");
+            assert!(!server_hash.is_empty());
+            assert_eq!(ip, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+            Ok(AuthResponse {
+                id: Uuid::from_u128(1),
+                name: username.into(),
+            })
+        }
+    }
 
-    use super::minecraft_style_crappy_hash;
+    #[tokio::test]
+    async fn test_authenticate() {
+        let keys = Keys::new().unwrap();
+        let session = AuthSession::new("player".into());
+        let ip = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let secret = [0x42; 16];
+
+        let response = authenticate(
+            &MockSessionServer,
+            &session,
+            &keys,
+            ip,
+            &secret,
+            &session.verify_token,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.id, Uuid::from_u128(1));
+        assert_eq!(response.name, "player");
+
+        // the session server isn't even asked if the verify token is wrong
+        let token = session.verify_token.map(|b| !b);
+        assert!(matches!(
+            authenticate(&MockSessionServer, &session, &keys, ip, &secret, &token).await,
+            Err(AuthenticationError::MismatchedVerifyToken)
+        ));
+    }
 
     #[test]
     fn test_crappy_hash() {
@@ -136,6 +261,23 @@ mod tests {
         test(b"simon", "88e16a1019277b15d58faf0541e11910eb756f6");
     }
 
+    #[test]
+    fn test_crappy_hash_edge_cases() {
+        assert_eq!(minecraft_style_crappy_hash(&[0; 20]), "0");
+        let mut one = [0; 20];
+        one[19] = 1;
+        assert_eq!(minecraft_style_crappy_hash(&one), "1");
+        // -1, where the carry stops right away
+        assert_eq!(minecraft_style_crappy_hash(&[0xff; 20]), "-1");
+        // the smallest number, where the carry goes through every byte
+        let mut min = [0; 20];
+        min[0] = 0x80;
+        assert_eq!(
+            minecraft_style_crappy_hash(&min),
+            format!("-8{}", "0".repeat(39))
+        );
+    }
+
     fn test(input: &[u8], expected: &str) {
         let mut sha1 = Sha1::new();
         sha1.update(input);
@@ -0,0 +1,191 @@
+//! Recording of the raw packets exchanged with a client, for reproducing
+//! protocol bugs.
+//!
+//! Every packet is written as a single line holding a timestamp, whether it
+//! was sent by the client (`in`) or by the server (`out`), the state of the
+//! connection at the time, and the hex-encoded packet ID and body, e.g.
+//!
+//! ```text
+//! 2021-12-10T15:32:01.123456789Z in handshake 00f405093132372e302e302e3163dd02
+//! ```
+//!
+//! Packets are recorded before encryption and after decryption, so captures
+//! never contain ciphertext.
+//!
+//! Connections from the same address share a capture file, each appending to
+//! it; a new connection starts with its `handshake` packet.
+use std::{
+    io::{self, BufRead, Write},
+    time::SystemTime,
+};
+
+use nom::IResult;
+use thiserror::Error;
+
+use super::{BoxedPacket, ConnectionState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// Sent by the client.
+    Inbound,
+    /// Sent by the server.
+    Outbound,
+}
+impl FrameDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Inbound => "in",
+            Self::Outbound => "out",
+        }
+    }
+}
+
+/// A single captured packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub timestamp: SystemTime,
+    pub direction: FrameDirection,
+    pub state: ConnectionState,
+    /// The packet ID and body, without the length prefix.
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// Parses a line of a capture.
+    pub fn parse_line(line: &str) -> Result<Self, CaptureError> {
+        let invalid = || CaptureError::InvalidLine(line.to_string());
+        let mut fields = line.split_whitespace();
+        let mut next = || fields.next().ok_or_else(invalid);
+
+        let timestamp = humantime::parse_rfc3339(next()?).map_err(|_| invalid())?;
+        let direction = match next()? {
+            "in" => FrameDirection::Inbound,
+            "out" => FrameDirection::Outbound,
+            _ => return Err(invalid()),
+        };
+        let state = match next()? {
+            "handshake" => ConnectionState::Handshake,
+            "status" => ConnectionState::Status,
+            "login" => ConnectionState::Login,
+            "play" => ConnectionState::Play,
+            _ => return Err(invalid()),
+        };
+        let data = hex::decode(next()?).map_err(|_| invalid())?;
+        Ok(Self {
+            timestamp,
+            direction,
+            state,
+            data,
+        })
+    }
+
+    /// Feeds the frame back through the parser of its connection state, just
+    /// like [`Connection`](super::Connection) does for incoming packets.
+    ///
+    /// Only inbound frames can be parsed, as the server doesn't know how to
+    /// read the packets it sends.
+    pub fn parse(&self) -> IResult<&[u8], BoxedPacket<'_>> {
+        super::parse_packet(self.state, &self.data)
+    }
+}
+
+/// Writes captured frames to a sink, usually a buffered file per address.
+///
+/// The sink isn't flushed after every frame, so a buffered one only gets
+/// written to once its buffer fills up or it is dropped.
+pub struct Capture<W: Write> {
+    sink: W,
+}
+
+impl<W: Write> Capture<W> {
+    pub fn new(sink: W) -> Self {
+        Self { sink }
+    }
+
+    pub fn record(
+        &mut self,
+        direction: FrameDirection,
+        state: ConnectionState,
+        data: &[u8],
+    ) -> io::Result<()> {
+        writeln!(
+            self.sink,
+            "{} {} {} {}",
+            humantime::format_rfc3339_nanos(SystemTime::now()),
+            direction.as_str(),
+            state.as_str(),
+            hex::encode(data)
+        )
+    }
+}
+
+/// Reads back every frame of a capture.
+pub fn read_capture<R: BufRead>(reader: R) -> Result<Vec<Frame>, CaptureError> {
+    let mut frames = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            frames.push(Frame::parse_line(&line)?);
+        }
+    }
+    Ok(frames)
+}
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Invalid capture line: {0}")]
+    InvalidLine(String),
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_capture, Capture, Frame, FrameDirection};
+    use crate::net::{parse_packet, ConnectionState, ResponseBuilder};
+
+    #[test]
+    fn test_replay() {
+        // a handshake to localhost:25565, switching to the login state
+        let mut handshake = ResponseBuilder::new(0x00);
+        handshake
+            .varint(756u32)
+            .add("localhost")
+            .add(25565u16)
+            .varint(2u32);
+        let mut capture = Capture::new(vec![]);
+        capture
+            .record(
+                FrameDirection::Inbound,
+                ConnectionState::Handshake,
+                &handshake.data,
+            )
+            .unwrap();
+        capture
+            .record(
+                FrameDirection::Outbound,
+                ConnectionState::Login,
+                &[0x03, 0x80, 0x02],
+            )
+            .unwrap();
+
+        let frames = read_capture(capture.sink.as_slice()).unwrap();
+        assert_eq!(frames.len(), 2);
+        let (inbound, outbound) = (&frames[0], &frames[1]);
+        assert_eq!(inbound.direction, FrameDirection::Inbound);
+        assert_eq!(inbound.state, ConnectionState::Handshake);
+        assert_eq!(outbound.direction, FrameDirection::Outbound);
+        assert_eq!(outbound.data, [0x03, 0x80, 0x02]);
+
+        // replaying gives back the same packet as parsing the original bytes
+        let (rem, original) = parse_packet(ConnectionState::Handshake, &handshake.data).unwrap();
+        assert!(rem.is_empty());
+        let (rem, replayed) = inbound.parse().unwrap();
+        assert!(rem.is_empty());
+        assert_eq!(format!("{:?}", replayed), format!("{:?}", original));
+
+        assert!(Frame::parse_line("yesterday in play 00").is_err());
+        assert!(Frame::parse_line("2021-12-10T15:32:01Z sideways play 00").is_err());
+        assert!(Frame::parse_line("2021-12-10T15:32:01Z in play zz").is_err());
+    }
+}
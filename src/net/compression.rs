@@ -0,0 +1,224 @@
+//! Packet compression, which is turned on by sending Set Compression during
+//! login.
+//!
+//! Once it is on, the length of every packet is followed by the length of its
+//! uncompressed ID and body. Packets at least as long as the threshold are
+//! compressed with zlib, while shorter ones are sent as-is, with a data length
+//! of 0.
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use once_cell::sync::OnceCell;
+use thiserror::Error;
+
+use super::ResponseBuilder;
+use crate::varint::{self, varint};
+
+/// Builds the frame of a packet as sent over the wire, prefixed with its
+/// length, and compressed if compression is on and the packet is long enough.
+pub fn frame(data: &[u8], threshold: Option<usize>) -> io::Result<Vec<u8>> {
+    let body = match threshold {
+        None => return Ok(prefix_length(data.len(), data)),
+        Some(threshold) if data.len() < threshold => {
+            let mut body = Vec::with_capacity(data.len() + 1);
+            body.push(0); // not compressed
+            body.extend_from_slice(data);
+            body
+        }
+        Some(_) => {
+            let mut body = varint::serialize_to_bytes(data.len() as u32);
+            let mut encoder = ZlibEncoder::new(body, Compression::default());
+            encoder.write_all(data)?;
+            body = encoder.finish()?;
+            body
+        }
+    };
+    Ok(prefix_length(body.len(), &body))
+}
+
+fn prefix_length(len: usize, data: &[u8]) -> Vec<u8> {
+    let mut frame = varint::serialize_to_bytes(len as u32);
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// Returns the ID and body of a packet received while compression is on, from
+/// the data following its length.
+///
+/// The length the packet claims to decompress to is checked against `max_len`
+/// before anything is allocated for it.
+pub fn decompress(
+    data: &[u8],
+    threshold: usize,
+    max_len: usize,
+) -> Result<Cow<'_, [u8]>, CompressionError> {
+    let (data, data_len) = varint::<u32>(data).map_err(|_| CompressionError::InvalidDataLength)?;
+    if data_len == 0 {
+        return Ok(Cow::Borrowed(data));
+    }
+    let data_len = data_len as usize;
+    if data_len < threshold {
+        return Err(CompressionError::BelowThreshold(data_len, threshold));
+    }
+    if data_len > max_len {
+        return Err(CompressionError::TooLarge(data_len, max_len));
+    }
+    let mut decompressed = Vec::with_capacity(data_len);
+    ZlibDecoder::new(data)
+        .take(data_len as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() != data_len {
+        return Err(CompressionError::WrongDataLength(
+            decompressed.len(),
+            data_len,
+        ));
+    }
+    Ok(Cow::Owned(decompressed))
+}
+
+/// A packet that is sent to several connections at once, like a broadcast.
+///
+/// Building its frame, which includes compressing it, is only done once for all
+/// the connections, each of which then only has to encrypt its own copy.
+#[derive(Debug)]
+pub struct PreparedPacket {
+    data: Vec<u8>,
+    frame: OnceCell<(Option<usize>, Vec<u8>)>,
+}
+
+impl PreparedPacket {
+    pub fn new(packet: ResponseBuilder) -> Self {
+        Self {
+            data: packet.data,
+            frame: OnceCell::new(),
+        }
+    }
+
+    /// Returns the packet ID and body.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the frame of the packet for a connection with the given
+    /// compression threshold.
+    ///
+    /// The threshold is the same for every connection that got to the Play
+    /// state, so the frame is only built for the first one to ask for it.
+    pub fn frame(&self, threshold: Option<usize>) -> io::Result<Cow<'_, [u8]>> {
+        let (prepared_for, prepared) = self
+            .frame
+            .get_or_try_init(|| Ok::<_, io::Error>((threshold, frame(&self.data, threshold)?)))?;
+        if *prepared_for == threshold {
+            Ok(Cow::Borrowed(prepared))
+        } else {
+            Ok(Cow::Owned(frame(&self.data, threshold)?))
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CompressionError {
+    #[error("Invalid data length")]
+    InvalidDataLength,
+    #[error("Packet of {0} bytes was compressed, below the threshold of {1} bytes")]
+    BelowThreshold(usize, usize),
+    #[error("Packet of {0} bytes is too large, the limit is {1} bytes")]
+    TooLarge(usize, usize),
+    #[error("Packet decompressed to {0} bytes instead of {1}")]
+    WrongDataLength(usize, usize),
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use tokio::io::AsyncReadExt;
+
+    use super::{decompress, frame, CompressionError, PreparedPacket};
+    use crate::{
+        net::{ClientEvent, Connection, ResponseBuilder},
+        server::ServerHook,
+        varint::{self, varint},
+    };
+
+    #[test]
+    fn test_compression() {
+        // short packets are sent uncompressed
+        let short = [0x0f, 1, 2, 3];
+        assert_eq!(frame(&short, Some(256)).unwrap(), [5, 0, 0x0f, 1, 2, 3]);
+        assert_eq!(frame(&short, None).unwrap(), [4, 0x0f, 1, 2, 3]);
+        assert!(matches!(
+            decompress(&[0, 0x0f, 1, 2, 3], 256, 1024).unwrap(),
+            Cow::Borrowed([0x0f, 1, 2, 3])
+        ));
+
+        // long ones are compressed, and come back the same
+        let long = [0x22; 1024];
+        let framed = frame(&long, Some(256)).unwrap();
+        let (body, len) = varint::<u32>(&framed).unwrap();
+        assert_eq!(len as usize, body.len());
+        assert!(body.len() < long.len());
+        assert_eq!(decompress(body, 256, 1024).unwrap(), &long[..]);
+
+        // ...but only if they're long enough to need it
+        let framed = frame(&short, Some(1)).unwrap();
+        assert!(decompress(&framed[1..], 256, 1024).is_err());
+    }
+
+    #[test]
+    fn test_decompress_too_large() {
+        // claims to decompress to 2 GiB, without the data to back it up
+        let mut data = varint::serialize_to_bytes(1u32 << 31);
+        data.extend_from_slice(&[0x78, 0x9c]);
+        assert!(matches!(
+            decompress(&data, 256, (1 << 21) - 1),
+            Err(CompressionError::TooLarge(len, _)) if len == 1 << 31
+        ));
+
+        // the limit is inclusive
+        let long = [0x22; 1024];
+        let framed = frame(&long, Some(256)).unwrap();
+        let (body, _) = varint::<u32>(&framed).unwrap();
+        assert!(decompress(body, 256, 1024).is_ok());
+        assert!(matches!(
+            decompress(body, 256, 1023),
+            Err(CompressionError::TooLarge(1024, 1023))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_prepared_packet() {
+        let mut chunk = ResponseBuilder::new(0x22);
+        chunk.raw_data([0x22; 1024]);
+        let expected = frame(&chunk.data, Some(256)).unwrap();
+        let packet = Arc::new(PreparedPacket::new(chunk));
+
+        for _ in 0..4 {
+            let (mut conn, mut client) = Connection::for_tests(ServerHook::stub().0);
+            conn.compression_threshold = Some(256);
+            conn.handle_client_event(ClientEvent::Prepared(packet.clone()))
+                .await
+                .unwrap();
+            drop(conn);
+
+            let mut received = vec![];
+            client.read_to_end(&mut received).await.unwrap();
+            assert_eq!(received, expected);
+        }
+
+        // every connection got the frame compressed for the first one
+        let (threshold, prepared) = packet.frame.get().unwrap();
+        assert_eq!(*threshold, Some(256));
+        assert!(matches!(
+            packet.frame(Some(256)).unwrap(),
+            Cow::Borrowed(frame) if frame.as_ptr() == prepared.as_ptr()
+        ));
+        // connections without compression get their own frame
+        assert_eq!(packet.frame(None).unwrap()[2..], [0x22; 1025]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Parsers and serializers for the ways the protocol encodes coordinates.
+//!
+//! - Block positions are packed into a single long, see [`Position`].
+//! - Exact positions are three doubles.
+//! - Fixed-point positions are three ints, in 1/32 of a block.
+//! - Deltas, as sent by Entity Position, are three shorts, in 1/4096 of a
+//!   block, so an entity can only move by less than 8 blocks at a time.
+//! - Velocities are three shorts, in 1/8000 of a block per tick.
+use nom::{
+    combinator::map,
+    number::streaming::{be_f64, be_i16, be_i32},
+    sequence::tuple,
+    IResult,
+};
+use nom_derive::Parse;
+
+use super::ResponseBuilder;
+use crate::{
+    data::Position,
+    math::{BlockPos, Vec3},
+};
+
+const FIXED_POINT_SCALE: f64 = 32.0;
+const DELTA_SCALE: f64 = 4096.0;
+const VELOCITY_SCALE: f64 = 8000.0;
+/// Clients can't handle more than 3.9 blocks per tick.
+const MAX_VELOCITY: f64 = 3.9;
+
+fn unscale((x, y, z): (f64, f64, f64), scale: f64) -> Vec3 {
+    Vec3::new(x / scale, y / scale, z / scale)
+}
+
+pub fn block_pos(i: &[u8]) -> IResult<&[u8], BlockPos> {
+    map(Position::parse, BlockPos::from)(i)
+}
+
+pub fn add_block_pos(builder: &mut ResponseBuilder, pos: BlockPos) {
+    builder.add(Position::from(pos));
+}
+
+pub fn vec3(i: &[u8]) -> IResult<&[u8], Vec3> {
+    map(tuple((be_f64, be_f64, be_f64)), |(x, y, z)| {
+        Vec3::new(x, y, z)
+    })(i)
+}
+
+pub fn add_vec3(builder: &mut ResponseBuilder, v: Vec3) {
+    builder.add(v.x).add(v.y).add(v.z);
+}
+
+pub fn fixed_point(i: &[u8]) -> IResult<&[u8], Vec3> {
+    map(tuple((be_i32, be_i32, be_i32)), |(x, y, z)| {
+        unscale((x.into(), y.into(), z.into()), FIXED_POINT_SCALE)
+    })(i)
+}
+
+pub fn add_fixed_point(builder: &mut ResponseBuilder, v: Vec3) {
+    let to_int = |v: f64| (v * FIXED_POINT_SCALE) as i32;
+    builder.add(to_int(v.x)).add(to_int(v.y)).add(to_int(v.z));
+}
+
+pub fn delta(i: &[u8]) -> IResult<&[u8], Vec3> {
+    map(tuple((be_i16, be_i16, be_i16)), |(x, y, z)| {
+        unscale((x.into(), y.into(), z.into()), DELTA_SCALE)
+    })(i)
+}
+
+/// Whether an entity can be moved by `delta` with a delta, instead of having to
+/// be teleported.
+pub fn fits_delta(delta: Vec3) -> bool {
+    let fits = |v: f64| (v * DELTA_SCALE).abs() <= i16::MAX as f64;
+    fits(delta.x) && fits(delta.y) && fits(delta.z)
+}
+
+/// Adds a delta, which has to [fit](fits_delta).
+pub fn add_delta(builder: &mut ResponseBuilder, delta: Vec3) {
+    debug_assert!(fits_delta(delta), "delta too long: {:?}", delta);
+    let to_short = |v: f64| (v * DELTA_SCALE) as i16;
+    builder
+        .add(to_short(delta.x))
+        .add(to_short(delta.y))
+        .add(to_short(delta.z));
+}
+
+pub fn velocity(i: &[u8]) -> IResult<&[u8], Vec3> {
+    map(tuple((be_i16, be_i16, be_i16)), |(x, y, z)| {
+        unscale((x.into(), y.into(), z.into()), VELOCITY_SCALE)
+    })(i)
+}
+
+/// Adds a velocity in blocks per tick, clamped to what clients can handle.
+pub fn add_velocity(builder: &mut ResponseBuilder, velocity: Vec3) {
+    let to_short = |v: f64| (v.clamp(-MAX_VELOCITY, MAX_VELOCITY) * VELOCITY_SCALE) as i16;
+    builder
+        .add(to_short(velocity.x))
+        .add(to_short(velocity.y))
+        .add(to_short(velocity.z));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_block_pos, add_delta, add_fixed_point, add_vec3, add_velocity, block_pos, delta,
+        fits_delta, fixed_point, vec3, velocity,
+    };
+    use crate::{
+        math::{BlockPos, Vec3},
+        net::ResponseBuilder,
+    };
+
+    fn written(add: impl FnOnce(&mut ResponseBuilder)) -> Vec<u8> {
+        let mut builder = ResponseBuilder::new(0x00);
+        add(&mut builder);
+        builder.data[1..].to_vec()
+    }
+
+    #[test]
+    fn test_block_pos() {
+        for pos in [
+            BlockPos::new(0, 0, 0),
+            BlockPos::new(18357644, 831, -20882616),
+            BlockPos::new(-1, -64, 1),
+        ] {
+            let data = written(|b| add_block_pos(b, pos));
+            assert_eq!(data.len(), 8);
+            assert_eq!(block_pos(&data).unwrap(), (&[][..], pos));
+        }
+        let data = written(|b| add_block_pos(b, BlockPos::new(18357644, 831, -20882616)));
+        assert_eq!(data, [0x46, 0x07, 0x63, 0x2c, 0x15, 0xb4, 0x83, 0x3f]);
+    }
+
+    #[test]
+    fn test_vec3() {
+        let v = Vec3::new(0.1, -64.0, 1e7);
+        let data = written(|b| add_vec3(b, v));
+        assert_eq!(data.len(), 24);
+        assert_eq!(vec3(&data).unwrap(), (&[][..], v));
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        let v = Vec3::new(1.5, -64.03125, 100.0);
+        let data = written(|b| add_fixed_point(b, v));
+        assert_eq!(&data[..4], 48i32.to_be_bytes());
+        assert_eq!(fixed_point(&data).unwrap(), (&[][..], v));
+        // anything finer than 1/32 of a block is lost
+        let data = written(|b| add_fixed_point(b, Vec3::new(0.01, 0.0, 0.0)));
+        assert_eq!(fixed_point(&data).unwrap().1, Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_delta() {
+        let d = Vec3::new(0.5, -7.75, 0.000244140625);
+        assert!(fits_delta(d));
+        let data = written(|b| add_delta(b, d));
+        assert_eq!(data, [0x08, 0x00, 0x84, 0x00, 0x00, 0x01]);
+        assert_eq!(delta(&data).unwrap(), (&[][..], d));
+
+        assert!(fits_delta(Vec3::new(-7.9, 0.0, 7.9)));
+        assert!(!fits_delta(Vec3::new(0.0, 8.0, 0.0)));
+        assert!(!fits_delta(Vec3::new(-8.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_velocity() {
+        let v = Vec3::new(0.25, -1.0, 0.0);
+        let data = written(|b| add_velocity(b, v));
+        assert_eq!(data, [0x07, 0xd0, 0xe0, 0xc0, 0x00, 0x00]);
+        assert_eq!(velocity(&data).unwrap(), (&[][..], v));
+        // too fast for clients to handle
+        let data = written(|b| add_velocity(b, Vec3::new(10.0, -10.0, 0.0)));
+        assert_eq!(data[..4], [0x79, 0xe0, 0x86, 0x20]);
+    }
+}
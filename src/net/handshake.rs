@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use nom::IResult;
 use nom_derive::Nom;
-use tracing::{debug, instrument};
+use tracing::instrument;
 
 use crate::{
     match_id_and_forward,
@@ -9,7 +9,7 @@ use crate::{
     varint::varint,
 };
 
-use super::{BoxedPacket, Connection, ConnectionState, Packet};
+use super::{BoxedPacket, Connection, ConnectionState, NetResult, Packet};
 
 pub fn read_packet(input: &[u8]) -> IResult<&[u8], BoxedPacket<'_>> {
     match_id_and_forward! {
@@ -32,9 +32,7 @@ struct Handshake<'a> {
 #[async_trait]
 impl Packet for Handshake<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
-        debug!(current = ?conn.state, next = ?self.next_state, "handshake - advancing to next state");
-        conn.state = self.next_state;
-        Ok(())
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        conn.transition_to(self.next_state)
     }
 }
@@ -1,27 +1,44 @@
 use aes::cipher::NewCipher;
-use nom::IResult;
+use nom::{combinator::rest, IResult};
 use nom_derive::Nom;
 use tracing::{debug, instrument, trace};
 use uuid::Uuid;
 
 use crate::{
+    audit::DisconnectReason,
+    chat::Chat,
+    data::Identifier,
     match_id_and_forward,
     net::{
-        auth::{AuthSession, SERVER_ID},
+        auth::{AuthSession, AuthenticationError, MojangSessionServer, SERVER_ID},
         AesCipher,
     },
-    nom::{var_bytes, var_str_with_max_length},
+    nom::{boolean, var_bytes, var_str_with_max_length},
     server::Player,
+    varint::varint,
 };
 
-use super::{auth, BoxedPacket, Connection, ConnectionState, Packet, ResponseBuilder};
+use super::{
+    auth, BoxedPacket, Connection, ConnectionState, NetError, NetResult, Packet, ResponseBuilder,
+};
 use async_trait::async_trait;
 
+/// Packet IDs of the Login state, as of 1.17.1.
+pub const LOGIN_SB_LOGIN_START: u32 = 0x00;
+pub const LOGIN_SB_ENCRYPTION_RESPONSE: u32 = 0x01;
+pub const LOGIN_SB_LOGIN_PLUGIN_RESPONSE: u32 = 0x02;
+pub const LOGIN_CB_DISCONNECT: u32 = 0x00;
+pub const LOGIN_CB_ENCRYPTION_REQUEST: u32 = 0x01;
+pub const LOGIN_CB_SUCCESS: u32 = 0x02;
+pub const LOGIN_CB_SET_COMPRESSION: u32 = 0x03;
+pub const LOGIN_CB_LOGIN_PLUGIN_REQUEST: u32 = 0x04;
+
 pub fn read_packet(input: &[u8]) -> IResult<&[u8], BoxedPacket<'_>> {
     match_id_and_forward! {
         input;
-        0 => LoginStart,
-        1 => EncryptionResponse
+        LOGIN_SB_LOGIN_START => LoginStart,
+        LOGIN_SB_ENCRYPTION_RESPONSE => EncryptionResponse,
+        LOGIN_SB_LOGIN_PLUGIN_RESPONSE => LoginPluginResponse
     }
 }
 
@@ -33,8 +50,17 @@ struct LoginStart<'a> {
 #[async_trait]
 impl Packet for LoginStart<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
-        if conn.config.online_mode {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        if !is_valid_username(self.username) {
+            debug!("Refusing login with an invalid username");
+            conn.send_disconnect(
+                DisconnectReason::ProtocolViolation,
+                &Chat::text("Invalid username"),
+            )
+            .await?;
+            return Ok(());
+        }
+        if conn.config.encryption() {
             let auth_session = conn
                 .auth_session
                 .insert(AuthSession::new(self.username.into()));
@@ -42,18 +68,15 @@ impl Packet for LoginStart<'_> {
             let verify_token = &auth_session.verify_token;
             trace!(?auth_session, ?pub_key, ?verify_token);
 
-            ResponseBuilder::new(1)
+            ResponseBuilder::new(LOGIN_CB_ENCRYPTION_REQUEST)
                 .var_data(SERVER_ID)
                 .var_data(pub_key)
                 .var_data(verify_token)
                 .send(conn)
                 .await?;
         } else {
-            let player = Player {
-                uuid: Uuid::new_v4(),
-                username: self.username.to_string(),
-            };
-            conn.login_success(player, None, None).await?;
+            conn.login_success(offline_player(self.username), None, None)
+                .await?;
         }
 
         Ok(())
@@ -70,32 +93,133 @@ struct EncryptionResponse<'a> {
 #[async_trait]
 impl Packet for EncryptionResponse<'_> {
     #[instrument(skip(self, conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
-        let shared_secret = conn
-            .keys
-            .priv_key
-            .decrypt(rsa::PaddingScheme::PKCS1v15Encrypt, self.shared_secret)?;
-        let verify_token = conn
-            .keys
-            .priv_key
-            .decrypt(rsa::PaddingScheme::PKCS1v15Encrypt, self.verify_token)?;
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        let decrypt = |data| {
+            conn.keys
+                .priv_key
+                .decrypt(rsa::PaddingScheme::PKCS1v15Encrypt, data)
+                .map_err(|e| NetError::ProtocolViolation(format!("Failed to decrypt: {}", e)))
+        };
+        let shared_secret = decrypt(self.shared_secret)?;
+        let verify_token = decrypt(self.verify_token)?;
         trace!(?shared_secret, ?verify_token);
 
-        let auth_response = auth::authenticate(conn, &shared_secret, &verify_token).await?;
+        auth::check_shared_secret(&shared_secret)?;
+        let auth_session = conn
+            .auth_session
+            .as_ref()
+            .ok_or(AuthenticationError::NotInAuthSession)?;
+        let player = if conn.config.online_mode {
+            let ip = conn
+                .config
+                .prevent_proxy_connections
+                .then(|| conn.addr.ip());
+            let auth_response = auth::authenticate(
+                &MojangSessionServer,
+                auth_session,
+                &conn.keys,
+                ip,
+                &shared_secret,
+                &verify_token,
+            )
+            .await?;
 
-        // Success! 🎉
-        let player = Player {
-            username: auth_response.name,
-            uuid: auth_response.id,
+            // Success! 🎉
+            Player {
+                username: auth_response.name,
+                uuid: auth_response.id,
+            }
+        } else {
+            // only encrypted, not authenticated
+            auth_session.check_verify_token(&verify_token)?;
+            offline_player(&auth_session.username)
+        };
+        let cipher = || {
+            AesCipher::new_from_slices(&shared_secret, &shared_secret)
+                .expect("the length of the shared secret was checked")
         };
-        let encrypt_cipher = AesCipher::new_from_slices(&shared_secret, &shared_secret)?;
-        let decrypt_cipher = AesCipher::new_from_slices(&shared_secret, &shared_secret)?;
+        let (encrypt_cipher, decrypt_cipher) = (cipher(), cipher());
         conn.login_success(player, Some(encrypt_cipher), Some(decrypt_cipher)).await?;
         Ok(())
     }
 }
 
+#[derive(Debug, Nom)]
+struct LoginPluginResponse<'a> {
+    #[nom(Parse = "varint")]
+    message_id: u32,
+    #[nom(Parse = "boolean")]
+    successful: bool,
+    #[nom(Parse = "rest")]
+    data: &'a [u8],
+}
+#[async_trait]
+impl Packet for LoginPluginResponse<'_> {
+    #[instrument(skip(conn))]
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        let channel = conn.login_plugins.respond(self.message_id).ok_or_else(|| {
+            NetError::ProtocolViolation(format!(
+                "Unexpected login plugin response {}",
+                self.message_id
+            ))
+        })?;
+        // clients that don't understand the channel don't send any data
+        let data = self.successful.then(|| self.data);
+        conn.login_plugin_response(&channel, data).await
+    }
+}
+
+/// Checks that a username is what the vanilla server allows, which is from 1
+/// to 16 letters, digits and underscores.
+///
+/// Offline mode takes the username of the client at its word, and odd ones
+/// make for confusing logs and commands.
+pub fn is_valid_username(username: &str) -> bool {
+    (1..=16).contains(&username.len())
+        && username
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// Returns the player logging in with `username` in offline mode, whose UUID
+/// is made up.
+fn offline_player(username: &str) -> Player {
+    Player {
+        uuid: Uuid::new_v4(),
+        username: username.to_string(),
+    }
+}
+
 impl Connection {
+    /// Sends a login plugin request over `channel`, returning its message ID.
+    /// The answer is handed to [`Connection::login_plugin_response`].
+    #[instrument(skip(self, data))]
+    pub async fn send_login_plugin_request(
+        &mut self,
+        channel: Identifier,
+        data: &[u8],
+    ) -> NetResult<u32> {
+        let message_id = self.login_plugins.request(channel.clone());
+        ResponseBuilder::new(LOGIN_CB_LOGIN_PLUGIN_REQUEST)
+            .varint(message_id)
+            .add(channel.to_string().as_str())
+            .raw_data(data)
+            .send(self)
+            .await?;
+        Ok(message_id)
+    }
+
+    /// Handles the answer to a login plugin request, with its data if the
+    /// client understood the request.
+    async fn login_plugin_response(
+        &mut self,
+        channel: &Identifier,
+        data: Option<&[u8]>,
+    ) -> NetResult<()> {
+        debug!(%channel, ?data, "Ignoring login plugin response");
+        Ok(())
+    }
+
     #[instrument(skip(self, encrypt_cipher, decrypt_cipher))]
     async fn login_success(
         &mut self,
@@ -103,13 +227,29 @@ impl Connection {
         encrypt_cipher: Option<AesCipher>,
         decrypt_cipher: Option<AesCipher>,
     ) -> eyre::Result<()> {
+        if let Err(e) = self.server.check_login(player.clone()).await? {
+            debug!(%e, "Refusing login");
+            self.send_disconnect(DisconnectReason::LoginRefused, &Chat::text(e.to_string()))
+                .await?;
+            return Ok(());
+        }
+        // from now on, the server has to hear about the player leaving, even
+        // before they join the game
+        self.player = Some(player.clone());
+
         debug!("Login successful: transitioning into Play state");
         self.encrypt_cipher = encrypt_cipher;
         self.decrypt_cipher = decrypt_cipher;
-        self.state = ConnectionState::Play;
+        if let Ok(threshold) = usize::try_from(self.config.network_compression_threshold) {
+            ResponseBuilder::new(LOGIN_CB_SET_COMPRESSION)
+                .varint(threshold as u32)
+                .send(self)
+                .await?;
+            self.compression_threshold = Some(threshold);
+        }
+        self.transition_to(ConnectionState::Play)?;
 
-        // Login success
-        ResponseBuilder::new(2)
+        ResponseBuilder::new(LOGIN_CB_SUCCESS)
             .add(player.uuid)
             .add(&player.username)
             .send(self)
@@ -119,3 +259,182 @@ impl Connection {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::rngs::OsRng;
+    use rsa::{PaddingScheme, PublicKey};
+    use tokio::io::{AsyncReadExt, DuplexStream};
+
+    use super::{
+        is_valid_username, LOGIN_CB_DISCONNECT, LOGIN_CB_ENCRYPTION_REQUEST,
+        LOGIN_CB_LOGIN_PLUGIN_REQUEST, LOGIN_CB_SET_COMPRESSION, LOGIN_CB_SUCCESS,
+        LOGIN_SB_ENCRYPTION_RESPONSE, LOGIN_SB_LOGIN_PLUGIN_RESPONSE, LOGIN_SB_LOGIN_START,
+    };
+    use crate::{
+        data::Identifier,
+        net::{auth::AuthenticationError, Connection, ConnectionState, NetError, ResponseBuilder},
+        server::{Server, ServerHook},
+        varint::varint,
+    };
+
+    #[test]
+    fn test_packet_ids() {
+        // https://wiki.vg/index.php?title=Protocol&oldid=16918#Login
+        assert_eq!(LOGIN_CB_DISCONNECT, 0x00);
+        assert_eq!(LOGIN_CB_ENCRYPTION_REQUEST, 0x01);
+        assert_eq!(LOGIN_CB_SUCCESS, 0x02);
+        assert_eq!(LOGIN_CB_SET_COMPRESSION, 0x03);
+        assert_eq!(LOGIN_CB_LOGIN_PLUGIN_REQUEST, 0x04);
+        assert_eq!(LOGIN_SB_LOGIN_START, 0x00);
+        assert_eq!(LOGIN_SB_ENCRYPTION_RESPONSE, 0x01);
+        assert_eq!(LOGIN_SB_LOGIN_PLUGIN_RESPONSE, 0x02);
+    }
+
+    #[tokio::test]
+    async fn test_login_plugin_request() {
+        let (mut conn, mut client) = Connection::for_tests(ServerHook::stub().0);
+        conn.state = ConnectionState::Login;
+        let channel = Identifier::try_from("velocity:player_info").unwrap();
+        let message_id = conn.send_login_plugin_request(channel, &[1]).await.unwrap();
+
+        let mut expected = ResponseBuilder::new(LOGIN_CB_LOGIN_PLUGIN_REQUEST);
+        expected
+            .varint(message_id)
+            .add("velocity:player_info")
+            .raw_data([1]);
+        let mut received = vec![0; expected.to_frame().len()];
+        client.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, expected.to_frame());
+
+        let response = |message_id: u32| {
+            let mut response = ResponseBuilder::new(LOGIN_SB_LOGIN_PLUGIN_RESPONSE);
+            response.varint(message_id).add(false);
+            response.to_frame()
+        };
+        // a response to a request that was never sent
+        assert!(matches!(
+            conn.read_packet(&response(message_id + 1)).await,
+            Err(NetError::ProtocolViolation(_))
+        ));
+        assert!(conn.read_packet(&response(message_id)).await.is_ok());
+        // ...or that was already answered
+        assert!(conn.read_packet(&response(message_id)).await.is_err());
+    }
+
+    /// Sends Login Start to a connection with the given config.
+    async fn login_start(config: &str) -> (Connection, DuplexStream) {
+        login_start_as(config, "player").await
+    }
+
+    /// Sends Login Start with the given username to a connection with the
+    /// given config.
+    async fn login_start_as(config: &str, username: &str) -> (Connection, DuplexStream) {
+        let (mut conn, client) = Connection::for_tests(Server::spawn_for_tests());
+        conn.config = Arc::new(toml::from_str(config).unwrap());
+        conn.state = ConnectionState::Login;
+        let mut login_start = ResponseBuilder::new(LOGIN_SB_LOGIN_START);
+        login_start.add(username);
+        conn.read_packet(&login_start.to_frame()).await.unwrap();
+        (conn, client)
+    }
+
+    /// Reads the ID of the next packet, assuming it is unencrypted.
+    async fn next_packet_id(client: &mut DuplexStream) -> u32 {
+        let mut len = [0; 3];
+        client.read_exact(&mut len).await.unwrap();
+        let (rest, _) = varint::<u32>(&len).unwrap();
+        let (_, id) = varint::<u32>(rest).unwrap();
+        id
+    }
+
+    /// Lets the connection write as much as it wants.
+    fn drain(mut client: DuplexStream) {
+        tokio::spawn(async move { client.read_to_end(&mut vec![]).await });
+    }
+
+    #[tokio::test]
+    async fn test_offline_login() {
+        let (conn, client) = login_start("online_mode = false").await;
+        drain(client);
+        assert_eq!(conn.state, ConnectionState::Play);
+        assert!(conn.encrypt_cipher.is_none());
+    }
+
+    #[test]
+    fn test_is_valid_username() {
+        assert!(is_valid_username("Notch"));
+        assert!(is_valid_username("jeb_"));
+        assert!(is_valid_username("a"));
+        assert!(is_valid_username("sixteen_chars_16"));
+        assert!(!is_valid_username(""));
+        assert!(!is_valid_username("seventeen_chars17"));
+        assert!(!is_valid_username("two words"));
+        assert!(!is_valid_username("Notch§c"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_username() {
+        for config in ["online_mode = false", "online_mode = true"] {
+            let (conn, mut client) = login_start_as(config, "not a name").await;
+            assert_eq!(next_packet_id(&mut client).await, LOGIN_CB_DISCONNECT);
+            assert_eq!(conn.state, ConnectionState::Login);
+            assert!(conn.player.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_offline_login() {
+        let config = "online_mode = false\nencryption = true";
+        let (mut conn, mut client) = login_start(config).await;
+        assert_eq!(conn.state, ConnectionState::Login);
+        assert_eq!(
+            next_packet_id(&mut client).await,
+            LOGIN_CB_ENCRYPTION_REQUEST
+        );
+        drain(client);
+
+        let key = conn.keys.priv_key.to_public_key();
+        let encrypt = |data: &[u8]| {
+            key.encrypt(&mut OsRng, PaddingScheme::PKCS1v15Encrypt, data)
+                .unwrap()
+        };
+        let verify_token = conn.auth_session.as_ref().unwrap().verify_token;
+        let response = |shared_secret: &[u8]| {
+            let mut response = ResponseBuilder::new(LOGIN_SB_ENCRYPTION_RESPONSE);
+            response
+                .var_data(encrypt(shared_secret))
+                .var_data(encrypt(&verify_token));
+            response.to_frame()
+        };
+
+        // the shared secret has to be a valid AES-128 key
+        match conn.read_packet(&response(&[0x42; 15])).await {
+            Err(e @ NetError::AuthError(AuthenticationError::InvalidSharedSecretLength(15))) => {
+                assert!(e.disconnect_reason().is_some())
+            }
+            result => panic!("expected an invalid shared secret, got {:?}", result),
+        }
+        assert_eq!(conn.state, ConnectionState::Login);
+
+        conn.read_packet(&response(&[0x42; 16])).await.unwrap();
+        // the player gets in without being authenticated
+        assert_eq!(conn.state, ConnectionState::Play);
+        assert!(conn.encrypt_cipher.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_online_login() {
+        // encryption can't be turned off in online mode
+        for config in ["online_mode = true", "encryption = false"] {
+            let (conn, mut client) = login_start(config).await;
+            assert_eq!(conn.state, ConnectionState::Login);
+            assert_eq!(
+                next_packet_id(&mut client).await,
+                LOGIN_CB_ENCRYPTION_REQUEST
+            );
+        }
+    }
+}
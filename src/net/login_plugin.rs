@@ -0,0 +1,56 @@
+//! Login plugin requests, which let the server talk to a client, or to a proxy
+//! in front of it, over custom channels before it logs in.
+//!
+//! Clients can answer requests in any order, so the channel of every request
+//! still waiting for an answer is kept along with its message ID.
+use std::collections::HashMap;
+
+use crate::data::Identifier;
+
+#[derive(Debug, Default)]
+pub struct LoginPlugins {
+    next_message_id: u32,
+    pending: HashMap<u32, Identifier>,
+}
+
+impl LoginPlugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a request sent over `channel`, returning its message ID.
+    pub fn request(&mut self, channel: Identifier) -> u32 {
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+        self.pending.insert(message_id, channel);
+        message_id
+    }
+
+    /// Returns the channel of the request a response is for, if it was still
+    /// waiting for one.
+    pub fn respond(&mut self, message_id: u32) -> Option<Identifier> {
+        self.pending.remove(&message_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoginPlugins;
+    use crate::data::Identifier;
+
+    #[test]
+    fn test_login_plugins() {
+        let mut plugins = LoginPlugins::new();
+        let velocity = Identifier::try_from("velocity:player_info").unwrap();
+        let custom = Identifier::try_from("hieronymus:custom").unwrap();
+        let first = plugins.request(velocity.clone());
+        let second = plugins.request(custom.clone());
+        assert_ne!(first, second);
+
+        // responses can come in any order, but only once
+        assert_eq!(plugins.respond(second), Some(custom));
+        assert_eq!(plugins.respond(first), Some(velocity));
+        assert_eq!(plugins.respond(first), None);
+        assert_eq!(plugins.respond(42), None);
+    }
+}
@@ -0,0 +1,1067 @@
+//! Builders for clientbound Play packets that the server may need to send to
+//! any player, not just the one a [`Connection`](super::Connection) belongs to.
+use uuid::Uuid;
+
+use crate::{
+    chat::{Chat, ChatPosition},
+    data::{
+        Angle, Animation, Arm, Difficulty, EntityStatus, Gamemode, Identifier, ParticleData, Slot,
+        SoundCategory,
+    },
+    math::{BlockPos, Vec3},
+    server::{
+        attribute::Attribute,
+        boss_bar::BossBarAction,
+        effect::Effect,
+        item_entity::{ItemEntity, ITEM_ENTITY_TYPE},
+        scoreboard::{DisplaySlot, Objective},
+        team::{Team, TeamAction},
+        world_border::{WorldBorder, MAX_RADIUS},
+        Player,
+    },
+};
+
+use super::{coords, ResponseBuilder};
+
+/// Player Info, with the Add Player action.
+pub fn player_info_add<'a>(players: impl ExactSizeIterator<Item = &'a Player>) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x36);
+    builder.varint(0u32).varint(players.len() as u32);
+    for player in players {
+        builder
+            .add(player.uuid)
+            .add(&player.username)
+            .varint(0u32) // no properties
+            .varint(0u32) // survival
+            .varint(0u32) // ping
+            .add(false); // no display name
+    }
+    builder
+}
+
+/// Player Info, with the Remove Player action.
+pub fn player_info_remove(players: &[Uuid]) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x36);
+    builder.varint(4u32).varint(players.len() as u32);
+    for &uuid in players {
+        builder.add(uuid);
+    }
+    builder
+}
+
+pub fn spawn_player(entity_id: i32, uuid: Uuid, position: Vec3) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x04);
+    builder
+        .varint(entity_id)
+        .add(uuid)
+        .add(position)
+        .add(Angle(0)) // yaw
+        .add(Angle(0)); // pitch
+    builder
+}
+
+pub fn entity_teleport(entity_id: i32, position: Vec3, on_ground: bool) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x61);
+    builder
+        .varint(entity_id)
+        .add(position)
+        .add(Angle(0)) // yaw
+        .add(Angle(0)) // pitch
+        .add(on_ground);
+    builder
+}
+
+pub fn block_change(pos: BlockPos, state: u32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x0c);
+    builder.add(pos).varint(state);
+    builder
+}
+
+/// Which slot a Set Slot packet changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotTarget {
+    /// A slot of an open window, 0 being the player inventory, which is always
+    /// open.
+    Window { id: u8, slot: i16 },
+    /// The item carried on the cursor, e.g. while dragging items around, which
+    /// is sent as window -1 and slot -1.
+    Cursor,
+    /// A slot of the player inventory, which is sent as window -2. Unlike with
+    /// window 0, this works whichever window is open, and the client doesn't
+    /// play the pickup animation.
+    Inventory(i16),
+}
+impl SlotTarget {
+    /// Returns the window ID and slot index sent for the target.
+    pub fn ids(self) -> (i8, i16) {
+        match self {
+            Self::Window { id, slot } => (id as i8, slot),
+            Self::Cursor => (-1, -1),
+            Self::Inventory(slot) => (-2, slot),
+        }
+    }
+}
+
+/// Set Slot, for a slot of the player inventory window.
+pub fn set_slot(slot: i16, data: &Slot) -> Result<ResponseBuilder, nbt::Error> {
+    set_slot_of(SlotTarget::Window { id: 0, slot }, data)
+}
+
+/// Set Slot, for any slot, including the cursor.
+pub fn set_slot_of(target: SlotTarget, data: &Slot) -> Result<ResponseBuilder, nbt::Error> {
+    let (window, slot) = target.ids();
+    let mut builder = ResponseBuilder::new(0x16);
+    builder
+        .add(window)
+        .varint(0u32) // state ID
+        .add(slot)
+        .try_add(data)?;
+    Ok(builder)
+}
+
+pub fn boss_bar(uuid: Uuid, action: &BossBarAction) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x0d);
+    builder.add(uuid);
+    match action {
+        BossBarAction::Add {
+            title,
+            health,
+            color,
+            division,
+            flags,
+        } => builder
+            .varint(0u32)
+            .add(title)
+            .add(*health)
+            .varint(*color as u32)
+            .varint(*division as u32)
+            .add(flags.bits()),
+        BossBarAction::Remove => builder.varint(1u32),
+        BossBarAction::UpdateHealth(health) => builder.varint(2u32).add(*health),
+        BossBarAction::UpdateTitle(title) => builder.varint(3u32).add(title),
+        BossBarAction::UpdateStyle { color, division } => builder
+            .varint(4u32)
+            .varint(*color as u32)
+            .varint(*division as u32),
+        BossBarAction::UpdateFlags(flags) => builder.varint(5u32).add(flags.bits()),
+    };
+    builder
+}
+
+/// Scoreboard Objective, with the Create mode.
+pub fn create_objective(objective: &Objective) -> ResponseBuilder {
+    objective_packet(objective, 0)
+}
+/// Scoreboard Objective, with the Update Display Text mode.
+pub fn update_objective(objective: &Objective) -> ResponseBuilder {
+    objective_packet(objective, 2)
+}
+/// Scoreboard Objective, with the Remove mode.
+pub fn remove_objective(name: &str) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x53);
+    builder.add(name).add(1u8);
+    builder
+}
+fn objective_packet(objective: &Objective, mode: u8) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x53);
+    builder
+        .add(&objective.name)
+        .add(mode)
+        .add(&objective.display_name)
+        .varint(objective.render_type as u32);
+    builder
+}
+
+/// Update Score, setting a score.
+pub fn update_score(entity: &str, objective: &str, value: i32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x56);
+    builder
+        .add(entity)
+        .add(0u8)
+        .add(objective)
+        .varint(value as u32); // negative values are sent as two's complement
+    builder
+}
+/// Update Score, removing a score.
+pub fn remove_score(entity: &str, objective: &str) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x56);
+    builder.add(entity).add(1u8).add(objective);
+    builder
+}
+
+/// Display Scoreboard. An empty objective name clears the slot.
+pub fn display_scoreboard(slot: DisplaySlot, objective: &str) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x4c);
+    builder.add(slot as u8).add(objective);
+    builder
+}
+
+pub fn teams(name: &str, action: &TeamAction) -> ResponseBuilder {
+    fn team_info<'b>(builder: &'b mut ResponseBuilder, team: &Team) -> &'b mut ResponseBuilder {
+        builder
+            .add(&team.display_name)
+            .add(team.flags.bits())
+            .add(team.name_tag_visibility.as_str())
+            .add(team.collision_rule.as_str())
+            .varint(team.color as u32)
+            .add(&team.prefix)
+            .add(&team.suffix)
+    }
+    fn members<'b>(
+        builder: &'b mut ResponseBuilder,
+        members: impl ExactSizeIterator<Item = &'b String>,
+    ) -> &'b mut ResponseBuilder {
+        builder.varint(members.len() as u32);
+        for member in members {
+            builder.add(member);
+        }
+        builder
+    }
+
+    let mut builder = ResponseBuilder::new(0x55);
+    builder.add(name);
+    match action {
+        TeamAction::Create(team) => {
+            team_info(builder.add(0u8), team);
+            members(&mut builder, team.members.iter());
+        }
+        TeamAction::Remove => {
+            builder.add(1u8);
+        }
+        TeamAction::UpdateInfo(team) => {
+            team_info(builder.add(2u8), team);
+        }
+        TeamAction::AddMembers(added) => {
+            members(builder.add(3u8), added.iter());
+        }
+        TeamAction::RemoveMembers(removed) => {
+            members(builder.add(4u8), removed.iter());
+        }
+    }
+    builder
+}
+
+/// Chat Message. `sender` is used by clients to hide messages from players
+/// they blocked, and can be nil for messages that aren't from a player.
+pub fn chat_message(message: &Chat, position: ChatPosition, sender: Uuid) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x0f);
+    builder.add(message).add(position as u8).add(sender);
+    builder
+}
+
+/// Player List Header And Footer. Empty components hide the header or footer.
+pub fn tab_list(header: &Chat, footer: &Chat) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x5e);
+    builder.add(header).add(footer);
+    builder
+}
+
+/// Particle. `offset` is multiplied by a random number to get the spread of
+/// the particles along each axis.
+pub fn particle(
+    particle_id: i32,
+    long_distance: bool,
+    position: Vec3,
+    offset: Vec3,
+    max_speed: f32,
+    count: i32,
+    data: &ParticleData,
+) -> Result<ResponseBuilder, nbt::Error> {
+    let mut builder = ResponseBuilder::new(0x24);
+    builder
+        .add(particle_id)
+        .add(long_distance)
+        .add(position)
+        .add(offset.x as f32)
+        .add(offset.y as f32)
+        .add(offset.z as f32)
+        .add(max_speed)
+        .add(count)
+        .try_add(data)?;
+    Ok(builder)
+}
+
+/// Explosion. The affected blocks are destroyed by the client, and must be
+/// within 128 blocks of the center.
+pub fn explosion(
+    position: Vec3,
+    strength: f32,
+    affected_blocks: &[BlockPos],
+    player_motion: Vec3,
+) -> ResponseBuilder {
+    let center = position.block_pos();
+    let mut builder = ResponseBuilder::new(0x1c);
+    builder
+        .add(position.x as f32)
+        .add(position.y as f32)
+        .add(position.z as f32)
+        .add(strength)
+        .varint(affected_blocks.len() as u32);
+    for &block in affected_blocks {
+        let offset = block - center;
+        builder
+            .add(offset.x as i8)
+            .add(offset.y as i8)
+            .add(offset.z as i8);
+    }
+    builder
+        .add(player_motion.x as f32)
+        .add(player_motion.y as f32)
+        .add(player_motion.z as f32);
+    builder
+}
+
+pub fn server_difficulty(difficulty: Difficulty, locked: bool) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x0e);
+    builder.add(difficulty).add(locked);
+    builder
+}
+
+/// Change Game State, with the Change Gamemode reason.
+pub fn change_gamemode(gamemode: Gamemode) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x1e);
+    builder.add(3u8).add(gamemode.game_state_value());
+    builder
+}
+
+/// Sound Effect, for sounds in the sound event registry. Positions are sent as
+/// fixed-point numbers, precise to an eighth of a block.
+pub fn sound_effect(
+    sound_id: u32,
+    category: SoundCategory,
+    position: Vec3,
+    volume: f32,
+    pitch: f32,
+) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x5c);
+    builder.varint(sound_id).add(category);
+    sound_position(&mut builder, position)
+        .add(volume)
+        .add(pitch);
+    builder
+}
+
+/// Named Sound Effect, for sounds that aren't in the registry, like the ones
+/// added by resource packs.
+pub fn named_sound_effect(
+    sound: &Identifier,
+    category: SoundCategory,
+    position: Vec3,
+    volume: f32,
+    pitch: f32,
+) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x19);
+    builder.add(sound.to_string().as_str()).add(category);
+    sound_position(&mut builder, position)
+        .add(volume)
+        .add(pitch);
+    builder
+}
+
+fn sound_position(builder: &mut ResponseBuilder, position: Vec3) -> &mut ResponseBuilder {
+    builder
+        .add((position.x * 8.0) as i32)
+        .add((position.y * 8.0) as i32)
+        .add((position.z * 8.0) as i32)
+}
+
+pub fn set_cooldown(item_id: u32, ticks: u32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x17);
+    builder.varint(item_id).varint(ticks);
+    builder
+}
+
+/// Initialize World Border, which sets every property of the border at once.
+pub fn initialize_world_border(border: &WorldBorder) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x20);
+    builder
+        .add(border.center_x)
+        .add(border.center_z)
+        .add(border.diameter()) // old diameter
+        .add(border.diameter()) // new diameter
+        .varint(0u64) // milliseconds to go from the old diameter to the new one
+        .varint(MAX_RADIUS as u32) // portal teleport boundary
+        .varint(border.warning_distance)
+        .varint(border.warning_time);
+    builder
+}
+
+pub fn world_border_center(x: f64, z: f64) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x42);
+    builder.add(x).add(z);
+    builder
+}
+
+/// Set Border Size, which resizes the border immediately.
+pub fn world_border_size(diameter: f64) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x44);
+    builder.add(diameter);
+    builder
+}
+
+pub fn world_border_warning_distance(blocks: u32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x46);
+    builder.varint(blocks);
+    builder
+}
+
+/// Update View Distance, sent when the view distance of the server changes.
+pub fn update_view_distance(view_distance: u8) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x4a);
+    builder.varint(u32::from(view_distance));
+    builder
+}
+
+/// Acknowledge Player Digging, telling the client which block is actually at
+/// `pos` after it started, cancelled or finished digging it.
+pub fn acknowledge_digging(
+    pos: BlockPos,
+    state: u32,
+    status: u32,
+    successful: bool,
+) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x08);
+    builder
+        .add(pos)
+        .varint(state)
+        .varint(status)
+        .add(successful);
+    builder
+}
+
+pub fn open_sign_editor(pos: BlockPos) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x2f);
+    builder.add(pos);
+    builder
+}
+
+/// Respawn, which brings a dead player back in the given dimension type, as
+/// NBT.
+pub fn respawn(dimension: &[u8]) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::with_capacity(0x3d, dimension.len() + 64);
+    builder
+        .raw_data(dimension)
+        .add("hieronymus:wonderland") // world name
+        .add(0u64) // hashed seed, only used for biome noise
+        .add(Gamemode::Survival)
+        .add(None::<Gamemode>) // no previous gamemode
+        .add(false) // is debug world
+        .add(false) // is superflat
+        .add(false); // don't keep the metadata
+    builder
+}
+
+/// Update Health, which changes the health and food of the player.
+pub fn update_health(health: f32, food: u32, saturation: f32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x52);
+    builder.add(health).varint(food).add(saturation);
+    builder
+}
+
+pub fn entity_animation(entity_id: i32, animation: Animation) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x06);
+    builder.varint(entity_id).add(animation);
+    builder
+}
+
+/// Spawn Entity, for entities that aren't living, like item entities, boats
+/// and projectiles. What `data` means depends on the type of entity.
+pub fn spawn_entity(
+    entity_id: i32,
+    uuid: Uuid,
+    entity_type: u32,
+    position: Vec3,
+    (yaw, pitch): (Angle, Angle),
+    data: i32,
+    velocity: Vec3,
+) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x00);
+    builder
+        .varint(entity_id)
+        .add(uuid)
+        .varint(entity_type)
+        .add(position)
+        .add(pitch)
+        .add(yaw)
+        .add(data);
+    coords::add_velocity(&mut builder, velocity);
+    builder
+}
+
+/// Spawn Living Entity, for mobs and armor stands.
+pub fn spawn_living_entity(
+    entity_id: i32,
+    uuid: Uuid,
+    entity_type: u32,
+    position: Vec3,
+    (yaw, pitch): (Angle, Angle),
+    head_yaw: Angle,
+    velocity: Vec3,
+) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x02);
+    builder
+        .varint(entity_id)
+        .add(uuid)
+        .varint(entity_type)
+        .add(position)
+        .add(yaw)
+        .add(pitch)
+        .add(head_yaw);
+    coords::add_velocity(&mut builder, velocity);
+    builder
+}
+
+/// Spawn Entity for an item entity, which has to be followed by
+/// [`item_entity_metadata`] for the client to know what the item is.
+pub fn spawn_item_entity(item: &ItemEntity) -> ResponseBuilder {
+    spawn_entity(
+        item.entity_id,
+        item.uuid,
+        ITEM_ENTITY_TYPE,
+        item.position,
+        (Angle(0), Angle(0)),
+        1, // unused for items
+        item.velocity,
+    )
+}
+
+/// Entity Metadata, with the stack of items held by an item entity.
+pub fn item_entity_metadata(item: &ItemEntity) -> Result<ResponseBuilder, nbt::Error> {
+    let mut builder = ResponseBuilder::new(0x4d);
+    builder
+        .varint(item.entity_id)
+        .add(8u8) // index of the item stack
+        .varint(6u32) // slot
+        .try_add(&Some(item.item.clone()))?
+        .add(0xffu8); // end of the metadata
+    Ok(builder)
+}
+
+/// Entity Metadata, with the main hand of a player, which other clients
+/// need to know which hand to show items in.
+pub fn player_metadata(entity_id: i32, main_hand: Arm) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x4d);
+    builder
+        .varint(entity_id)
+        .add(18u8) // index of the main hand
+        .varint(0u32) // byte
+        .add(main_hand as u8)
+        .add(0xffu8); // end of the metadata
+    builder
+}
+
+/// Collect Item, which shows an entity picking up `count` items from an item
+/// entity. The item entity still has to be destroyed afterwards.
+pub fn collect_item(collected_id: i32, collector_id: i32, count: u32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x60);
+    builder
+        .varint(collected_id)
+        .varint(collector_id)
+        .varint(count);
+    builder
+}
+
+/// Update Attributes (also known as Entity Properties), which changes the
+/// given attributes of an entity and leaves the others alone.
+pub fn update_attributes(entity_id: i32, attributes: &[Attribute]) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x63);
+    builder.varint(entity_id).varint(attributes.len() as u32);
+    for attribute in attributes {
+        builder
+            .add(attribute.key.to_string().as_str())
+            .add(attribute.base)
+            .varint(attribute.modifiers.len() as u32);
+        for modifier in &attribute.modifiers {
+            builder
+                .add(modifier.uuid)
+                .add(modifier.amount)
+                .add(modifier.operation as u8);
+        }
+    }
+    builder
+}
+
+/// Entity Effect, which puts an entity under an effect, or changes the one
+/// with the same ID it is under.
+pub fn entity_effect(entity_id: i32, effect: &Effect) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x64);
+    builder
+        .varint(entity_id)
+        .add(effect.id)
+        .add(effect.amplifier)
+        .varint(effect.duration)
+        .add(effect.flags.bits());
+    builder
+}
+
+/// Remove Entity Effect, which takes an effect off an entity.
+pub fn remove_entity_effect(entity_id: i32, effect_id: u8) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x3b);
+    builder.varint(entity_id).add(effect_id);
+    builder
+}
+
+/// Camera, which makes the client view the world from the eyes of another
+/// entity, or from its own again if `entity_id` is the player's own. Clients
+/// only follow it in spectator mode.
+pub fn camera(entity_id: i32) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x47);
+    builder.varint(entity_id);
+    builder
+}
+
+pub fn entity_status(entity_id: i32, status: EntityStatus) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x1b);
+    builder.add(entity_id).add(status);
+    builder
+}
+
+/// Attach Entity, which leashes an entity to another one, or breaks its leash
+/// if `holder_id` is `None`.
+pub fn attach_entity(attached_id: i32, holder_id: Option<i32>) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x4e);
+    builder.add(attached_id).add(holder_id.unwrap_or(-1));
+    builder
+}
+
+/// Set Passengers, which replaces every entity riding a vehicle. An empty
+/// list dismounts them all.
+pub fn set_passengers(vehicle_id: i32, passenger_ids: &[i32]) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x54);
+    builder
+        .varint(vehicle_id)
+        .varint(passenger_ids.len() as u32);
+    for &id in passenger_ids {
+        builder.varint(id);
+    }
+    builder
+}
+
+pub fn destroy_entities(entity_ids: &[i32]) -> ResponseBuilder {
+    let mut builder = ResponseBuilder::new(0x3a);
+    builder.varint(entity_ids.len() as u32);
+    for &id in entity_ids {
+        builder.varint(id);
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{
+        attach_entity, boss_bar, camera, collect_item, create_objective, destroy_entities,
+        entity_effect, entity_status, explosion, initialize_world_border, item_entity_metadata,
+        named_sound_effect, open_sign_editor, particle, player_info_remove, player_metadata,
+        remove_entity_effect, set_cooldown, set_passengers, sound_effect, spawn_entity,
+        spawn_item_entity, spawn_living_entity, spawn_player, tab_list, teams, update_attributes,
+        update_score, update_view_distance,
+    };
+    use crate::{
+        chat::{Chat, ChatColor},
+        data::{Angle, Arm, EntityStatus, Identifier, ParticleData, SlotData, SoundCategory},
+        math::{BlockPos, Vec3},
+        server::{
+            attribute::{Attribute, AttributeModifier, ModifierOperation},
+            boss_bar::{BossBar, BossBarAction, BossBarColor, BossBarDivision, BossBarFlags},
+            effect::{Effect, EffectFlags},
+            item_entity::ItemEntity,
+            scoreboard::{Objective, RenderType},
+            team::{CollisionRule, Team, TeamAction},
+            world_border::WorldBorder,
+        },
+    };
+
+    #[test]
+    fn test_spawn_and_destroy() {
+        let uuid = Uuid::from_u128(0x0123456789abcdef0123456789abcdef);
+        let spawn = spawn_player(300, uuid, Vec3::new(1.0, 64.0, -2.5));
+        let mut expected = vec![0x04, 0xac, 0x02];
+        expected.extend_from_slice(&uuid.as_u128().to_be_bytes());
+        expected.extend_from_slice(&1f64.to_be_bytes());
+        expected.extend_from_slice(&64f64.to_be_bytes());
+        expected.extend_from_slice(&(-2.5f64).to_be_bytes());
+        expected.extend_from_slice(&[0, 0]);
+        assert_eq!(spawn.data, expected);
+
+        assert_eq!(destroy_entities(&[1, 300]).data, [0x3a, 2, 1, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_spawn_item_entity() {
+        let uuid = Uuid::from_u128(0x0123456789abcdef0123456789abcdef);
+        let item = ItemEntity {
+            entity_id: 300,
+            uuid,
+            item: SlotData::new(1, 64),
+            position: Vec3::new(1.0, 64.0, -2.5),
+            velocity: Vec3::new(0.0, 0.1, -10.0),
+        };
+        let mut expected = vec![0x00, 0xac, 0x02];
+        expected.extend_from_slice(&uuid.as_u128().to_be_bytes());
+        expected.push(41); // minecraft:item
+        expected.extend_from_slice(&1f64.to_be_bytes());
+        expected.extend_from_slice(&64f64.to_be_bytes());
+        expected.extend_from_slice(&(-2.5f64).to_be_bytes());
+        expected.extend_from_slice(&[0, 0]);
+        expected.extend_from_slice(&1i32.to_be_bytes());
+        expected.extend_from_slice(&0i16.to_be_bytes());
+        expected.extend_from_slice(&800i16.to_be_bytes());
+        // too fast for the client, so it is capped
+        expected.extend_from_slice(&(-31200i16).to_be_bytes());
+        assert_eq!(spawn_item_entity(&item).data, expected);
+
+        assert_eq!(
+            item_entity_metadata(&item).unwrap().data,
+            [0x4d, 0xac, 0x02, 8, 6, 1, 1, 64, 0, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_player_metadata() {
+        assert_eq!(
+            player_metadata(300, Arm::Left).data,
+            [0x4d, 0xac, 0x02, 18, 0, 0, 0xff]
+        );
+        assert_eq!(
+            player_metadata(300, Arm::Right).data,
+            [0x4d, 0xac, 0x02, 18, 0, 1, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_spawn_entity() {
+        let uuid = Uuid::from_u128(0x0123456789abcdef0123456789abcdef);
+        let position = Vec3::new(1.0, 64.0, -2.5);
+        let mut position_bytes = vec![];
+        for coordinate in [1f64, 64.0, -2.5] {
+            position_bytes.extend_from_slice(&coordinate.to_be_bytes());
+        }
+
+        // a boat, facing west and looking slightly up
+        let boat = spawn_entity(
+            300,
+            uuid,
+            7,
+            position,
+            (Angle(64), Angle(250)),
+            0,
+            Vec3::new(0.5, 0.0, 0.0),
+        );
+        let mut expected = vec![0x00, 0xac, 0x02];
+        expected.extend_from_slice(&uuid.as_u128().to_be_bytes());
+        expected.push(7);
+        expected.extend_from_slice(&position_bytes);
+        expected.extend_from_slice(&[250, 64]); // pitch comes first
+        expected.extend_from_slice(&0i32.to_be_bytes());
+        expected.extend_from_slice(&4000i16.to_be_bytes());
+        expected.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(boat.data, expected);
+
+        // a zombie, looking to its left
+        let zombie = spawn_living_entity(
+            300,
+            uuid,
+            107,
+            position,
+            (Angle(64), Angle(0)),
+            Angle(0),
+            Vec3::ZERO,
+        );
+        let mut expected = vec![0x02, 0xac, 0x02];
+        expected.extend_from_slice(&uuid.as_u128().to_be_bytes());
+        expected.push(107);
+        expected.extend_from_slice(&position_bytes);
+        expected.extend_from_slice(&[64, 0, 0]);
+        expected.extend_from_slice(&[0; 6]);
+        assert_eq!(zombie.data, expected);
+    }
+
+    #[test]
+    fn test_update_attributes() {
+        let uuid = Uuid::from_u128(0x662a6b8d_da3e_4c1c_8813_96ea6097278d);
+        let speed = Attribute {
+            key: Identifier::minecraft("generic.movement_speed"),
+            base: 0.1,
+            modifiers: vec![AttributeModifier {
+                uuid,
+                amount: 0.3,
+                operation: ModifierOperation::MultiplyBase,
+            }],
+        };
+        let mut expected = vec![0x63, 0xac, 0x02, 1, 32];
+        expected.extend_from_slice(b"minecraft:generic.movement_speed");
+        expected.extend_from_slice(&0.1f64.to_be_bytes());
+        expected.push(1);
+        expected.extend_from_slice(&uuid.as_u128().to_be_bytes());
+        expected.extend_from_slice(&0.3f64.to_be_bytes());
+        expected.push(1);
+        assert_eq!(update_attributes(300, &[speed]).data, expected);
+    }
+
+    #[test]
+    fn test_entity_effect() {
+        // Speed II for a minute, from a beacon
+        let effect = Effect {
+            id: 1,
+            amplifier: 1,
+            duration: 1200,
+            flags: EffectFlags::AMBIENT | EffectFlags::SHOW_ICON,
+        };
+        assert_eq!(
+            entity_effect(300, &effect).data,
+            [0x64, 0xac, 0x02, 1, 1, 0xb0, 0x09, 0x05]
+        );
+        assert_eq!(remove_entity_effect(300, 1).data, [0x3b, 0xac, 0x02, 1]);
+    }
+
+    #[test]
+    fn test_collect_item() {
+        assert_eq!(collect_item(300, 1, 64).data, [0x60, 0xac, 0x02, 1, 64]);
+    }
+
+    #[test]
+    fn test_camera() {
+        assert_eq!(camera(300).data, [0x47, 0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_entity_status() {
+        assert_eq!(
+            entity_status(300, EntityStatus::TotemOfUndying).data,
+            [0x1b, 0, 0, 0x01, 0x2c, 35]
+        );
+    }
+
+    #[test]
+    fn test_player_info_remove() {
+        let uuid = Uuid::from_u128(0x0123456789abcdef0123456789abcdef);
+        let mut expected = vec![0x36, 4, 1];
+        expected.extend_from_slice(&uuid.as_u128().to_be_bytes());
+        assert_eq!(player_info_remove(&[uuid]).data, expected);
+    }
+
+    #[test]
+    fn test_boss_bar() {
+        let uuid = Uuid::from_u128(1);
+        let mut bar = BossBar::new(
+            Chat::text("Hi"),
+            BossBarColor::Red,
+            BossBarDivision::Notches10,
+        );
+        bar.apply(&BossBarAction::UpdateHealth(0.5));
+        bar.flags = BossBarFlags::DARKEN_SKY | BossBarFlags::CREATE_FOG;
+
+        let mut expected = vec![0x0d];
+        expected.extend_from_slice(&1u128.to_be_bytes());
+        expected.push(0); // add
+        expected.push(13);
+        expected.extend_from_slice(br#"{"text":"Hi"}"#);
+        expected.extend_from_slice(&0.5f32.to_be_bytes());
+        expected.extend_from_slice(&[2, 2, 0x05]);
+        assert_eq!(boss_bar(uuid, &bar.add_action()).data, expected);
+
+        let mut expected = vec![0x0d];
+        expected.extend_from_slice(&1u128.to_be_bytes());
+        expected.push(1); // remove
+        assert_eq!(boss_bar(uuid, &BossBarAction::Remove).data, expected);
+    }
+
+    #[test]
+    fn test_scoreboard() {
+        let objective = Objective {
+            name: "kills".into(),
+            display_name: Chat::text("Kills"),
+            render_type: RenderType::Hearts,
+        };
+        let mut expected = vec![0x53, 5];
+        expected.extend_from_slice(b"kills");
+        expected.extend_from_slice(&[0, 16]);
+        expected.extend_from_slice(br#"{"text":"Kills"}"#);
+        expected.push(1);
+        assert_eq!(create_objective(&objective).data, expected);
+
+        let mut expected = vec![0x56, 5];
+        expected.extend_from_slice(b"pluie");
+        expected.extend_from_slice(&[0, 5]);
+        expected.extend_from_slice(b"kills");
+        expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f]); // -1
+        assert_eq!(update_score("pluie", "kills", -1).data, expected);
+    }
+
+    #[test]
+    fn test_teams() {
+        let mut team = Team::new("red");
+        team.color = ChatColor::Red;
+        team.collision_rule = CollisionRule::Never;
+        team.members.insert("pluie".into());
+        team.members.insert("lumi".into());
+
+        let mut expected = vec![0x55, 3];
+        expected.extend_from_slice(b"red");
+        expected.extend_from_slice(&[0, 14]); // create
+        expected.extend_from_slice(br#"{"text":"red"}"#);
+        expected.extend_from_slice(&[0x03, 6]);
+        expected.extend_from_slice(b"always");
+        expected.push(5);
+        expected.extend_from_slice(b"never");
+        expected.extend_from_slice(&[12, 11]);
+        expected.extend_from_slice(br#"{"text":""}"#);
+        expected.push(11);
+        expected.extend_from_slice(br#"{"text":""}"#);
+        expected.extend_from_slice(&[2, 4]);
+        expected.extend_from_slice(b"lumi");
+        expected.push(5);
+        expected.extend_from_slice(b"pluie");
+        assert_eq!(teams("red", &TeamAction::Create(team)).data, expected);
+    }
+
+    #[test]
+    fn test_tab_list() {
+        let header = Chat::text("Welcome").color(ChatColor::Gold);
+        let footer = Chat::text("hieronymus");
+
+        let mut expected = vec![0x5e, 33];
+        expected.extend_from_slice(br#"{"text":"Welcome","color":"gold"}"#);
+        expected.push(21);
+        expected.extend_from_slice(br#"{"text":"hieronymus"}"#);
+        assert_eq!(tab_list(&header, &footer).data, expected);
+    }
+
+    #[test]
+    fn test_particle() {
+        let position = Vec3::new(1.0, 2.0, 3.0);
+        let offset = Vec3::new(0.5, 0.0, 0.5);
+        let mut expected = vec![0x24];
+        expected.extend_from_slice(&20i32.to_be_bytes()); // flame
+        expected.push(0);
+        for f in [1f64, 2.0, 3.0] {
+            expected.extend_from_slice(&f.to_be_bytes());
+        }
+        for f in [0.5f32, 0.0, 0.5, 0.1] {
+            expected.extend_from_slice(&f.to_be_bytes());
+        }
+        expected.extend_from_slice(&8i32.to_be_bytes());
+        let flame = particle(20, false, position, offset, 0.1, 8, &ParticleData::None).unwrap();
+        assert_eq!(flame.data, expected);
+
+        // the same, but for dust, with the color and scale after everything else
+        expected[1..5].copy_from_slice(&14i32.to_be_bytes());
+        for f in [1f32, 0.0, 0.0, 2.0] {
+            expected.extend_from_slice(&f.to_be_bytes());
+        }
+        let dust = ParticleData::Dust {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+            scale: 2.0,
+        };
+        let dust = particle(14, false, position, offset, 0.1, 8, &dust).unwrap();
+        assert_eq!(dust.data, expected);
+    }
+
+    #[test]
+    fn test_sound_effect() {
+        let position = Vec3::new(1.5, 64.0, -0.25);
+        let mut expected = vec![0x5c, 0x90, 0x01, 4]; // 144, blocks
+        for i in [12i32, 512, -2] {
+            expected.extend_from_slice(&i.to_be_bytes());
+        }
+        for f in [1f32, 0.5] {
+            expected.extend_from_slice(&f.to_be_bytes());
+        }
+        let packet = sound_effect(144, SoundCategory::Blocks, position, 1.0, 0.5);
+        assert_eq!(packet.data, expected);
+
+        let sound = Identifier::minecraft("block.note_block.harp");
+        let packet = named_sound_effect(&sound, SoundCategory::Blocks, position, 1.0, 0.5);
+        let mut named = vec![0x19, 31];
+        named.extend_from_slice(b"minecraft:block.note_block.harp");
+        named.extend_from_slice(&expected[3..]);
+        assert_eq!(packet.data, named);
+    }
+
+    #[test]
+    fn test_explosion() {
+        let blocks = [BlockPos::new(10, 63, -5), BlockPos::new(9, 64, -6)];
+        let packet = explosion(
+            Vec3::new(10.5, 64.0, -5.5),
+            4.0,
+            &blocks,
+            Vec3::new(0.0, 0.5, -1.0),
+        );
+
+        let mut expected = vec![0x1c];
+        for f in [10.5f32, 64.0, -5.5, 4.0] {
+            expected.extend_from_slice(&f.to_be_bytes());
+        }
+        expected.push(2);
+        expected.extend_from_slice(&[0, 0xff, 1, 0xff, 0, 0]);
+        for f in [0f32, 0.5, -1.0] {
+            expected.extend_from_slice(&f.to_be_bytes());
+        }
+        assert_eq!(packet.data, expected);
+    }
+
+    #[test]
+    fn test_set_cooldown() {
+        // a one second cooldown
+        assert_eq!(set_cooldown(665, 20).data, [0x17, 0x99, 0x05, 20]);
+    }
+
+    #[test]
+    fn test_update_view_distance() {
+        assert_eq!(update_view_distance(12).data, [0x4a, 12]);
+    }
+
+    #[test]
+    fn test_open_sign_editor() {
+        let mut expected = vec![0x2f];
+        expected.extend_from_slice(&0x4607632c15b4833fu64.to_be_bytes());
+        assert_eq!(
+            open_sign_editor(BlockPos::new(18357644, 831, -20882616)).data,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_world_border() {
+        let border = WorldBorder {
+            center_x: 8.0,
+            center_z: -8.0,
+            radius: 50.0,
+            warning_distance: 5,
+            warning_time: 15,
+        };
+        let mut expected = vec![0x20];
+        expected.extend_from_slice(&8f64.to_be_bytes());
+        expected.extend_from_slice(&(-8f64).to_be_bytes());
+        expected.extend_from_slice(&100f64.to_be_bytes());
+        expected.extend_from_slice(&100f64.to_be_bytes());
+        expected.push(0);
+        expected.extend_from_slice(&[0xf0, 0x86, 0xa7, 0x0e]); // 29999984
+        expected.extend_from_slice(&[5, 15]);
+        assert_eq!(initialize_world_border(&border).data, expected);
+    }
+
+    #[test]
+    fn test_attach_entity() {
+        let mut expected = vec![0x4e];
+        expected.extend_from_slice(&300i32.to_be_bytes());
+        expected.extend_from_slice(&1i32.to_be_bytes());
+        assert_eq!(attach_entity(300, Some(1)).data, expected);
+
+        // detaching is holding on to entity -1
+        let mut expected = vec![0x4e];
+        expected.extend_from_slice(&300i32.to_be_bytes());
+        expected.extend_from_slice(&[0xff; 4]);
+        assert_eq!(attach_entity(300, None).data, expected);
+
+        assert_eq!(
+            set_passengers(1, &[2, 300]).data,
+            [0x54, 1, 2, 2, 0xac, 0x02]
+        );
+        assert_eq!(set_passengers(1, &[]).data, [0x54, 1, 0]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use async_trait::async_trait;
 use bitflags::bitflags;
 use nom::{
@@ -6,19 +8,37 @@ use nom::{
 };
 use nom_derive::{Nom, Parse};
 use num_derive::FromPrimitive;
-use num_traits::FromPrimitive;
 use tracing::instrument;
+use uuid::Uuid;
 
 use crate::{
-    data::{Direction, Hand, Identifier, Position, Slot, Arm},
+    audit::DisconnectReason,
+    chat::{Chat, ChatMode},
+    data::{
+        Angle, Arm, Difficulty, Direction, EntityStatus, Gamemode, Hand, Identifier, ParticleData,
+        Slot,
+    },
     match_id_and_forward,
-    nom::{boolean, maybe, var_str, var_str_with_max_length},
+    math::{AbsOrRel, BlockPos, Vec3},
+    nom::{boolean, enum_varint, maybe, var_str, var_str_with_max_length},
     parse_impl_for_bitflags,
-    server::Player,
+    server::{
+        attribute::Attribute,
+        boss_bar::{BossBarAction, BossBarColor, BossBarDivision, BossBarFlags},
+        effect::Effect,
+        team::TeamAction,
+        world_border::WorldBorder,
+        Player,
+    },
     varint::varint,
 };
 
-use super::{BoxedPacket, Connection, Packet, ResponseBuilder};
+use super::{
+    coords,
+    packets::{self, SlotTarget},
+    rate_limit::RateLimit,
+    BoxedPacket, Connection, ConnectionState, NetResult, Packet, ResponseBuilder,
+};
 
 pub fn read_packet(input: &[u8]) -> IResult<&[u8], BoxedPacket<'_>> {
     match_id_and_forward! {
@@ -54,7 +74,11 @@ pub fn read_packet(input: &[u8]) -> IResult<&[u8], BoxedPacket<'_>> {
         0x1c => SteerVehicle,
         0x1d => Pong,
         0x1e => SetRecipeBookState,
-        0x1f => SetDisplayedRecipe
+        0x1f => SetDisplayedRecipe,
+        0x25 => HeldItemChange,
+        0x2b => UpdateSign,
+        0x2c => Animation,
+        0x2e => PlayerBlockPlacement
     }
 }
 #[derive(Debug, Nom)]
@@ -65,7 +89,7 @@ struct TeleportConfirm {
 #[async_trait]
 impl Packet for TeleportConfirm {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -74,24 +98,25 @@ impl Packet for TeleportConfirm {
 struct QueryBlockNbt {
     #[nom(Parse = "varint")]
     transaction_id: u32,
-    location: Position,
+    #[nom(Parse = "coords::block_pos")]
+    location: BlockPos,
 }
 #[async_trait]
 impl Packet for QueryBlockNbt {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
 
 #[derive(Debug, Nom)]
 struct SetDifficulty {
-    new_difficulty: u8,
+    new_difficulty: Difficulty,
 }
 #[async_trait]
 impl Packet for SetDifficulty {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -104,7 +129,37 @@ struct ChatMessage<'a> {
 #[async_trait]
 impl Packet for ChatMessage<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        let uuid = match &conn.player {
+            Some(player) => player.uuid,
+            None => return Ok(()),
+        };
+        match conn.chat_limiter.check(Instant::now()) {
+            RateLimit::Allowed => {}
+            RateLimit::Throttled => {
+                conn.send_system_message("You are sending messages too quickly")
+                    .await?;
+                return Ok(());
+            }
+            RateLimit::Exceeded => {
+                conn.send_disconnect(DisconnectReason::Spam, &Chat::text("Kicked for spamming"))
+                    .await?;
+                conn.close();
+                return Ok(());
+            }
+        }
+        match self.message.strip_prefix('/') {
+            Some(command) => {
+                conn.server
+                    .player_command(uuid, command.to_string())
+                    .await?
+            }
+            None => {
+                conn.server
+                    .chat_message(uuid, self.message.to_string())
+                    .await?
+            }
+        }
         Ok(())
     }
 }
@@ -112,12 +167,18 @@ impl Packet for ChatMessage<'_> {
 #[derive(Debug, Nom)]
 struct ClientStatus {
     #[nom(Parse = "varint")]
-    action_id: u32, // todo
+    action_id: u32,
 }
 #[async_trait]
 impl Packet for ClientStatus {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        // 0 is Perform Respawn, 1 is Request Stats
+        if self.action_id == 0 {
+            if let Some(player) = &conn.player {
+                conn.server.respawn(player.uuid).await?;
+            }
+        }
         Ok(())
     }
 }
@@ -127,8 +188,7 @@ struct ClientSettings<'a> {
     #[nom(Parse = "var_str_with_max_length(16u32)")]
     locale: &'a str,
     view_distance: u8,
-    #[nom(Parse = "varint")]
-    chat_mode: u32, //TODO
+    chat_mode: ChatMode,
     #[nom(Parse = "boolean")]
     chat_colors: bool,
     displayed_skin_parts: DisplayedSkinParts,
@@ -139,7 +199,18 @@ struct ClientSettings<'a> {
 #[async_trait]
 impl Packet for ClientSettings<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        if let Some(player) = &conn.player {
+            conn.server
+                .client_settings(
+                    player.uuid,
+                    self.view_distance,
+                    self.chat_mode,
+                    self.main_arm,
+                    self.enable_text_filtering,
+                )
+                .await?;
+        }
         Ok(())
     }
 }
@@ -166,7 +237,7 @@ struct TabComplete<'a> {
 #[async_trait]
 impl Packet for TabComplete<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -179,7 +250,7 @@ struct ClickWindowButton {
 #[async_trait]
 impl Packet for ClickWindowButton {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -199,7 +270,7 @@ struct ClickWindow {
 #[async_trait]
 impl Packet for ClickWindow {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -211,7 +282,7 @@ struct CloseWindow {
 #[async_trait]
 impl Packet for CloseWindow {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -225,7 +296,7 @@ struct PluginMessage<'a> {
 #[async_trait]
 impl Packet for PluginMessage<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -243,7 +314,7 @@ struct EditBook<'a> {
 #[async_trait]
 impl Packet for EditBook<'_> {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -258,7 +329,7 @@ struct QueryEntityNbt {
 #[async_trait]
 impl Packet for QueryEntityNbt {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -277,7 +348,12 @@ struct InteractEntity {
 #[async_trait]
 impl Packet for InteractEntity {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        if let (Some(player), EntityInteraction::Attack) = (&conn.player, &self.interaction) {
+            conn.server
+                .attack(player.uuid, self.entity_id as i32)
+                .await?;
+        }
         Ok(())
     }
 }
@@ -295,7 +371,8 @@ enum EntityInteraction {
 
 #[derive(Debug, Nom)]
 struct GenerateStructure {
-    location: Position,
+    #[nom(Parse = "coords::block_pos")]
+    location: BlockPos,
     #[nom(Parse = "varint")]
     levels: u32,
     #[nom(Parse = "boolean")]
@@ -304,7 +381,7 @@ struct GenerateStructure {
 #[async_trait]
 impl Packet for GenerateStructure {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -314,7 +391,7 @@ struct KeepAlive(u64);
 #[async_trait]
 impl Packet for KeepAlive {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -327,17 +404,11 @@ struct LockDifficulty {
 #[async_trait]
 impl Packet for LockDifficulty {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
 
-#[derive(Debug, Nom)]
-struct PacketPos {
-    x: f64,
-    y: f64,
-    z: f64,
-}
 #[derive(Debug, Nom)]
 struct PacketRot {
     yaw: f32,
@@ -346,21 +417,23 @@ struct PacketRot {
 
 #[derive(Debug, Nom)]
 struct PlayerPosition {
-    pos: PacketPos,
+    #[nom(Parse = "coords::vec3")]
+    pos: Vec3,
     #[nom(Parse = "boolean")]
     on_ground: bool,
 }
 #[async_trait]
 impl Packet for PlayerPosition {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
-        Ok(())
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        conn.moved(self.pos).await
     }
 }
 
 #[derive(Debug, Nom)]
 struct PlayerPositionAndRotation {
-    pos: PacketPos,
+    #[nom(Parse = "coords::vec3")]
+    pos: Vec3,
     rot: PacketRot,
     #[nom(Parse = "boolean")]
     on_ground: bool,
@@ -368,8 +441,9 @@ struct PlayerPositionAndRotation {
 #[async_trait]
 impl Packet for PlayerPositionAndRotation {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
-        Ok(())
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        conn.look = (self.rot.yaw, self.rot.pitch);
+        conn.moved(self.pos).await
     }
 }
 
@@ -383,7 +457,8 @@ struct PlayerRotation {
 #[async_trait]
 impl Packet for PlayerRotation {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        conn.look = (self.rot.yaw, self.rot.pitch);
         Ok(())
     }
 }
@@ -396,20 +471,21 @@ struct PlayerMovement {
 #[async_trait]
 impl Packet for PlayerMovement {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
 
 #[derive(Debug, Nom)]
 struct VehicleMove {
-    pos: PacketPos,
+    #[nom(Parse = "coords::vec3")]
+    pos: Vec3,
     rot: PacketRot,
 }
 #[async_trait]
 impl Packet for VehicleMove {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -424,7 +500,7 @@ struct SteerBoat {
 #[async_trait]
 impl Packet for SteerBoat {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -437,7 +513,7 @@ struct PickItem {
 #[async_trait]
 impl Packet for PickItem {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -452,7 +528,7 @@ struct CraftRecipeRequest {
 #[async_trait]
 impl Packet for CraftRecipeRequest {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -466,28 +542,55 @@ parse_impl_for_bitflags!(PlayerAbilities);
 #[async_trait]
 impl Packet for PlayerAbilities {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
 
 #[derive(Debug, Nom)]
 struct PlayerDigging {
-    #[nom(Parse = "map_opt(varint::<u32>, DiggingStatus::from_u32)")]
     status: DiggingStatus,
-    location: Position,
+    #[nom(Parse = "coords::block_pos")]
+    location: BlockPos,
     face: Direction,
 }
 #[async_trait]
 impl Packet for PlayerDigging {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
-        Ok(())
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        use DiggingStatus::*;
+        match self.status {
+            // the client expects these to be acknowledged, or else it keeps
+            // showing whatever it predicted
+            StartedDigging | CancelledDigging | FinishedDigging => {
+                let uuid = match &conn.player {
+                    Some(player) => player.uuid,
+                    None => return Ok(()),
+                };
+                let pos = self.location;
+                let finished = matches!(self.status, FinishedDigging);
+                let (state, successful) = conn.server.dig_block(uuid, pos, finished).await?;
+                conn.send_ack_player_digging(pos, state, self.status, successful)
+                    .await?;
+                Ok(())
+            }
+            DropItem | DropItemStack => {
+                if let Some(player) = &conn.player {
+                    let (yaw, pitch) = conn.look;
+                    let whole_stack = matches!(self.status, DropItemStack);
+                    conn.server
+                        .drop_item(player.uuid, whole_stack, yaw, pitch)
+                        .await?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
     }
 }
 
-#[derive(Debug, FromPrimitive)]
-enum DiggingStatus {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum DiggingStatus {
     StartedDigging,
     CancelledDigging,
     FinishedDigging,
@@ -496,12 +599,16 @@ enum DiggingStatus {
     FinishUsing,
     SwapItem,
 }
+impl Parse<&[u8]> for DiggingStatus {
+    fn parse(i: &[u8]) -> IResult<&[u8], Self> {
+        enum_varint(i)
+    }
+}
 
 #[derive(Debug, Nom)]
 struct EntityAction {
     #[nom(Parse = "varint")]
     entity_id: u32,
-    #[nom(Parse = "map_opt(varint::<u32>, EntityActionVariant::from_u32)")]
     action_id: EntityActionVariant,
     #[nom(Parse = "varint")]
     jump_boost: u32,
@@ -509,12 +616,12 @@ struct EntityAction {
 #[async_trait]
 impl Packet for EntityAction {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
 
-#[derive(Debug, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 enum EntityActionVariant {
     StartSneaking,
     StopSneaking,
@@ -526,6 +633,11 @@ enum EntityActionVariant {
     OpenHorseInventory,
     StartFlyingWithElytra,
 }
+impl Parse<&[u8]> for EntityActionVariant {
+    fn parse(i: &[u8]) -> IResult<&[u8], Self> {
+        enum_varint(i)
+    }
+}
 
 #[derive(Debug, Nom)]
 struct SteerVehicle {
@@ -536,7 +648,7 @@ struct SteerVehicle {
 #[async_trait]
 impl Packet for SteerVehicle {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -553,7 +665,7 @@ struct Pong(u32);
 #[async_trait]
 impl Packet for Pong {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -570,7 +682,7 @@ struct SetRecipeBookState {
 #[async_trait]
 impl Packet for SetRecipeBookState {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         Ok(())
     }
 }
@@ -582,7 +694,100 @@ struct SetDisplayedRecipe {
 #[async_trait]
 impl Packet for SetDisplayedRecipe {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Nom)]
+struct HeldItemChange {
+    slot: i16,
+}
+#[async_trait]
+impl Packet for HeldItemChange {
+    #[instrument(skip(conn))]
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        if let (Some(player), 0..=8) = (&conn.player, self.slot) {
+            conn.server
+                .held_item_changed(player.uuid, self.slot as u8)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Nom)]
+struct UpdateSign<'a> {
+    #[nom(Parse = "coords::block_pos")]
+    location: BlockPos,
+    #[nom(Parse = "var_str_with_max_length(384u32)")]
+    line_1: &'a str,
+    #[nom(Parse = "var_str_with_max_length(384u32)")]
+    line_2: &'a str,
+    #[nom(Parse = "var_str_with_max_length(384u32)")]
+    line_3: &'a str,
+    #[nom(Parse = "var_str_with_max_length(384u32)")]
+    line_4: &'a str,
+}
+#[async_trait]
+impl Packet for UpdateSign<'_> {
+    #[instrument(skip(conn))]
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        let player = match &conn.player {
+            Some(player) => player.uuid,
+            None => return Ok(()),
+        };
+        let lines = [self.line_1, self.line_2, self.line_3, self.line_4].map(String::from);
+        conn.server
+            .update_sign(player, self.location, lines)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Nom)]
+struct Animation {
+    hand: Hand,
+}
+#[async_trait]
+impl Packet for Animation {
+    #[instrument(skip(conn))]
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        if let Some(player) = &conn.player {
+            conn.server.swing_arm(player.uuid, self.hand).await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Nom)]
+struct PlayerBlockPlacement {
+    hand: Hand,
+    #[nom(Parse = "coords::block_pos")]
+    location: BlockPos,
+    #[nom(Parse = "enum_varint")]
+    face: Direction,
+    cursor_x: f32,
+    cursor_y: f32,
+    cursor_z: f32,
+    #[nom(Parse = "boolean")]
+    inside_block: bool,
+}
+#[async_trait]
+impl Packet for PlayerBlockPlacement {
+    #[instrument(skip(conn))]
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
+        let player = match &conn.player {
+            Some(player) => player.uuid,
+            None => return Ok(()),
+        };
+        let sign = conn
+            .server
+            .place_block(player, self.location, self.face, self.hand)
+            .await?;
+        if let Some(pos) = sign {
+            conn.open_sign_editor(pos).await?;
+        }
         Ok(())
     }
 }
@@ -590,29 +795,35 @@ impl Packet for SetDisplayedRecipe {
 impl Connection {
     #[instrument(skip(self))]
     pub async fn join_game(&mut self, player: Player) -> eyre::Result<()> {
-        self.server.join_game(player).await?;
+        self.ensure_state(ConnectionState::Play)?;
+        let entity_id = self.server.join_game(player, self.client.clone()).await?;
 
         //TODO
         let dimension_info = self.server.get_dimension_info().await?;
         // Join game
 
-        ResponseBuilder::new(0x26)
-            .add(0u32) // EID
+        // the dimension codec makes up most of the packet
+        ResponseBuilder::with_capacity(0x26, dimension_info.len() + 128)
+            .add(entity_id)
             .add(false) // not hardcore
-            .add(0u8) // survival
-            .add(-1i8) // no previous gamemode
+            .add(Gamemode::Survival)
+            .add(None::<Gamemode>) // no previous gamemode
             .add_many(&["hieronymus:wonderland"]) // world names
             .raw_data(dimension_info) // dimension codec and current dimension
             .add("hieronymus:wonderland") // current world name
             .add(rand::random::<u64>()) // hashed seed
             .varint(0u32) // max players (ignored)
-            .varint(10u32) // view distance
-            .add(false) // reduced debug info
+            .varint(u32::from(self.config.view_distance))
+            .add(self.config.reduced_debug_info)
             .add(true) // enable respawn screen
             .add(false) // is debug world
             .add(false) // is superflat
             .send(self)
             .await?;
+        // the difficulty can't be changed by players
+        packets::server_difficulty(Difficulty::Normal, true)
+            .send(self)
+            .await?;
 
         use AbsOrRel::*;
         self.player_position_and_look(
@@ -625,9 +836,10 @@ impl Connection {
         )
         .await?;
         // prematurely kick
-        // self.kick(
-        //     r#"{"text":"well... i haven't implemented like, the game yet lol. come back later XD"}"#
-        // ).await?;
+        // self.kick(&Chat::text(
+        //     "well... i haven't implemented like, the game yet lol. come back later XD",
+        // ))
+        // .await?;
 
         Ok(())
     }
@@ -642,6 +854,7 @@ impl Connection {
         pitch: AbsOrRel<f32>,
         dismount_vehicle: bool,
     ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
         let mut flags = 0;
         let x = x.unwrap_and_set_flag(0b00001, &mut flags);
         let y = y.unwrap_and_set_flag(0b00010, &mut flags);
@@ -662,21 +875,426 @@ impl Connection {
             .send(self)
             .await
     }
-}
 
-#[derive(Debug)]
-pub enum AbsOrRel<T> {
-    Absolute(T),
-    Relative(T),
+    /// Tells the client which block is at `location` after it dug there. The
+    /// fields have to match what the client sent, or else it rolls the block
+    /// back to what it was before.
+    #[instrument(skip(self))]
+    pub async fn send_ack_player_digging(
+        &mut self,
+        location: BlockPos,
+        block_state: u32,
+        status: DiggingStatus,
+        successful: bool,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::acknowledge_digging(location, block_state, status as u32, successful)
+            .send(self)
+            .await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn send_system_message(&mut self, message: &str) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        ResponseBuilder::new(0x0f)
+            .add(&Chat::text(message))
+            .add(1u8) // system message
+            .add(Uuid::nil()) // sender
+            .send(self)
+            .await
+    }
+
+    pub async fn add_boss_bar(
+        &mut self,
+        uuid: Uuid,
+        title: Chat,
+        health: f32,
+        color: BossBarColor,
+        division: BossBarDivision,
+        flags: BossBarFlags,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        let action = BossBarAction::Add {
+            title,
+            health,
+            color,
+            division,
+            flags,
+        };
+        self.update_boss_bar(uuid, &action).await
+    }
+    pub async fn update_boss_bar(
+        &mut self,
+        uuid: Uuid,
+        action: &BossBarAction,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::boss_bar(uuid, action).send(self).await
+    }
+    pub async fn remove_boss_bar(&mut self, uuid: Uuid) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        self.update_boss_bar(uuid, &BossBarAction::Remove).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn spawn_particle(
+        &mut self,
+        particle_id: i32,
+        long_distance: bool,
+        position: Vec3,
+        offset: Vec3,
+        max_speed: f32,
+        count: i32,
+        data: &ParticleData,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::particle(
+            particle_id,
+            long_distance,
+            position,
+            offset,
+            max_speed,
+            count,
+            data,
+        )?
+        .send(self)
+        .await
+    }
+    pub async fn send_explosion(
+        &mut self,
+        position: Vec3,
+        strength: f32,
+        affected_blocks: &[BlockPos],
+        player_motion: Vec3,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::explosion(position, strength, affected_blocks, player_motion)
+            .send(self)
+            .await
+    }
+    pub async fn open_sign_editor(&mut self, pos: BlockPos) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::open_sign_editor(pos).send(self).await
+    }
+    /// Changes the item in a slot, or the one carried on the cursor with
+    /// [`SlotTarget::Cursor`].
+    pub async fn send_set_slot(&mut self, target: SlotTarget, data: &Slot) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::set_slot_of(target, data)?.send(self).await
+    }
+    /// Shows an entity picking up `count` items from an item entity, which
+    /// then has to be destroyed.
+    pub async fn send_collect_item(
+        &mut self,
+        collected_id: i32,
+        collector_id: i32,
+        count: u32,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::collect_item(collected_id, collector_id, count)
+            .send(self)
+            .await
+    }
+    /// Makes the player view the world from the eyes of another entity, or
+    /// from their own again if `entity_id` is theirs.
+    pub async fn send_camera(&mut self, entity_id: i32) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::camera(entity_id).send(self).await
+    }
+    pub async fn send_entity_status(
+        &mut self,
+        entity_id: i32,
+        status: EntityStatus,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::entity_status(entity_id, status).send(self).await
+    }
+    pub async fn send_set_cooldown(&mut self, item_id: u32, ticks: u32) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::set_cooldown(item_id, ticks).send(self).await
+    }
+    pub async fn send_update_view_distance(&mut self, view_distance: u8) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::update_view_distance(view_distance)
+            .send(self)
+            .await
+    }
+    pub async fn send_tab_list(&mut self, header: Chat, footer: Chat) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::tab_list(&header, &footer).send(self).await
+    }
+    /// Leashes an entity to another one, or breaks its leash if `holder_id`
+    /// is `None`.
+    pub async fn send_attach_entity(
+        &mut self,
+        attached_id: i32,
+        holder_id: Option<i32>,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::attach_entity(attached_id, holder_id)
+            .send(self)
+            .await
+    }
+    pub async fn send_set_passengers(
+        &mut self,
+        vehicle_id: i32,
+        passenger_ids: &[i32],
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::set_passengers(vehicle_id, passenger_ids)
+            .send(self)
+            .await
+    }
+    /// Spawns an entity that isn't living, using Spawn Entity. `rotation` is
+    /// its yaw and pitch, and what `data` means depends on its type.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_spawn_entity(
+        &mut self,
+        entity_type: u32,
+        entity_id: i32,
+        uuid: Uuid,
+        position: Vec3,
+        rotation: (Angle, Angle),
+        data: i32,
+        velocity: Vec3,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::spawn_entity(
+            entity_id,
+            uuid,
+            entity_type,
+            position,
+            rotation,
+            data,
+            velocity,
+        )
+        .send(self)
+        .await
+    }
+    /// Spawns a mob or an armor stand, with its head facing the same way as
+    /// its body.
+    pub async fn send_spawn_living_entity(
+        &mut self,
+        entity_type: u32,
+        entity_id: i32,
+        uuid: Uuid,
+        position: Vec3,
+        rotation: (Angle, Angle),
+        velocity: Vec3,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::spawn_living_entity(
+            entity_id,
+            uuid,
+            entity_type,
+            position,
+            rotation,
+            rotation.0,
+            velocity,
+        )
+        .send(self)
+        .await
+    }
+    /// Changes attributes of an entity, like the movement speed of the
+    /// player.
+    pub async fn send_update_attributes(
+        &mut self,
+        entity_id: i32,
+        attributes: &[Attribute],
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::update_attributes(entity_id, attributes)
+            .send(self)
+            .await
+    }
+    pub async fn send_entity_effect(
+        &mut self,
+        entity_id: i32,
+        effect: &Effect,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::entity_effect(entity_id, effect).send(self).await
+    }
+    pub async fn send_remove_entity_effect(
+        &mut self,
+        entity_id: i32,
+        effect_id: u8,
+    ) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::remove_entity_effect(entity_id, effect_id)
+            .send(self)
+            .await
+    }
+    pub async fn send_world_border(&mut self, border: &WorldBorder) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::initialize_world_border(border).send(self).await
+    }
+    pub async fn send_teams(&mut self, team: &str, action: &TeamAction) -> eyre::Result<()> {
+        self.ensure_state(ConnectionState::Play)?;
+        packets::teams(team, action).send(self).await
+    }
+
+    async fn moved(&mut self, pos: Vec3) -> NetResult<()> {
+        if let Some(player) = &self.player {
+            self.server.player_moved(player.uuid, pos)?;
+        }
+        Ok(())
+    }
 }
-impl<T> AbsOrRel<T> {
-    pub fn unwrap_and_set_flag(self, flag: u8, flags: &mut u8) -> T {
-        match self {
-            Self::Absolute(t) => t,
-            Self::Relative(t) => {
-                *flags |= flag;
-                t
-            }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use nom_derive::Parse;
+    use tokio::io::AsyncReadExt;
+    use uuid::Uuid;
+
+    use super::{read_packet, DiggingStatus, EntityActionVariant};
+    use crate::{
+        data::{Position, SlotData},
+        math::BlockPos,
+        net::{packets::SlotTarget, Connection, ConnectionState, NetError, ResponseBuilder},
+        server::{Player, Server, ServerHook},
+        varint::varint,
+    };
+
+    #[test]
+    fn test_digging_status() {
+        assert_eq!(
+            DiggingStatus::parse(&[2]).unwrap().1,
+            DiggingStatus::FinishedDigging
+        );
+        assert_eq!(
+            DiggingStatus::parse(&[6]).unwrap().1,
+            DiggingStatus::SwapItem
+        );
+        assert!(DiggingStatus::parse(&[7]).is_err());
+
+        // a whole Player Digging packet is refused, instead of panicking
+        let packet = [0x1a, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert!(read_packet(&packet).is_ok());
+        let packet = [0x1a, 7, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert!(read_packet(&packet).is_err());
+    }
+
+    #[test]
+    fn test_entity_action() {
+        assert_eq!(
+            EntityActionVariant::parse(&[3]).unwrap().1,
+            EntityActionVariant::StartSprinting
+        );
+        assert!(EntityActionVariant::parse(&[9]).is_err());
+        assert!(EntityActionVariant::parse(&[0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ack_player_digging() {
+        let (mut conn, mut client) = Connection::for_tests(ServerHook::stub().0);
+        conn.state = ConnectionState::Play;
+        let pos = BlockPos::new(10, 64, -3);
+        conn.send_ack_player_digging(pos, 1, DiggingStatus::FinishedDigging, true)
+            .await
+            .unwrap();
+        conn.send_ack_player_digging(pos, 0, DiggingStatus::StartedDigging, false)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        let mut expected = vec![];
+        for (state, status, successful) in [(1u32, 2u32, true), (0, 0, false)] {
+            let mut ack = ResponseBuilder::new(0x08);
+            ack.add(Position::new(10, 64, -3))
+                .varint(state)
+                .varint(status)
+                .add(successful);
+            expected.extend(ack.to_frame());
+        }
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn test_set_slot() {
+        let (mut conn, mut client) = Connection::for_tests(ServerHook::stub().0);
+        conn.state = ConnectionState::Play;
+        let stone = Some(SlotData::new(1, 64));
+        conn.send_set_slot(SlotTarget::Cursor, &stone)
+            .await
+            .unwrap();
+        conn.send_set_slot(SlotTarget::Window { id: 0, slot: 36 }, &None)
+            .await
+            .unwrap();
+        conn.send_set_slot(SlotTarget::Inventory(9), &stone)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        let mut expected = vec![];
+        for (window, slot, data) in [(-1i8, -1i16, &stone), (0, 36, &None), (-2, 9, &stone)] {
+            let mut set_slot = ResponseBuilder::new(0x16);
+            set_slot
+                .add(window)
+                .varint(0u32)
+                .add(slot)
+                .try_add(data)
+                .unwrap();
+            expected.extend(set_slot.to_frame());
         }
+        assert_eq!(received, expected);
+        // the window ID is a signed byte, and the slot a signed short
+        assert_eq!(received[2..6], [0xff, 0, 0xff, 0xff]);
+    }
+
+    #[tokio::test]
+    async fn test_reduced_debug_info() {
+        for reduced in [false, true] {
+            let (mut conn, mut client) = Connection::for_tests(Server::spawn_for_tests());
+            conn.state = ConnectionState::Play;
+            Arc::get_mut(&mut conn.config).unwrap().reduced_debug_info = reduced;
+            let player = Player {
+                username: "player".into(),
+                uuid: Uuid::new_v4(),
+            };
+            conn.join_game(player).await.unwrap();
+            drop(conn);
+
+            let mut received = vec![];
+            client.read_to_end(&mut received).await.unwrap();
+            let (received, len) = varint::<u32>(&received).unwrap();
+            let join_game = &received[..len as usize];
+            assert_eq!(join_game[0], 0x26);
+            // followed by the respawn screen, debug world and superflat flags
+            assert_eq!(join_game[join_game.len() - 4..], [reduced as u8, 1, 0, 0]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_join_game_outside_play() {
+        let (server, mut events) = ServerHook::stub();
+        let (mut conn, mut client) = Connection::for_tests(server);
+        conn.state = ConnectionState::Login;
+        let player = Player {
+            username: "player".into(),
+            uuid: Uuid::new_v4(),
+        };
+        let e = conn.join_game(player).await.unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<NetError>(),
+            Some(NetError::WrongState {
+                expected: ConnectionState::Play,
+                actual: ConnectionState::Login,
+            })
+        ));
+        drop(conn);
+
+        // neither the client nor the server heard of it
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        assert!(received.is_empty());
+        assert!(events.try_recv().is_err());
     }
 }
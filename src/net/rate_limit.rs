@@ -0,0 +1,96 @@
+//! Token buckets, which limit how often a client can do something while still
+//! allowing short bursts.
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimit {
+    Allowed,
+    /// Too many actions were done recently, so this one should be dropped.
+    Throttled,
+    /// The client kept going long after being throttled, and should be kicked.
+    Exceeded,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// How many actions can be done in a burst.
+    capacity: f64,
+    /// How many actions are allowed per second in the long run.
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+    /// How many actions were throttled since the last allowed one.
+    strikes: u32,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64, burst: u32, now: Instant) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            capacity,
+            rate,
+            tokens: capacity,
+            last_refill: now,
+            strikes: 0,
+        }
+    }
+
+    /// Takes a token for an action done at `now`.
+    ///
+    /// The client is considered to be exceeding the limit once it got throttled
+    /// as many times in a row as the size of a burst.
+    pub fn check(&mut self, now: Instant) -> RateLimit {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            self.strikes = 0;
+            RateLimit::Allowed
+        } else {
+            self.strikes += 1;
+            if f64::from(self.strikes) > self.capacity {
+                RateLimit::Exceeded
+            } else {
+                RateLimit::Throttled
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{RateLimit, RateLimiter};
+
+    #[test]
+    fn test_rate_limit() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(2.0, 3, start);
+
+        // a burst is fine, but not more
+        for _ in 0..3 {
+            assert_eq!(limiter.check(start), RateLimit::Allowed);
+        }
+        assert_eq!(limiter.check(start), RateLimit::Throttled);
+
+        // tokens come back over time
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.check(later), RateLimit::Allowed);
+        assert_eq!(limiter.check(later), RateLimit::Throttled);
+
+        // keeping on after being throttled gets the client kicked
+        assert_eq!(limiter.check(later), RateLimit::Throttled);
+        assert_eq!(limiter.check(later), RateLimit::Throttled);
+        assert_eq!(limiter.check(later), RateLimit::Exceeded);
+
+        // the bucket doesn't fill up past its capacity
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_eq!(limiter.check(much_later), RateLimit::Allowed);
+        }
+        assert_eq!(limiter.check(much_later), RateLimit::Throttled);
+    }
+}
@@ -4,7 +4,7 @@ use tracing::{instrument, trace};
 
 use crate::match_id_and_forward;
 
-use super::{BoxedPacket, Connection, Packet, ResponseBuilder};
+use super::{BoxedPacket, Connection, NetResult, Packet, ResponseBuilder};
 use async_trait::async_trait;
 
 pub fn read_packet(input: &[u8]) -> IResult<&[u8], BoxedPacket<'_>> {
@@ -20,7 +20,7 @@ struct Status;
 #[async_trait]
 impl Packet for Status {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         let status = conn.server.get_server_status().await?;
         trace!(?status);
 
@@ -29,14 +29,114 @@ impl Packet for Status {
     }
 }
 
+/// Sent by the client to measure its latency, usually after a [`Status`]
+/// request, but some clients skip that and only ping.
 #[derive(Debug, Nom)]
 struct Ping(u64);
 #[async_trait]
 impl Packet for Ping {
     #[instrument(skip(conn))]
-    async fn handle(&self, conn: &mut Connection) -> eyre::Result<()> {
+    async fn handle(&self, conn: &mut Connection) -> NetResult<()> {
         ResponseBuilder::new(1).add(self.0).send(conn).await?;
+        // the status flow ends with the pong
+        conn.close();
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nom::multi::length_data;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+        task::JoinHandle,
+    };
+
+    use super::Ping;
+    use crate::{
+        net::{Connection, Packet, ResponseBuilder},
+        server::{Server, ServerHook},
+        varint::varint,
+    };
+
+    /// Starts a connection to a running server, returning the client's end of
+    /// it.
+    fn connect() -> (DuplexStream, JoinHandle<eyre::Result<()>>) {
+        let (conn, client) = Connection::for_tests(Server::spawn_for_tests());
+        (client, tokio::spawn(conn.connection_loop()))
+    }
+
+    /// Prefixes packets with their length, like they are sent over the wire.
+    fn frame(packets: &[ResponseBuilder]) -> Vec<u8> {
+        packets.iter().flat_map(ResponseBuilder::to_frame).collect()
+    }
+
+    /// A handshake switching to the status state.
+    fn handshake() -> ResponseBuilder {
+        let mut handshake = ResponseBuilder::new(0x00);
+        handshake
+            .varint(756u32)
+            .add("localhost")
+            .add(25565u16)
+            .varint(1u32);
+        handshake
+    }
+
+    fn ping(payload: u64) -> ResponseBuilder {
+        let mut ping = ResponseBuilder::new(0x01);
+        ping.add(payload);
+        ping
+    }
+
+    #[tokio::test]
+    async fn test_ping_closes_connection() {
+        let (mut client, conn) = connect();
+        let mut status = ResponseBuilder::new(0x00);
+        status.add("ignored");
+        let packets = frame(&[
+            handshake(),
+            ResponseBuilder::new(0x00),
+            ping(0x1234_5678_9abc_def0),
+            status,
+        ]);
+        client.write_all(&packets).await.unwrap();
+
+        // the server closes its end after the pong, ignoring what came next
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        conn.await.unwrap().unwrap();
+
+        let (rest, response) = length_data(varint::<u32>)(received.as_slice()).unwrap();
+        assert_eq!(response[0], 0x00);
+        let (rest, pong) = length_data(varint::<u32>)(rest).unwrap();
+        assert_eq!(pong, [0x01, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0]);
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ping_without_status_request() {
+        let (mut client, conn) = connect();
+        let packets = frame(&[handshake(), ping(42)]);
+        client.write_all(&packets).await.unwrap();
+
+        let mut received = vec![];
+        client.read_to_end(&mut received).await.unwrap();
+        conn.await.unwrap().unwrap();
+
+        let (rest, pong) = length_data(varint::<u32>)(received.as_slice()).unwrap();
+        assert_eq!(pong, [0x01, 0, 0, 0, 0, 0, 0, 0, 42]);
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ping_handler() {
+        let (mut conn, sink) = Connection::capturing(ServerHook::stub().0);
+        Ping(42).handle(&mut conn).await.unwrap();
+
+        let mut pong = ResponseBuilder::new(1);
+        pong.add(42u64);
+        assert_eq!(sink.packets(), [pong]);
+        assert!(conn.closed);
+    }
+}
@@ -3,12 +3,14 @@
 use std::ops::RangeFrom;
 
 use nom::{
+    bytes::streaming::take,
     combinator::{map, map_opt, map_res},
     error::ParseError,
     multi::length_data,
     number::streaming::be_u8,
     IResult, InputIter, InputLength, Parser, Slice, ToUsize,
 };
+use num_traits::FromPrimitive;
 
 use crate::{
     net::ConnectionState,
@@ -17,7 +19,7 @@ use crate::{
 
 #[macro_export]
 macro_rules! match_id_and_forward {
-    {$input:expr; $($id:expr => $ty:ty),*} => {{
+    {$input:expr; $($id:pat => $ty:ty),*} => {{
         use nom::{Err::Failure, error::{ErrorKind, make_error}};
         use tracing::trace;
         let input = $input;
@@ -95,6 +97,16 @@ pub fn var_bytes(i: &[u8]) -> IResult<&[u8], &[u8]> {
     length_data(varint::<u32>)(i)
 }
 
+/// A parser that reads a fixed number of bytes into an array, like a verify
+/// token.
+pub fn fixed_bytes<const N: usize>(i: &[u8]) -> IResult<&[u8], [u8; N]> {
+    map(take(N), |bytes: &[u8]| {
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        array
+    })(i)
+}
+
 /// Returns a parser that reads a variable-length UTF-8 string with a maximum
 /// length of 32767 bytes.
 ///
@@ -128,6 +140,14 @@ where
     }
 }
 
+/// A parser that reads an enum from its discriminant, encoded as a
+/// [variable-length integer](varint).
+///
+/// Discriminants that don't match any variant trigger an error in the parser.
+pub fn enum_varint<T: FromPrimitive>(i: &[u8]) -> IResult<&[u8], T> {
+    map_opt(varint::<u32>, T::from_u32)(i)
+}
+
 /// Reads a [`ConnectionState`].
 ///
 /// Only used with handshake packets to determine the state to progress to.
@@ -143,7 +163,34 @@ pub fn connection_state(i: &[u8]) -> IResult<&[u8], ConnectionState> {
 
 #[cfg(test)]
 mod tests {
-    use crate::nom::var_str;
+    use crate::{
+        data::Hand,
+        nom::{enum_varint, fixed_bytes, var_str},
+    };
+
+    #[test]
+    fn test_fixed_bytes() {
+        assert_eq!(
+            fixed_bytes::<8>(b"\x01\x02\x03\x04\x05\x06\x07\x08\x09").unwrap(),
+            (&b"\x09"[..], [1, 2, 3, 4, 5, 6, 7, 8])
+        );
+        assert!(matches!(
+            fixed_bytes::<8>(b"\x01\x02\x03"),
+            Err(nom::Err::Incomplete(_))
+        ));
+        assert_eq!(fixed_bytes::<0>(b"").unwrap(), (&b""[..], []));
+    }
+
+    #[test]
+    fn test_enum_varint() {
+        assert_eq!(
+            enum_varint::<Hand>(b"\x01").unwrap(),
+            (&b""[..], Hand::Offhand)
+        );
+        assert!(enum_varint::<Hand>(b"\x02").is_err());
+        // the discriminant is a varint, not a single byte
+        assert!(enum_varint::<Hand>(b"\x80\x01").is_err());
+    }
 
     #[test]
     fn test_read_var_str() {
@@ -0,0 +1,58 @@
+//! Registries mapping identifiers to the numeric IDs used by the protocol.
+//!
+//! The registries are loaded from the reports generated by the vanilla
+//! server's data generator (`java -cp server.jar net.minecraft.data.Main
+//! --reports`).
+pub mod block;
+pub mod entity;
+pub mod item;
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::data::{Identifier, ParseIdentifierError};
+
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error("Malformed report: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Invalid identifier {0}: {1}")]
+    InvalidIdentifier(String, ParseIdentifierError),
+}
+
+/// The registries report (`registries.json`), with the registries the server
+/// knows about.
+#[derive(Debug, Deserialize)]
+struct RegistriesReport {
+    #[serde(rename = "minecraft:item", default)]
+    item: RegistryReport,
+    #[serde(rename = "minecraft:entity_type", default)]
+    entity_type: RegistryReport,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RegistryReport {
+    entries: HashMap<String, EntryReport>,
+}
+impl RegistryReport {
+    /// Returns the identifier and ID of every entry.
+    fn entries(self) -> Result<Vec<(Identifier, u32)>, RegistryError> {
+        self.entries
+            .into_iter()
+            .map(|(name, entry)| {
+                let identifier = Identifier::try_from(name.as_str())
+                    .map_err(|e| RegistryError::InvalidIdentifier(name.clone(), e))?;
+                Ok((identifier, entry.protocol_id))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EntryReport {
+    protocol_id: u32,
+}
@@ -0,0 +1,231 @@
+//! Block states.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use super::RegistryError;
+use crate::data::Identifier;
+
+/// The state ID of `minecraft:air`, which is what any block that hasn't been
+/// set to anything else is.
+pub const AIR: u32 = 0;
+
+/// Default state IDs of some common blocks, as of 1.17.1, for when no blocks
+/// report is available.
+const DEFAULT_STATES: &[(&str, u32)] = &[
+    ("air", AIR),
+    ("stone", 1),
+    ("granite", 2),
+    ("polished_granite", 3),
+    ("diorite", 4),
+    ("polished_diorite", 5),
+    ("andesite", 6),
+    ("polished_andesite", 7),
+    ("grass_block", 9),
+    ("dirt", 10),
+    ("coarse_dirt", 11),
+    ("podzol", 13),
+    ("cobblestone", 14),
+    ("oak_planks", 15),
+    ("spruce_planks", 16),
+    ("birch_planks", 17),
+    ("jungle_planks", 18),
+    ("acacia_planks", 19),
+    ("dark_oak_planks", 20),
+    ("bedrock", 33),
+    ("sand", 66),
+    ("red_sand", 67),
+    ("gravel", 68),
+];
+
+/// The properties of a block state, like `axis=y` for logs.
+pub type Properties = BTreeMap<String, String>;
+
+/// Maps blocks and their properties to numeric state IDs.
+#[derive(Debug)]
+pub struct BlockRegistry {
+    blocks: HashMap<Identifier, Block>,
+}
+
+#[derive(Debug)]
+struct Block {
+    default_state: u32,
+    states: Vec<(Properties, u32)>,
+}
+
+impl BlockRegistry {
+    /// Creates a registry with only the default states of a few common blocks.
+    pub fn new() -> Self {
+        let blocks = DEFAULT_STATES
+            .iter()
+            .map(|&(path, id)| {
+                let block = Block {
+                    default_state: id,
+                    states: vec![(Properties::new(), id)],
+                };
+                (Identifier::minecraft(path), block)
+            })
+            .collect();
+        Self { blocks }
+    }
+
+    /// Loads the registry from a blocks report (`blocks.json`).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RegistryError> {
+        Self::from_report(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_report(json: &str) -> Result<Self, RegistryError> {
+        let report: HashMap<String, BlockReport> = serde_json::from_str(json)?;
+        let blocks = report
+            .into_iter()
+            .map(|(name, block)| {
+                let id = Identifier::try_from(name.as_str())
+                    .map_err(|e| RegistryError::InvalidIdentifier(name.clone(), e))?;
+                // states without a `default` flag only exist for blocks that have
+                // a single state
+                let default_state = block
+                    .states
+                    .iter()
+                    .find(|s| s.default)
+                    .or_else(|| block.states.first())
+                    .map_or(AIR, |s| s.id);
+                let states = block
+                    .states
+                    .into_iter()
+                    .map(|s| (s.properties, s.id))
+                    .collect();
+                Ok((
+                    id,
+                    Block {
+                        default_state,
+                        states,
+                    },
+                ))
+            })
+            .collect::<Result<_, RegistryError>>()?;
+        Ok(Self { blocks })
+    }
+
+    /// Returns the ID of the default state of a block, if it is known.
+    pub fn default_state(&self, block: &Identifier) -> Option<u32> {
+        self.blocks.get(block).map(|b| b.default_state)
+    }
+
+    /// Returns the ID of the state of a block with the given properties, with
+    /// every other property taking its default value.
+    ///
+    /// Returns [`None`] if the block is unknown, or if it doesn't have one of
+    /// the properties or values.
+    pub fn state(&self, block: &Identifier, properties: &[(&str, &str)]) -> Option<u32> {
+        let block = self.blocks.get(block)?;
+        let (defaults, _) = block
+            .states
+            .iter()
+            .find(|(_, id)| *id == block.default_state)?;
+
+        let mut wanted = defaults.clone();
+        for &(key, value) in properties {
+            *wanted.get_mut(key)? = value.to_string();
+        }
+        block
+            .states
+            .iter()
+            .find(|(props, _)| *props == wanted)
+            .map(|(_, id)| *id)
+    }
+}
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockReport {
+    states: Vec<StateReport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StateReport {
+    id: u32,
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    properties: Properties,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockRegistry, AIR};
+    use crate::data::Identifier;
+
+    // an excerpt of the 1.17.1 blocks report
+    const REPORT: &str = r#"{
+        "minecraft:air": {
+            "states": [{ "id": 0, "default": true }]
+        },
+        "minecraft:stone": {
+            "states": [{ "id": 1, "default": true }]
+        },
+        "minecraft:grass_block": {
+            "properties": { "snowy": ["true", "false"] },
+            "states": [
+                { "properties": { "snowy": "true" }, "id": 8 },
+                { "properties": { "snowy": "false" }, "id": 9, "default": true }
+            ]
+        },
+        "minecraft:oak_log": {
+            "properties": { "axis": ["x", "y", "z"] },
+            "states": [
+                { "properties": { "axis": "x" }, "id": 76 },
+                { "properties": { "axis": "y" }, "id": 77, "default": true },
+                { "properties": { "axis": "z" }, "id": 78 }
+            ]
+        }
+    }"#;
+
+    fn id(s: &str) -> Identifier {
+        Identifier::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn test_default_state() {
+        let registry = BlockRegistry::from_report(REPORT).unwrap();
+        assert_eq!(registry.default_state(&id("minecraft:air")), Some(AIR));
+        assert_eq!(registry.default_state(&id("minecraft:stone")), Some(1));
+        assert_eq!(
+            registry.default_state(&id("minecraft:grass_block")),
+            Some(9)
+        );
+        assert_eq!(registry.default_state(&id("minecraft:oak_log")), Some(77));
+        assert_eq!(registry.default_state(&id("minecraft:not_a_block")), None);
+
+        // the built-in fallback agrees with the report
+        let builtin = BlockRegistry::new();
+        for block in ["minecraft:air", "minecraft:stone", "minecraft:grass_block"] {
+            assert_eq!(
+                builtin.default_state(&id(block)),
+                registry.default_state(&id(block))
+            );
+        }
+    }
+
+    #[test]
+    fn test_state_with_properties() {
+        let registry = BlockRegistry::from_report(REPORT).unwrap();
+        let log = id("minecraft:oak_log");
+        assert_eq!(registry.state(&log, &[]), Some(77));
+        assert_eq!(registry.state(&log, &[("axis", "x")]), Some(76));
+        assert_eq!(registry.state(&log, &[("axis", "z")]), Some(78));
+        assert_eq!(registry.state(&log, &[("axis", "w")]), None);
+        assert_eq!(registry.state(&log, &[("snowy", "true")]), None);
+        assert_eq!(
+            registry.state(&id("minecraft:grass_block"), &[("snowy", "true")]),
+            Some(8)
+        );
+    }
+}
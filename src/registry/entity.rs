@@ -0,0 +1,151 @@
+//! Entity types.
+use std::{collections::HashMap, fs, path::Path};
+
+use super::{RegistriesReport, RegistryError};
+use crate::data::Identifier;
+
+/// Maps entity type identifiers to their numeric IDs, and back.
+#[derive(Debug, Default)]
+pub struct EntityTypeRegistry {
+    ids: HashMap<Identifier, u32>,
+    identifiers: HashMap<u32, Identifier>,
+}
+
+impl EntityTypeRegistry {
+    /// Creates a registry without any entity types.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the registry from a registries report (`registries.json`).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RegistryError> {
+        Self::from_report(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_report(json: &str) -> Result<Self, RegistryError> {
+        let report: RegistriesReport = serde_json::from_str(json)?;
+        let mut registry = Self::new();
+        for (identifier, id) in report.entity_type.entries()? {
+            registry.ids.insert(identifier.clone(), id);
+            registry.identifiers.insert(id, identifier);
+        }
+        Ok(registry)
+    }
+
+    pub fn id(&self, entity_type: &Identifier) -> Option<u32> {
+        self.ids.get(entity_type).copied()
+    }
+    pub fn identifier(&self, id: u32) -> Option<&Identifier> {
+        self.identifiers.get(&id)
+    }
+}
+
+/// Which packet spawns entities of a type on the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnKind {
+    /// Spawn Entity, for objects like boats, minecarts and projectiles.
+    Object,
+    /// Spawn Living Entity, for mobs and armor stands.
+    Living,
+    ExperienceOrb,
+    Painting,
+    Player,
+}
+
+/// Returns which packet spawns entities of the given type.
+///
+/// The reports don't include this, so anything that isn't known to be an
+/// object is taken for a living entity.
+pub fn spawn_kind(entity_type: &Identifier) -> SpawnKind {
+    const OBJECTS: &[&str] = &[
+        "area_effect_cloud",
+        "arrow",
+        "boat",
+        "dragon_fireball",
+        "egg",
+        "end_crystal",
+        "ender_pearl",
+        "evoker_fangs",
+        "experience_bottle",
+        "eye_of_ender",
+        "falling_block",
+        "fireball",
+        "firework_rocket",
+        "fishing_bobber",
+        "glow_item_frame",
+        "item",
+        "item_frame",
+        "leash_knot",
+        "lightning_bolt",
+        "llama_spit",
+        "marker",
+        "potion",
+        "shulker_bullet",
+        "small_fireball",
+        "snowball",
+        "spectral_arrow",
+        "tnt",
+        "trident",
+        "wither_skull",
+    ];
+
+    if entity_type.namespace != Identifier::DEFAULT_NAMESPACE {
+        return SpawnKind::Living;
+    }
+    match entity_type.path.as_str() {
+        "experience_orb" => SpawnKind::ExperienceOrb,
+        "painting" => SpawnKind::Painting,
+        "player" => SpawnKind::Player,
+        path if OBJECTS.contains(&path) || path.ends_with("minecart") => SpawnKind::Object,
+        _ => SpawnKind::Living,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spawn_kind, EntityTypeRegistry, SpawnKind};
+    use crate::data::Identifier;
+
+    // an excerpt of the 1.17.1 registries report
+    const REPORT: &str = r#"{
+        "minecraft:entity_type": {
+            "default": "minecraft:pig",
+            "protocol_id": 6,
+            "entries": {
+                "minecraft:area_effect_cloud": { "protocol_id": 0 },
+                "minecraft:item": { "protocol_id": 41 },
+                "minecraft:pig": { "protocol_id": 64 },
+                "minecraft:zombie": { "protocol_id": 107 }
+            }
+        },
+        "minecraft:item": {
+            "protocol_id": 31,
+            "entries": {
+                "minecraft:stone": { "protocol_id": 1 }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_entity_type_ids() {
+        let registry = EntityTypeRegistry::from_report(REPORT).unwrap();
+        let pig = Identifier::minecraft("pig");
+
+        assert_eq!(registry.id(&pig), Some(64));
+        assert_eq!(registry.identifier(64), Some(&pig));
+        assert_eq!(registry.id(&Identifier::minecraft("item")), Some(41));
+        assert_eq!(registry.id(&Identifier::minecraft("stone")), None);
+        assert_eq!(registry.identifier(108), None);
+    }
+
+    #[test]
+    fn test_spawn_kind() {
+        let kind = |path| spawn_kind(&Identifier::minecraft(path));
+        assert_eq!(kind("zombie"), SpawnKind::Living);
+        assert_eq!(kind("armor_stand"), SpawnKind::Living);
+        assert_eq!(kind("boat"), SpawnKind::Object);
+        assert_eq!(kind("chest_minecart"), SpawnKind::Object);
+        assert_eq!(kind("painting"), SpawnKind::Painting);
+        assert_eq!(kind("player"), SpawnKind::Player);
+    }
+}
@@ -0,0 +1,161 @@
+//! Items.
+use std::{collections::HashMap, fs, path::Path};
+
+use super::{RegistriesReport, RegistryError};
+use crate::data::Identifier;
+
+/// Maps item identifiers to their numeric IDs, and back.
+#[derive(Debug, Default)]
+pub struct ItemRegistry {
+    ids: HashMap<Identifier, u32>,
+    identifiers: HashMap<u32, Identifier>,
+}
+
+impl ItemRegistry {
+    /// Creates a registry without any items.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the registry from a registries report (`registries.json`).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RegistryError> {
+        Self::from_report(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_report(json: &str) -> Result<Self, RegistryError> {
+        let report: RegistriesReport = serde_json::from_str(json)?;
+        let mut registry = Self::new();
+        for (identifier, id) in report.item.entries()? {
+            registry.ids.insert(identifier.clone(), id);
+            registry.identifiers.insert(id, identifier);
+        }
+        Ok(registry)
+    }
+
+    pub fn id(&self, item: &Identifier) -> Option<u32> {
+        self.ids.get(item).copied()
+    }
+    pub fn identifier(&self, id: u32) -> Option<&Identifier> {
+        self.identifiers.get(&id)
+    }
+}
+
+/// Returns how many of an item fit in a single slot.
+///
+/// The reports don't include this, so it's worked out from the item's name.
+pub fn max_stack_size(item: &Identifier) -> u8 {
+    const UNSTACKABLE_SUFFIXES: &[&str] = &[
+        "_sword",
+        "_pickaxe",
+        "_axe",
+        "_shovel",
+        "_hoe",
+        "_helmet",
+        "_chestplate",
+        "_leggings",
+        "_boots",
+        "_horse_armor",
+        "_boat",
+        "minecart",
+        "_bucket",
+        "_bed",
+        "shulker_box",
+        "_stew",
+        "_soup",
+        "potion",
+        "_on_a_stick",
+    ];
+    const UNSTACKABLE: &[&str] = &[
+        "bow",
+        "crossbow",
+        "trident",
+        "shield",
+        "elytra",
+        "fishing_rod",
+        "flint_and_steel",
+        "shears",
+        "saddle",
+        "totem_of_undying",
+        "enchanted_book",
+        "writable_book",
+        "cake",
+        "debug_stick",
+        "knowledge_book",
+        "spyglass",
+        "bundle",
+    ];
+    const STACKS_OF_16: &[&str] = &[
+        "ender_pearl",
+        "snowball",
+        "egg",
+        "bucket",
+        "honey_bottle",
+        "armor_stand",
+        "written_book",
+    ];
+
+    if item.namespace != Identifier::DEFAULT_NAMESPACE {
+        return 64;
+    }
+    let path = item.path.as_str();
+    if STACKS_OF_16.contains(&path) || path.ends_with("_sign") || path.ends_with("_banner") {
+        16
+    } else if UNSTACKABLE.contains(&path)
+        || path.starts_with("music_disc_")
+        || UNSTACKABLE_SUFFIXES.iter().any(|s| path.ends_with(s))
+    {
+        1
+    } else {
+        64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{max_stack_size, ItemRegistry};
+    use crate::data::Identifier;
+
+    // an excerpt of the 1.17.1 registries report
+    const REPORT: &str = r#"{
+        "minecraft:item": {
+            "default": "minecraft:air",
+            "protocol_id": 31,
+            "entries": {
+                "minecraft:air": { "protocol_id": 0 },
+                "minecraft:stone": { "protocol_id": 1 },
+                "minecraft:diamond_sword": { "protocol_id": 598 }
+            }
+        },
+        "minecraft:sound_event": {
+            "protocol_id": 0,
+            "entries": {
+                "minecraft:ambient.cave": { "protocol_id": 0 }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_item_ids() {
+        let registry = ItemRegistry::from_report(REPORT).unwrap();
+        let sword = Identifier::minecraft("diamond_sword");
+
+        assert_eq!(registry.id(&sword), Some(598));
+        assert_eq!(registry.identifier(598), Some(&sword));
+        assert_eq!(registry.id(&Identifier::minecraft("air")), Some(0));
+        assert_eq!(registry.id(&Identifier::minecraft("ambient.cave")), None);
+        assert_eq!(registry.identifier(599), None);
+    }
+
+    #[test]
+    fn test_max_stack_size() {
+        let size = |path| max_stack_size(&Identifier::minecraft(path));
+        assert_eq!(size("stone"), 64);
+        assert_eq!(size("diamond_sword"), 1);
+        assert_eq!(size("water_bucket"), 1);
+        assert_eq!(size("bucket"), 16);
+        assert_eq!(size("oak_sign"), 16);
+        assert_eq!(size("music_disc_cat"), 1);
+        assert_eq!(size("potion"), 1);
+        assert_eq!(size("glass_pane"), 64);
+    }
+}
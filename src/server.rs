@@ -1,25 +1,128 @@
+mod allowlist;
+pub mod attribute;
+pub mod boss_bar;
+mod chat_filter;
+pub mod command;
 pub mod dimension;
+pub mod effect;
+pub mod entity;
+mod entity_id;
+mod inventory;
+pub mod item_entity;
+mod motd;
+pub mod scoreboard;
+mod shutdown;
+pub mod team;
+pub mod tracker;
+mod world;
+pub mod world_border;
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use eyre::eyre;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::sync::{mpsc, oneshot};
-use tracing::{debug, instrument, trace};
+use thiserror::Error;
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
+    time::{self, MissedTickBehavior},
+};
+use tracing::{debug, info, instrument, trace, warn};
 use uuid::Uuid;
 
-use crate::config::Config;
+use crate::{
+    audit::{AuditEntry, AuditWriter, DisconnectReason},
+    chat::{Chat, ChatMode, ChatPosition},
+    config::Config,
+    data::{Animation, Arm, Direction, Hand, Identifier},
+    math::{AbsOrRel, BlockPos, Vec3},
+    net::{compression::PreparedPacket, is_valid_username, packets, ClientHook, ResponseBuilder},
+    registry::{
+        block::{BlockRegistry, AIR},
+        entity::{spawn_kind, EntityTypeRegistry, SpawnKind},
+        item::ItemRegistry,
+        RegistryError,
+    },
+};
 
-use self::dimension::DimensionManager;
+use self::{
+    allowlist::Allowlist,
+    attribute::Attribute,
+    boss_bar::{BossBar, BossBarAction, BossBars},
+    chat_filter::ChatFilter,
+    command::{CommandError, SourceKind},
+    dimension::DimensionManager,
+    effect::Effect,
+    entity::Entity,
+    entity_id::EntityIds,
+    inventory::Inventory,
+    item_entity::ItemEntity,
+    scoreboard::{DisplaySlot, Objective, Scoreboard},
+    shutdown::{Countdown, Step},
+    team::{Team, TeamAction, Teams},
+    tracker::{clamp_view_distance, tracking_range, EntityTracker, Tracking},
+    world::{BlockEntity, World, MAX_EXPLOSION_RADIUS},
+    world_border::WorldBorder,
+};
 pub struct Server {
     rx: mpsc::Receiver<ServerEvent>,
+    /// Sends the server the results of work done off the server loop, like
+    /// reading files, which it doesn't keep alive on its own.
+    loopback: mpsc::UnboundedSender<Notification>,
+    loopback_rx: mpsc::UnboundedReceiver<Notification>,
     config: Arc<Config>,
     version: Version,
-    players: Vec<Player>,
+    players: Vec<OnlinePlayer>,
+    /// The number of players that are online or about to join, which is what
+    /// `max_players` is enforced against.
+    player_count: Arc<AtomicUsize>,
+    /// The players that were allowed to log in, but haven't joined the game
+    /// yet, whose slots are reserved.
+    logging_in: Vec<Player>,
     favicon: Option<String>,
+    audit_log: AuditWriter,
+    entity_ids: EntityIds,
+    allowlist: Allowlist,
+    chat_filter: ChatFilter,
+    /// The seed of the world, either configured or picked at random.
+    seed: i64,
+    started: Instant,
+    /// The view distance of the server, which starts out as the configured
+    /// one but can be changed while it runs.
+    view_distance: u8,
+    /// Whether only operators can join, which starts out as configured but can
+    /// be changed while the server runs.
+    maintenance: bool,
+    /// The countdown to stopping the server, if it is stopping.
+    shutdown: Option<Countdown>,
 
     dimension_manager: DimensionManager,
+    entity_tracker: EntityTracker,
+    block_registry: BlockRegistry,
+    item_registry: ItemRegistry,
+    entity_type_registry: EntityTypeRegistry,
+    world: World,
+    /// The item entities in the world, by entity ID.
+    items: HashMap<i32, ItemEntity>,
+    /// The other entities in the world apart from players, by entity ID.
+    entities: HashMap<i32, Entity>,
+    world_border: WorldBorder,
+    boss_bars: BossBars,
+    scoreboard: Scoreboard,
+    teams: Teams,
+    tab_list_header: Chat,
+    tab_list_footer: Chat,
 }
 
 impl Server {
@@ -27,98 +130,1881 @@ impl Server {
         let favicon_path = &config.favicon_path;
         trace!(?favicon_path);
         let favicon = match tokio::fs::read(favicon_path).await {
-            Ok(image) => {
-                let mut favicon =
-                    String::with_capacity("data:image/png;base64,".len() + image.len() * 4 / 3 + 4);
-                favicon.push_str("data:image/png;base64,");
-                base64::encode_config_buf(image, base64::STANDARD, &mut favicon);
-                Some(favicon)
-            }
+            Ok(image) => Some(favicon_data_url(&image)),
             Err(_) => None,
         };
+        let audit_log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.audit_log_path)?;
+        let allowlist = match Allowlist::load(&config.allowlist_path, config.enforce_allowlist) {
+            Ok(allowlist) => allowlist,
+            Err(e) => {
+                warn!(?e, "Failed to load the allowlist, it will be empty");
+                Allowlist::new(config.enforce_allowlist)
+            }
+        };
+        info!(
+            enforced = allowlist.is_enforced(),
+            players = allowlist.len(),
+            "Loaded the allowlist"
+        );
+        let blocks = match BlockRegistry::load(&config.blocks_report_path) {
+            Ok(registry) => registry,
+            Err(e) => {
+                warn!(
+                    ?e,
+                    "Failed to load the blocks report, only common blocks will be known"
+                );
+                BlockRegistry::new()
+            }
+        };
+        let items = match ItemRegistry::load(&config.registries_report_path) {
+            Ok(registry) => registry,
+            Err(e) => {
+                warn!(
+                    ?e,
+                    "Failed to load the registries report, no items will be known"
+                );
+                ItemRegistry::new()
+            }
+        };
+        let entity_types = match EntityTypeRegistry::load(&config.registries_report_path) {
+            Ok(registry) => registry,
+            Err(e) => {
+                warn!(
+                    ?e,
+                    "Failed to load the registries report, no entity types will be known"
+                );
+                EntityTypeRegistry::new()
+            }
+        };
+        let registries = Registries {
+            blocks,
+            items,
+            entity_types,
+        };
+
+        Ok(Self::from_parts(
+            rx,
+            config,
+            favicon,
+            registries,
+            allowlist,
+            AuditWriter::spawn(audit_log)?,
+        ))
+    }
 
-        Ok(Server {
+    /// Puts together a server from what [`Server::new`] loaded, with nothing
+    /// going on in it yet.
+    fn from_parts(
+        rx: mpsc::Receiver<ServerEvent>,
+        config: Arc<Config>,
+        favicon: Option<String>,
+        registries: Registries,
+        allowlist: Allowlist,
+        audit_log: AuditWriter,
+    ) -> Self {
+        let chat_filter = ChatFilter::new(&config.chat_filter, config.block_filtered_chat);
+        let seed = config.seed.unwrap_or_else(rand::random);
+        debug!(seed, "Picked the world seed");
+        let view_distance = config.view_distance;
+        let maintenance = config.maintenance;
+        let dimension_manager = DimensionManager::new();
+        let world = World::new(dimension_manager.current_dimension());
+        let (loopback, loopback_rx) = mpsc::unbounded_channel();
+
+        Server {
             rx,
+            loopback,
+            loopback_rx,
             config,
             version: Version::CURRENT,
             players: vec![],
+            player_count: Arc::new(AtomicUsize::new(0)),
+            logging_in: vec![],
             favicon,
-            dimension_manager: DimensionManager::new(),
-        })
+            audit_log,
+            entity_ids: EntityIds::new(),
+            allowlist,
+            chat_filter,
+            seed,
+            started: Instant::now(),
+            view_distance,
+            maintenance,
+            shutdown: None,
+            dimension_manager,
+            entity_tracker: EntityTracker::new(),
+            block_registry: registries.blocks,
+            item_registry: registries.items,
+            entity_type_registry: registries.entity_types,
+            world,
+            items: HashMap::new(),
+            entities: HashMap::new(),
+            world_border: WorldBorder::default(),
+            boss_bars: BossBars::new(),
+            scoreboard: Scoreboard::new(),
+            teams: Teams::new(),
+            tab_list_header: Chat::default(),
+            tab_list_footer: Chat::default(),
+        }
+    }
+
+    /// Handles events until the server is stopped, or until nothing can send
+    /// it events anymore.
+    #[instrument(skip(self))]
+    pub async fn server_loop(mut self) -> eyre::Result<()> {
+        let mut ticks = time::interval(TICK);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            let next_step_at = self.shutdown.as_ref().map(Countdown::next_step_at);
+            let sleep = time::sleep_until(next_step_at.unwrap_or_else(Instant::now).into());
+            tokio::select! {
+                event = self.rx.recv() => match event {
+                    Some(event) => self.handle_event(event)?,
+                    None => return Ok(()),
+                },
+                Some(notification) = self.loopback_rx.recv() => {
+                    self.handle_notification(notification)
+                }
+                _ = sleep, if next_step_at.is_some() => {
+                    if self.count_down(Instant::now()) {
+                        self.stop().await;
+                        return Ok(());
+                    }
+                }
+                _ = ticks.tick() => self.tick(),
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn handle_events(&mut self) -> eyre::Result<()> {
+        while let Some(event) = self.rx.recv().await {
+            self.handle_event(event)?;
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, ServerEvent(event): ServerEvent) -> eyre::Result<()> {
+        match event {
+            Inner::Request(request) => self.handle_request(request)?,
+            Inner::Notification(notification) => self.handle_notification(notification),
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> eyre::Result<()> {
+        match request {
+            Request::GetServerStatus { tx } => {
+                let motd = match (&self.config.maintenance_motd, self.maintenance) {
+                    (Some(motd), true) => motd,
+                    _ => &self.config.motd,
+                };
+                let motd = motd::substitute(motd, |name| match name {
+                    "online" => Some(self.player_count().to_string()),
+                    "max" => Some(self.config.max_players.to_string()),
+                    "version" => Some(self.version.name.to_string()),
+                    _ => None,
+                });
+                let mut json = json!({
+                    "version": {
+                        "name": self.version.name,
+                        "protocol": self.version.protocol_version,
+                    },
+                    "players": {
+                        "max": self.config.max_players,
+                        "online": self.player_count(),
+                        "sample": self.players.iter().filter(|p| !p.vanished).take(5).map(|p| &p.player).collect::<Vec<_>>()
+                    },
+                    "description": {
+                        "text": motd
+                    },
+                });
+                if let Some(favicon) = &self.favicon {
+                    json["favicon"] = json!(favicon);
+                }
+
+                let json = serde_json::to_string(&json)?;
+                trace!(?json);
+                reply(tx, json, "status data");
+            }
+            Request::GetStats { tx } => reply(tx, self.stats(), "stats"),
+            Request::GetPlayerList { tx } => reply(tx, self.player_list(), "player list"),
+            Request::GetDimensionInfo { tx } => {
+                let mut buf = vec![];
+                nbt::to_writer(&mut buf, &self.dimension_manager, None)?;
+                nbt::to_writer(&mut buf, &self.dimension_manager.current_dimension(), None)?;
+                reply(tx, buf, "dimension info");
+            }
+            Request::CheckLogin { player, tx } => {
+                let check = self.check_login(&player);
+                if tx.send(check).is_err() {
+                    debug!(?player, "Player left while logging in");
+                    self.cancel_login(player.uuid);
+                }
+            }
+            Request::JoinGame { player, client, tx } => {
+                let entity_id = self.join_game(player, client);
+                reply(tx, entity_id, "entity ID");
+            }
+            Request::PlaceBlock {
+                uuid,
+                clicked,
+                face,
+                hand,
+                tx,
+            } => {
+                let sign = self.place_block(uuid, clicked, face, hand);
+                reply(tx, sign, "placed sign");
+            }
+            Request::DigBlock {
+                uuid,
+                pos,
+                finished,
+                tx,
+            } => {
+                let dug = self.dig_block(uuid, pos, finished);
+                reply(tx, dug, "block state");
+            }
+            Request::RunCommand {
+                source,
+                command,
+                tx,
+            } => {
+                let result = command::dispatch(self, source, &command);
+                reply(tx, result, "command result");
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, notification: Notification) {
+        match notification {
+            Notification::PlayerMoved { uuid, position } => self.player_moved(uuid, position),
+            Notification::PlayerLeft { uuid } => self.leave_game(uuid),
+            Notification::SwingArm { uuid, hand } => self.swing_arm(uuid, hand),
+            Notification::Attack { uuid, entity_id } => self.attack(uuid, entity_id),
+            Notification::Respawn { uuid } => self.respawn(uuid),
+            Notification::DropItem {
+                uuid,
+                whole_stack,
+                yaw,
+                pitch,
+            } => self.drop_item(uuid, whole_stack, yaw, pitch),
+            Notification::HeldItemChanged { uuid, slot } => {
+                if let Some(player) = self.players.iter_mut().find(|p| p.player.uuid == uuid) {
+                    player.selected_slot = slot;
+                }
+            }
+            Notification::UpdateSign { uuid, pos, lines } => self.update_sign(uuid, pos, lines),
+            Notification::ChatMessage { uuid, message } => self.broadcast_chat(uuid, &message),
+            Notification::ClientSettings {
+                uuid,
+                view_distance,
+                chat_mode,
+                main_hand,
+                text_filtering,
+            } => {
+                let max = self.view_distance;
+                let player = match self.players.iter_mut().find(|p| p.player.uuid == uuid) {
+                    Some(player) => player,
+                    None => return,
+                };
+                player.requested_view_distance = view_distance;
+                player.view_distance = clamp_view_distance(view_distance, max);
+                player.chat_mode = chat_mode;
+                player.text_filtering = text_filtering;
+                if player.main_hand != main_hand {
+                    player.main_hand = main_hand;
+                    let player = self.players.iter().find(|p| p.player.uuid == uuid).unwrap();
+                    self.send_to_self_and_viewers(player, player.metadata_packet());
+                }
+            }
+            Notification::PlayerCommand { uuid, command } => {
+                self.run_player_command(uuid, &command)
+            }
+            Notification::Reloaded { source, reloaded } => self.finish_reload(source, reloaded),
+            Notification::Audit(entry) => self.audit_log.record(entry),
+        }
+    }
+
+    /// Starts reading the favicon again, e.g. after it was replaced, without
+    /// blocking the server loop. The current one is kept if the new one can't
+    /// be read.
+    ///
+    /// `source` is told how it went once it's done.
+    pub fn reload_favicon(&self, source: SourceKind) {
+        let path = self.config.favicon_path.clone();
+        self.load_in_background(source, move || {
+            Reloaded::Favicon(fs::read(&path).map(|image| favicon_data_url(&image)))
+        });
+    }
+
+    /// Starts loading the registries again from the reports, without blocking
+    /// the server loop. They are only replaced once every one of them is
+    /// loaded, so that they never come from different reports.
+    ///
+    /// `source` is told how it went once it's done.
+    pub fn reload_registries(&self, source: SourceKind) {
+        let blocks_report_path = self.config.blocks_report_path.clone();
+        let registries_report_path = self.config.registries_report_path.clone();
+        self.load_in_background(source, move || {
+            let load = || {
+                Ok(Box::new(Registries {
+                    blocks: BlockRegistry::load(&blocks_report_path)?,
+                    items: ItemRegistry::load(&registries_report_path)?,
+                    entity_types: EntityTypeRegistry::load(&registries_report_path)?,
+                }))
+            };
+            Reloaded::Registries(load())
+        });
+    }
+
+    /// Runs `load` on a thread where it may block, and hands what it loaded
+    /// back to the server loop.
+    fn load_in_background<F>(&self, source: SourceKind, load: F)
+    where
+        F: FnOnce() -> Reloaded + Send + 'static,
+    {
+        let loopback = self.loopback.clone();
+        tokio::task::spawn_blocking(move || {
+            let reloaded = load();
+            let _ = loopback.send(Notification::Reloaded { source, reloaded });
+        });
+    }
+
+    /// Swaps in what was reloaded, and tells the source that asked for it how
+    /// it went.
+    fn finish_reload(&mut self, source: SourceKind, reloaded: Reloaded) {
+        let (what, result) = match reloaded {
+            Reloaded::Favicon(Ok(favicon)) => {
+                self.favicon = Some(favicon);
+                ("favicon", Ok(()))
+            }
+            Reloaded::Favicon(Err(e)) => ("favicon", Err(e.to_string())),
+            Reloaded::Registries(Ok(registries)) => {
+                self.block_registry = registries.blocks;
+                self.item_registry = registries.items;
+                self.entity_type_registry = registries.entity_types;
+                ("registries", Ok(()))
+            }
+            Reloaded::Registries(Err(e)) => ("registries", Err(e.to_string())),
+        };
+        let message = match result {
+            Ok(()) => {
+                info!(what, "Reloaded");
+                format!("Reloaded the {}", what)
+            }
+            Err(e) => {
+                warn!(what, %e, "Failed to reload");
+                format!("Could not reload the {}: {}", what, e)
+            }
+        };
+        // the console and RCON see it in the logs
+        if let SourceKind::Player(player) = source {
+            if let Ok(player) = self.online_player(&player.username) {
+                let _ = player.client.send_system_message(message);
+            }
+        }
+    }
+
+    /// Starts counting down to stopping the server, telling every player about
+    /// it. A countdown that was already going is replaced.
+    pub fn stop_in(&mut self, seconds: u64) {
+        info!(seconds, "Stopping the server");
+        self.shutdown = Some(Countdown::new(Instant::now(), seconds));
+        if seconds > 0 {
+            self.warn_shutdown(seconds);
+        }
+    }
+
+    /// Stops the countdown to stopping the server, returning whether there was
+    /// one.
+    pub fn cancel_stop(&mut self) -> bool {
+        if self.shutdown.take().is_none() {
+            return false;
+        }
+        info!("No longer stopping the server");
+        self.broadcast_chat_message(
+            &Chat::text("The server is no longer stopping"),
+            ChatPosition::System,
+            Uuid::nil(),
+        );
+        true
+    }
+
+    /// Goes on with the countdown to stopping the server, returning whether it
+    /// is time to stop.
+    fn count_down(&mut self, now: Instant) -> bool {
+        match self.shutdown.as_mut().and_then(|c| c.poll(now)) {
+            Some(Step::Warn(seconds)) => {
+                self.warn_shutdown(seconds);
+                false
+            }
+            Some(Step::Stop) => true,
+            None => false,
+        }
+    }
+
+    fn warn_shutdown(&self, seconds: u64) {
+        let unit = if seconds == 1 { "second" } else { "seconds" };
+        self.broadcast_chat_message(
+            &Chat::text(format!("The server is stopping in {} {}", seconds, unit)),
+            ChatPosition::System,
+            Uuid::nil(),
+        );
+    }
+
+    /// Kicks every player, before the server loop stops.
+    async fn stop(&mut self) {
+        info!("Kicking every player and stopping");
+        for player in &self.players {
+            let _ = player.client.kick(
+                DisconnectReason::ServerStopping,
+                Chat::text("The server stopped"),
+            );
+        }
+        // gives the connections some time to send the kicks
+        let closed = time::timeout(STOP_TIMEOUT, async {
+            for player in &self.players {
+                player.client.closed().await;
+            }
+        });
+        if closed.await.is_err() {
+            warn!("Some connections didn't close in time");
+        }
+    }
+
+    /// Returns how long the server has been running for.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn stats(&self) -> ServerStats {
+        ServerStats {
+            uptime_secs: self.uptime().as_secs(),
+            online_players: self.player_count(),
+            max_players: self.config.max_players,
+        }
+    }
+
+    /// Returns every player in the game, vanished or not, in the order they
+    /// joined.
+    pub fn player_list(&self) -> Vec<PlayerListEntry> {
+        self.players
+            .iter()
+            .map(|p| PlayerListEntry {
+                username: p.player.username.clone(),
+                // no Keep Alives are sent yet, so the latency isn't known,
+                // which is also what the tab list says
+                ping: 0,
+            })
+            .collect()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.player_count.load(Ordering::SeqCst)
+    }
+
+    /// Checks whether a player that just authenticated may join the game, and
+    /// if so, reserves a slot for them.
+    ///
+    /// Operators can join even if the server is full.
+    fn check_login(&mut self, player: &Player) -> Result<(), LoginError> {
+        if self.maintenance && !self.is_op(player) {
+            return Err(LoginError::Maintenance(
+                self.config.maintenance_message.clone(),
+            ));
+        }
+        if !self.allowlist.allows(&player.username) {
+            return Err(LoginError::NotAllowed);
+        }
+        // in online mode, usernames are checked by the session server, but in
+        // offline mode, anyone can claim to be anyone
+        if !self.config.online_mode && self.is_name_taken(&player.username) {
+            return Err(LoginError::AlreadyOnline);
+        }
+        let max_players = self.config.max_players;
+        let bypass = self.is_op(player);
+        self.player_count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (bypass || count < max_players).then(|| count + 1)
+            })
+            .map_err(|_| LoginError::ServerFull)?;
+        self.logging_in.push(player.clone());
+        Ok(())
+    }
+
+    /// Returns whether a player with this name, in any case, is online or about
+    /// to join.
+    fn is_name_taken(&self, username: &str) -> bool {
+        self.players
+            .iter()
+            .map(|p| &p.player)
+            .chain(&self.logging_in)
+            .any(|p| p.username.eq_ignore_ascii_case(username))
+    }
+
+    /// Gives back the slot reserved for a player who logged in, but never
+    /// joined the game.
+    fn cancel_login(&mut self, uuid: Uuid) {
+        let len = self.logging_in.len();
+        self.logging_in.retain(|p| p.uuid != uuid);
+        if self.logging_in.len() < len {
+            debug!(%uuid, "Releasing the slot of a player who never joined");
+            self.player_count.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Adds a player to the game, returning their entity ID.
+    #[instrument(skip(self, client))]
+    fn join_game(&mut self, player: Player, client: ClientHook) -> i32 {
+        debug!(?player, "Player joined");
+        self.logging_in.retain(|p| p.uuid != player.uuid);
+        let entity_id = self.allocate_entity_id();
+
+        for other in &self.players {
+            let _ = other
+                .client
+                .send_packet(packets::player_info_add([&player].into_iter()));
+        }
+        let uuid = player.uuid;
+        self.players.push(OnlinePlayer {
+            player,
+            client,
+            entity_id,
+            position: None,
+            inventory: Inventory::new(),
+            selected_slot: 0,
+            vanished: false,
+            requested_view_distance: self.view_distance,
+            view_distance: self.view_distance,
+            chat_mode: ChatMode::Enabled,
+            main_hand: Arm::Right,
+            text_filtering: false,
+            npc: false,
+            attributes: vec![],
+            effects: vec![],
+            health: MAX_HEALTH,
+            hurt_cooldown: 0,
+            editing_sign: None,
+        });
+        // there can be plenty of these, e.g. with many players or teams
+        let mut batch = vec![
+            packets::player_info_add(self.visible_players(uuid).into_iter()),
+            packets::initialize_world_border(&self.world_border),
+        ];
+        batch.extend(self.scoreboard_packets());
+        if self.tab_list_header != Chat::default() || self.tab_list_footer != Chat::default() {
+            batch.push(packets::tab_list(
+                &self.tab_list_header,
+                &self.tab_list_footer,
+            ));
+        }
+        for team in self.teams.teams() {
+            let create = TeamAction::Create(team.clone());
+            batch.push(packets::teams(&team.name, &create));
+        }
+        // Join Game has the configured view distance
+        if self.view_distance != self.config.view_distance {
+            batch.push(packets::update_view_distance(self.view_distance));
+        }
+        let _ = self.players.last().unwrap().client.send_batch(batch);
+        entity_id
+    }
+
+    /// Adds a fake player without a client to the game at `position`, for
+    /// testing what other players see. Returns its entity ID.
+    pub fn spawn_npc(&mut self, username: &str, position: Vec3) -> Result<i32, CommandError> {
+        if !is_valid_username(username) {
+            return Err(CommandError::InvalidUsername(username.to_string()));
+        }
+        if self.is_name_taken(username) {
+            return Err(CommandError::AlreadyOnline(username.to_string()));
+        }
+        let player = Player {
+            username: username.to_string(),
+            uuid: Uuid::new_v4(),
+        };
+        let uuid = player.uuid;
+        // whatever is sent to the NPC goes nowhere
+        let (client, _) = ClientHook::new();
+        self.player_count.fetch_add(1, Ordering::SeqCst);
+        let entity_id = self.join_game(player, client);
+        self.players.last_mut().unwrap().npc = true;
+        self.player_moved(uuid, position);
+        Ok(entity_id)
+    }
+
+    /// Removes a fake player spawned with [`Server::spawn_npc`].
+    pub fn remove_npc(&mut self, username: &str) -> Result<(), CommandError> {
+        let npc = self.online_player(username)?;
+        if !npc.npc {
+            return Err(CommandError::NotAnNpc(username.to_string()));
+        }
+        self.leave_game(npc.player.uuid);
+        Ok(())
+    }
+
+    fn allocate_entity_id(&mut self) -> i32 {
+        self.entity_ids.allocate(Instant::now())
+    }
+
+    /// Returns the players in the tab list of a player, which are everyone but
+    /// the players that vanished, apart from the viewer themselves.
+    fn visible_players(&self, viewer: Uuid) -> Vec<&Player> {
+        self.players
+            .iter()
+            .filter(|p| !p.vanished || p.player.uuid == viewer)
+            .map(|p| &p.player)
+            .collect()
+    }
+
+    /// Changes the base value of an attribute of a player, for them and for
+    /// the players who can see them, returning the value it was clamped to.
+    pub fn set_attribute(
+        &mut self,
+        username: &str,
+        key: &Identifier,
+        base: f64,
+    ) -> Result<f64, CommandError> {
+        let attribute = Attribute::player(key, base)
+            .ok_or_else(|| CommandError::UnknownAttribute(key.clone()))?;
+        let player = self.online_player_mut(username)?;
+        let base = attribute.base;
+        let packet = packets::update_attributes(player.entity_id, std::slice::from_ref(&attribute));
+        match player.attributes.iter_mut().find(|a| a.key == *key) {
+            Some(existing) => existing.base = base,
+            None => player.attributes.push(attribute),
+        }
+
+        self.send_to_self_and_viewers(self.online_player(username)?, packet);
+        Ok(base)
+    }
+
+    /// Puts a player under an effect, replacing the one with the same ID they
+    /// were under, if any.
+    pub fn add_effect(&mut self, username: &str, effect: Effect) -> Result<(), CommandError> {
+        let player = self.online_player_mut(username)?;
+        player.effects.retain(|e| e.id != effect.id);
+        player.effects.push(effect);
+        let packet = packets::entity_effect(player.entity_id, &effect);
+        self.send_to_self_and_viewers(self.online_player(username)?, packet);
+        Ok(())
+    }
+
+    /// Takes an effect off a player, or all of them, returning how many were
+    /// taken off. Taking a single effect off fails if the player isn't under
+    /// it.
+    pub fn clear_effects(
+        &mut self,
+        username: &str,
+        effect_id: Option<u8>,
+    ) -> Result<usize, CommandError> {
+        let player = self.online_player_mut(username)?;
+        if let Some(id) = effect_id {
+            if !player.effects.iter().any(|e| e.id == id) {
+                return Err(CommandError::NoSuchEffect(username.to_string()));
+            }
+        }
+        let (cleared, kept) = player
+            .effects
+            .drain(..)
+            .partition::<Vec<_>, _>(|e| effect_id.is_none() || effect_id == Some(e.id));
+        player.effects = kept;
+
+        let player = self.online_player(username)?;
+        for effect in &cleared {
+            let packet = packets::remove_entity_effect(player.entity_id, effect.id);
+            self.send_to_self_and_viewers(player, packet);
+        }
+        Ok(cleared.len())
+    }
+
+    /// Counts down the effects players are under, taking off the ones that
+    /// ran out, and how long until they can be hurt again.
+    fn tick(&mut self) {
+        let mut expired = vec![];
+        for (index, player) in self.players.iter_mut().enumerate() {
+            player.hurt_cooldown = player.hurt_cooldown.saturating_sub(1);
+            for effect in &mut player.effects {
+                if !effect.tick() {
+                    expired.push((index, effect.id));
+                }
+            }
+            player.effects.retain(|e| e.duration > 0);
+        }
+        for (index, effect_id) in expired {
+            let player = &self.players[index];
+            let packet = packets::remove_entity_effect(player.entity_id, effect_id);
+            self.send_to_self_and_viewers(player, packet);
+        }
+    }
+
+    /// Sends a packet about a player to them and to the players who can see
+    /// them.
+    fn send_to_self_and_viewers(&self, player: &OnlinePlayer, packet: ResponseBuilder) {
+        for other in &self.players {
+            if other.player.uuid == player.player.uuid
+                || self
+                    .entity_tracker
+                    .is_tracking(other.player.uuid, player.entity_id)
+            {
+                let _ = other.client.send_packet(packet.clone());
+            }
+        }
+    }
+
+    /// Hides a player from the tab list of everyone else, or shows them again.
+    pub fn set_vanished(&mut self, username: &str, vanished: bool) -> Result<(), CommandError> {
+        let player = self.online_player_mut(username)?;
+        if player.vanished == vanished {
+            return Ok(());
+        }
+        player.vanished = vanished;
+        let player = player.player.clone();
+
+        let packet = if vanished {
+            packets::player_info_remove(&[player.uuid])
+        } else {
+            packets::player_info_add([&player].into_iter())
+        };
+        for other in self.players.iter().filter(|p| p.player.uuid != player.uuid) {
+            let _ = other.client.send_packet(packet.clone());
+        }
+        Ok(())
+    }
+
+    /// Changes the view distance of the server, telling every client about
+    /// it if it changed.
+    ///
+    /// Players who asked for more than the old view distance get the new one,
+    /// and everyone is then shown or hidden the players that came into or
+    /// went out of range.
+    pub fn set_view_distance(&mut self, view_distance: u8) {
+        if view_distance == self.view_distance {
+            return;
+        }
+        debug!(
+            old = self.view_distance,
+            new = view_distance,
+            "Changing the view distance"
+        );
+        self.view_distance = view_distance;
+        for player in &mut self.players {
+            player.view_distance =
+                clamp_view_distance(player.requested_view_distance, view_distance);
+        }
+        self.broadcast(packets::update_view_distance(view_distance));
+
+        let positions: Vec<_> = self
+            .players
+            .iter()
+            .filter_map(|p| Some((p.player.uuid, p.position?)))
+            .collect();
+        for (uuid, position) in positions {
+            self.player_moved(uuid, position);
+        }
+    }
+
+    /// Removes a player from the game once their connection is gone, even if
+    /// they only got as far as logging in.
+    #[instrument(skip(self))]
+    fn leave_game(&mut self, uuid: Uuid) {
+        let index = match self.players.iter().position(|p| p.player.uuid == uuid) {
+            Some(index) => index,
+            None => return self.cancel_login(uuid),
+        };
+        let left = self.players.remove(index);
+        debug!(player = ?left.player, "Player left");
+        self.player_count.fetch_sub(1, Ordering::SeqCst);
+
+        let viewers = self.entity_tracker.remove(uuid, left.entity_id);
+        self.entity_ids.release(left.entity_id, Instant::now());
+        // so that they are shown the bars again if they come back
+        self.boss_bars.remove_viewer(uuid);
+        for other in &self.players {
+            let _ = other
+                .client
+                .send_packet(packets::player_info_remove(&[uuid]));
+            if viewers.contains(&other.player.uuid) {
+                let _ = other
+                    .client
+                    .send_packet(packets::destroy_entities(&[left.entity_id]));
+            }
+        }
+    }
+
+    /// Hurts an entity a player attacked, if they can see it and it is within
+    /// [reach](REACH). Players can only hurt each other with PvP on, and
+    /// not again until their [cooldown](HURT_COOLDOWN) is over.
+    ///
+    /// Only players have health, so mobs merely look hurt.
+    fn attack(&mut self, uuid: Uuid, entity_id: i32) {
+        let attacker = match self.players.iter().find(|p| p.player.uuid == uuid) {
+            Some(player) => player,
+            None => return,
+        };
+        if attacker.entity_id == entity_id
+            || attacker.health <= 0.0
+            || !self.entity_tracker.is_tracking(uuid, entity_id)
+        {
+            return;
+        }
+        let target_position = match self.players.iter().find(|p| p.entity_id == entity_id) {
+            Some(target) => target.position,
+            None => self.entities.get(&entity_id).map(|e| e.position),
+        };
+        if !target_position.is_some_and(|to| attacker.can_reach(to)) {
+            debug!(%uuid, entity_id, "Ignoring attack out of reach");
+            return;
+        }
+        let attack_damage = Identifier::minecraft("generic.attack_damage");
+        let damage = attacker
+            .attributes
+            .iter()
+            .find(|a| a.key == attack_damage)
+            .map_or_else(
+                || Attribute::player_default(&attack_damage).unwrap_or(1.0),
+                |a| a.base,
+            );
+        let hurt = packets::entity_animation(entity_id, Animation::TakeDamage);
+
+        if let Some(index) = self.players.iter().position(|p| p.entity_id == entity_id) {
+            let target = &mut self.players[index];
+            if !self.config.pvp || target.health <= 0.0 || target.hurt_cooldown > 0 {
+                return;
+            }
+            target.health = (target.health - damage as f32).max(0.0);
+            target.hurt_cooldown = HURT_COOLDOWN;
+            let _ =
+                target
+                    .client
+                    .send_packet(packets::update_health(target.health, FOOD, SATURATION));
+            self.send_to_self_and_viewers(&self.players[index], hurt);
+        } else if self.entities.contains_key(&entity_id) {
+            for player in &self.players {
+                if self
+                    .entity_tracker
+                    .is_tracking(player.player.uuid, entity_id)
+                {
+                    let _ = player.client.send_packet(hurt.clone());
+                }
+            }
+        }
+    }
+
+    /// Brings a dead player back to life at the spawn point. Players who
+    /// aren't dead can't respawn.
+    fn respawn(&mut self, uuid: Uuid) {
+        let mut dimension = vec![];
+        if let Err(e) = nbt::to_writer(
+            &mut dimension,
+            &self.dimension_manager.current_dimension(),
+            None,
+        ) {
+            warn!(%e, "Failed to serialize the dimension to respawn in");
+            return;
+        }
+        let player = match self.players.iter_mut().find(|p| p.player.uuid == uuid) {
+            Some(player) if player.health <= 0.0 => player,
+            _ => return,
+        };
+        player.health = MAX_HEALTH;
+        player.hurt_cooldown = 0;
+        let _ = player.client.send_packet(packets::respawn(&dimension));
+        let _ = player
+            .client
+            .send_packet(packets::update_health(MAX_HEALTH, FOOD, SATURATION));
+        // the same spawn point as when joining
+        let _ = player.client.teleport(
+            AbsOrRel::Absolute(69.0),
+            AbsOrRel::Relative(0.0),
+            AbsOrRel::Absolute(420.0),
+        );
+    }
+
+    /// Shows a player swinging their arm to the players who can see them.
+    fn swing_arm(&self, uuid: Uuid, hand: Hand) {
+        let swinging = match self.players.iter().find(|p| p.player.uuid == uuid) {
+            Some(player) => player,
+            None => return,
+        };
+        let packet = packets::entity_animation(swinging.entity_id, Animation::swing(hand));
+        for other in &self.players {
+            if self
+                .entity_tracker
+                .is_tracking(other.player.uuid, swinging.entity_id)
+            {
+                let _ = other.client.send_packet(packet.clone());
+            }
+        }
+    }
+
+    /// Shows an item entity being picked up to the players who can see it,
+    /// then removes it from their world.
+    pub fn collect_item(&mut self, collected_id: i32, collector_id: i32, count: u32) {
+        if self.items.remove(&collected_id).is_some() {
+            self.entity_ids.release(collected_id, Instant::now());
+        }
+        let viewers = self.entity_tracker.remove_entity(collected_id);
+        let collect = packets::collect_item(collected_id, collector_id, count);
+        let destroy = packets::destroy_entities(&[collected_id]);
+        for player in &self.players {
+            if viewers.contains(&player.player.uuid) {
+                let _ = player.client.send_packet(collect.clone());
+                let _ = player.client.send_packet(destroy.clone());
+            }
+        }
+    }
+
+    /// Updates the position of a player, and which players can see each other
+    /// as a result.
+    #[instrument(skip(self))]
+    fn player_moved(&mut self, uuid: Uuid, position: Vec3) {
+        let moved = match self.players.iter().position(|p| p.player.uuid == uuid) {
+            Some(index) => {
+                self.players[index].position = Some(position);
+                &self.players[index]
+            }
+            None => return,
+        };
+
+        for other in &self.players {
+            let other_position = match other.position {
+                Some(pos) if other.player.uuid != uuid => pos,
+                _ => continue,
+            };
+            let distance = position.distance(other_position);
+
+            // the moved player, as seen by the other one...
+            let tracking = self.entity_tracker.track(
+                other.player.uuid,
+                moved.entity_id,
+                distance,
+                other.range(),
+            );
+            let packet = match tracking {
+                Some(Tracking::Spawn) => Some(moved.spawn_packet(position)),
+                Some(Tracking::Move) => {
+                    Some(packets::entity_teleport(moved.entity_id, position, false))
+                }
+                Some(Tracking::Destroy) => Some(packets::destroy_entities(&[moved.entity_id])),
+                None => None,
+            };
+            if let Some(packet) = packet {
+                let _ = other.client.send_packet(packet);
+            }
+            if tracking == Some(Tracking::Spawn) {
+                for packet in moved.tracking_packets() {
+                    let _ = other.client.send_packet(packet);
+                }
+            }
+
+            // ...and the other player, as seen by the moved one.
+            let tracking =
+                self.entity_tracker
+                    .track(uuid, other.entity_id, distance, moved.range());
+            let packet = match tracking {
+                Some(Tracking::Spawn) => Some(other.spawn_packet(other_position)),
+                Some(Tracking::Destroy) => Some(packets::destroy_entities(&[other.entity_id])),
+                Some(Tracking::Move) | None => None,
+            };
+            if let Some(packet) = packet {
+                let _ = moved.client.send_packet(packet);
+            }
+            if tracking == Some(Tracking::Spawn) {
+                for packet in other.tracking_packets() {
+                    let _ = moved.client.send_packet(packet);
+                }
+            }
+        }
+
+        // items don't move, so only the moved player can see new ones
+        for item in self.items.values() {
+            let distance = position.distance(item.position);
+            match self
+                .entity_tracker
+                .track(uuid, item.entity_id, distance, moved.range())
+            {
+                Some(Tracking::Spawn) => Self::spawn_item(&moved.client, item),
+                Some(Tracking::Destroy) => {
+                    let _ = moved
+                        .client
+                        .send_packet(packets::destroy_entities(&[item.entity_id]));
+                }
+                Some(Tracking::Move) | None => {}
+            }
+        }
+        // ...and neither do other entities
+        for entity in self.entities.values() {
+            let distance = position.distance(entity.position);
+            let packet =
+                match self
+                    .entity_tracker
+                    .track(uuid, entity.entity_id, distance, moved.range())
+                {
+                    Some(Tracking::Spawn) => entity.spawn_packet(),
+                    Some(Tracking::Destroy) => packets::destroy_entities(&[entity.entity_id]),
+                    Some(Tracking::Move) | None => continue,
+                };
+            let _ = moved.client.send_packet(packet);
+        }
+    }
+
+    /// Spawns an entity of the given type, showing it to the players in range,
+    /// and returns its entity ID.
+    pub fn summon(
+        &mut self,
+        position: Vec3,
+        entity_type: &Identifier,
+    ) -> Result<i32, CommandError> {
+        let type_id = self
+            .entity_type_registry
+            .id(entity_type)
+            .ok_or_else(|| CommandError::UnknownEntity(entity_type.clone()))?;
+        let kind = spawn_kind(entity_type);
+        if !matches!(kind, SpawnKind::Object | SpawnKind::Living) {
+            return Err(CommandError::CannotSummon(entity_type.clone()));
+        }
+
+        let entity_id = self.allocate_entity_id();
+        let entity = Entity {
+            entity_id,
+            uuid: Uuid::new_v4(),
+            entity_type: type_id,
+            kind,
+            position,
+            rotation: (0.0, 0.0),
+        };
+        let packet = entity.spawn_packet();
+        for player in &self.players {
+            let distance = match player.position {
+                Some(position) => position.distance(entity.position),
+                None => continue,
+            };
+            let tracking =
+                self.entity_tracker
+                    .track(player.player.uuid, entity_id, distance, player.range());
+            if tracking == Some(Tracking::Spawn) {
+                let _ = player.client.send_packet(packet.clone());
+            }
+        }
+        self.entities.insert(entity_id, entity);
+        Ok(entity_id)
+    }
+
+    /// Drops one item, or the whole stack, from the hotbar slot a player has
+    /// selected, throwing it in the direction they are looking.
+    fn drop_item(&mut self, uuid: Uuid, whole_stack: bool, yaw: f32, pitch: f32) {
+        let player = match self.players.iter_mut().find(|p| p.player.uuid == uuid) {
+            Some(player) => player,
+            None => return,
+        };
+        let position = match player.position {
+            Some(position) => position,
+            None => return,
+        };
+        let slot = 36 + usize::from(player.selected_slot);
+        let stack = match player.inventory.slot_mut(slot) {
+            Some(stack) => stack,
+            None => return,
+        };
+        let mut dropped = stack.clone();
+        if whole_stack || stack.count() <= 1 {
+            *player.inventory.slot_mut(slot) = None;
+        } else {
+            stack.set_count(stack.count() - 1);
+            dropped.set_count(1);
+        }
+        if let Ok(packet) = packets::set_slot(slot as i16, player.inventory.slot(slot)) {
+            let _ = player.client.send_packet(packet);
+        }
+
+        let entity_id = self.allocate_entity_id();
+        let item = ItemEntity::dropped(entity_id, dropped, position, yaw, pitch);
+        for player in &self.players {
+            let distance = match player.position {
+                Some(position) => position.distance(item.position),
+                None => continue,
+            };
+            let tracking =
+                self.entity_tracker
+                    .track(player.player.uuid, entity_id, distance, player.range());
+            if tracking == Some(Tracking::Spawn) {
+                Self::spawn_item(&player.client, &item);
+            }
+        }
+        self.items.insert(entity_id, item);
+    }
+
+    /// Sends the packets spawning an item entity to a client.
+    fn spawn_item(client: &ClientHook, item: &ItemEntity) {
+        let _ = client.send_packet(packets::spawn_item_entity(item));
+        match packets::item_entity_metadata(item) {
+            Ok(packet) => {
+                let _ = client.send_packet(packet);
+            }
+            Err(e) => warn!(?e, "Failed to serialize an item entity"),
+        }
+    }
+
+    /// Sends a chat message from a player to everyone, filtering it for the
+    /// players who asked for it.
+    #[instrument(skip(self))]
+    fn broadcast_chat(&mut self, uuid: Uuid, message: &str) {
+        let sender = match self.players.iter().find(|p| p.player.uuid == uuid) {
+            Some(p) => p.player.username.clone(),
+            None => {
+                warn!("Chat message sent by a player that isn't online");
+                return;
+            }
+        };
+        let filtered = self.chat_filter.filter(message);
+        for player in &self.players {
+            if !player.chat_mode.accepts(ChatPosition::Chat) {
+                continue;
+            }
+            let message = match (player.text_filtering, &filtered) {
+                (false, _) => message,
+                (true, Some(filtered)) => filtered,
+                (true, None) => continue,
+            };
+            let chat = Chat::text(format!("<{}> {}", sender, message));
+            let _ =
+                player
+                    .client
+                    .send_packet(packets::chat_message(&chat, ChatPosition::Chat, uuid));
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn run_player_command(&mut self, uuid: Uuid, command: &str) {
+        let sender = match self.players.iter().find(|p| p.player.uuid == uuid) {
+            Some(p) => p.player.clone(),
+            None => {
+                warn!("Command sent by a player that isn't online");
+                return;
+            }
+        };
+        let source = SourceKind::Player(sender.clone());
+        let feedback = match command::dispatch(self, source, command) {
+            // the command already told everyone involved
+            Ok(feedback) if feedback.is_empty() => return,
+            Ok(feedback) => feedback,
+            Err(e) => e.to_string(),
+        };
+        // players get the feedback of their commands whatever their chat mode
+        if let Ok(player) = self.online_player(&sender.username) {
+            if let Err(e) = player.client.send_system_message(feedback) {
+                debug!(?e, "Failed to send command feedback");
+            }
+        }
+    }
+
+    /// Digs up a block once a player finished digging it, if it is within
+    /// their reach and the world. Returns the block that is there afterwards,
+    /// which is what the client has to show, and whether they could dig it.
+    fn dig_block(&mut self, uuid: Uuid, pos: BlockPos, finished: bool) -> (u32, bool) {
+        let allowed = self.world.contains(pos)
+            && self
+                .players
+                .iter()
+                .find(|p| p.player.uuid == uuid)
+                .is_some_and(|p| p.health > 0.0 && p.can_reach(pos.center()));
+        if !allowed {
+            debug!(%uuid, ?pos, "Ignoring digging out of reach");
+        } else if finished {
+            self.set_block(pos, AIR);
+        }
+        (self.world.block(pos), allowed)
+    }
+
+    /// Places the block a player is holding, returning its position if it is a
+    /// sign.
+    fn place_block(
+        &mut self,
+        uuid: Uuid,
+        clicked: BlockPos,
+        face: Direction,
+        hand: Hand,
+    ) -> Option<BlockPos> {
+        let index = self.players.iter().position(|p| p.player.uuid == uuid)?;
+        let player = &self.players[index];
+        let slot = match hand {
+            Hand::Mainhand => 36 + usize::from(player.selected_slot),
+            Hand::Offhand => 45,
+        };
+        let item = player.inventory.slot(slot).as_ref()?;
+        let item = self.item_registry.identifier(item.id())?;
+
+        // signs are either standing on top of a block, or hanging on its side
+        let is_sign = item.path.ends_with("_sign");
+        let state = match face {
+            _ if !is_sign => self.block_registry.default_state(item),
+            Direction::Top => self.block_registry.default_state(item),
+            Direction::Bottom => None,
+            side => {
+                let wall_sign = Identifier {
+                    namespace: item.namespace.clone(),
+                    path: item.path.replace("_sign", "_wall_sign").into(),
+                };
+                self.block_registry
+                    .state(&wall_sign, &[("facing", side.as_str())])
+            }
+        }?;
+
+        let pos = clicked + face.offset();
+        if !self.world.contains(pos) {
+            warn!(?pos, "Ignoring a block placed outside the world");
+            return None;
+        }
+        let existing = self.world.block(pos);
+        if existing != AIR || !player.can_reach(pos.center()) {
+            // the client has already placed the block on its end
+            let _ = player
+                .client
+                .send_packet(packets::block_change(pos, existing));
+            return None;
+        }
+        self.set_block(pos, state);
+
+        let player = &mut self.players[index];
+        if let Some(stack) = player.inventory.slot_mut(slot) {
+            if stack.count() > 1 {
+                stack.set_count(stack.count() - 1);
+            } else {
+                *player.inventory.slot_mut(slot) = None;
+            }
+        }
+        if let Ok(packet) = packets::set_slot(slot as i16, player.inventory.slot(slot)) {
+            let _ = player.client.send_packet(packet);
+        }
+
+        if is_sign {
+            // the client gets to edit it right away, and only then
+            player.editing_sign = Some(pos);
+            self.world.set_block_entity(pos, BlockEntity::sign());
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the text of a sign, if it is the one the player just placed, and
+    /// it is still within their reach.
+    fn update_sign(&mut self, uuid: Uuid, pos: BlockPos, lines: [String; 4]) {
+        let player = match self.players.iter_mut().find(|p| p.player.uuid == uuid) {
+            Some(player) => player,
+            None => return,
+        };
+        if player.editing_sign.take() != Some(pos) || !player.can_reach(pos.center()) {
+            debug!(%uuid, ?pos, "Ignoring an update of a sign the player isn't editing");
+            return;
+        }
+        if let Some(BlockEntity::Sign { lines: old }) = self.world.block_entity_mut(pos) {
+            *old = lines;
+        }
+    }
+
+    /// Sets a block in the world, and sends the change to every player close
+    /// enough to see it.
+    fn set_block(&mut self, pos: BlockPos, state: u32) {
+        if self.world.set_block(pos, state).is_none() {
+            return;
+        }
+
+        for player in &self.players {
+            match player.position {
+                Some(position) if position.distance(pos.center()) <= player.range() => {
+                    let _ = player.client.send_packet(packets::block_change(pos, state));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Registers a boss bar, without showing it to anyone yet.
+    pub fn create_boss_bar(&mut self, bar: BossBar) -> Uuid {
+        self.boss_bars.add(bar)
+    }
+
+    /// Shows a boss bar to a player.
+    pub fn show_boss_bar(&mut self, bar_uuid: Uuid, player: Uuid) {
+        if let Some(bar) = self.boss_bars.get_mut(bar_uuid) {
+            if bar.add_viewer(player) {
+                let packet = packets::boss_bar(bar_uuid, &bar.add_action());
+                self.send_to(player, packet);
+            }
+        }
+    }
+
+    /// Hides a boss bar from a player.
+    pub fn hide_boss_bar(&mut self, bar_uuid: Uuid, player: Uuid) {
+        if let Some(bar) = self.boss_bars.get_mut(bar_uuid) {
+            if bar.remove_viewer(player) {
+                self.send_to(player, packets::boss_bar(bar_uuid, &BossBarAction::Remove));
+            }
+        }
+    }
+
+    /// Updates a boss bar for everyone viewing it.
+    ///
+    /// [`BossBarAction::Remove`] removes the bar altogether.
+    pub fn update_boss_bar(&mut self, bar_uuid: Uuid, action: BossBarAction) {
+        let bar = match action {
+            BossBarAction::Remove => self.boss_bars.remove(bar_uuid),
+            _ => self.boss_bars.get_mut(bar_uuid).map(|bar| {
+                bar.apply(&action);
+                bar.clone()
+            }),
+        };
+        if let Some(bar) = bar {
+            let packet = packets::boss_bar(bar_uuid, &action);
+            for &viewer in bar.viewers() {
+                self.send_to(viewer, packet.clone());
+            }
+        }
+    }
+
+    /// Adds a scoreboard objective, or updates the one with the same name.
+    pub fn add_objective(&mut self, objective: Objective) {
+        let packet = match self.scoreboard.objective(&objective.name) {
+            Some(_) => packets::update_objective(&objective),
+            None => packets::create_objective(&objective),
+        };
+        self.scoreboard.add_objective(objective);
+        self.broadcast(packet);
+    }
+    pub fn remove_objective(&mut self, name: &str) {
+        if self.scoreboard.remove_objective(name).is_some() {
+            self.broadcast(packets::remove_objective(name));
+        }
+    }
+    pub fn set_score(&mut self, entity: &str, objective: &str, value: i32) {
+        if self.scoreboard.set_score(entity, objective, value) {
+            self.broadcast(packets::update_score(entity, objective, value));
+        }
+    }
+    pub fn reset_score(&mut self, entity: &str, objective: &str) {
+        if self.scoreboard.reset_score(entity, objective).is_some() {
+            self.broadcast(packets::remove_score(entity, objective));
+        }
+    }
+    /// Displays an objective in a slot, or clears the slot.
+    pub fn display_objective(&mut self, slot: DisplaySlot, objective: Option<&str>) {
+        if self.scoreboard.set_displayed(slot, objective) {
+            self.broadcast(packets::display_scoreboard(slot, objective.unwrap_or("")));
+        }
+    }
+
+    /// Adds a team, or updates the one with the same name.
+    ///
+    /// Teams and their members are kept across reconnects, as members are
+    /// identified by name.
+    pub fn add_team(&mut self, team: Team) {
+        for (name, action) in self.teams.add(team) {
+            self.broadcast(packets::teams(&name, &action));
+        }
+    }
+    pub fn remove_team(&mut self, name: &str) {
+        if self.teams.remove(name).is_some() {
+            self.broadcast(packets::teams(name, &TeamAction::Remove));
+        }
+    }
+    pub fn add_team_members(&mut self, name: &str, members: &[String]) {
+        for (name, action) in self.teams.add_members(name, members) {
+            self.broadcast(packets::teams(&name, &action));
+        }
+    }
+    pub fn remove_team_members(&mut self, name: &str, members: &[String]) {
+        let removed = self.teams.remove_members(name, members);
+        if !removed.is_empty() {
+            self.broadcast(packets::teams(name, &TeamAction::RemoveMembers(removed)));
+        }
+    }
+
+    /// Makes an explosion, destroying every block within `radius` of `center`,
+    /// and returns how many blocks were destroyed. The radius is clamped to
+    /// [`MAX_EXPLOSION_RADIUS`].
+    ///
+    /// Players close enough to see it are sent the explosion, which also
+    /// removes the blocks on their end.
+    pub fn explode(&mut self, center: Vec3, radius: f64) -> usize {
+        let radius = radius.clamp(0.0, MAX_EXPLOSION_RADIUS);
+        let destroyed = self.world.explode(center, radius);
+        let packet = packets::explosion(center, radius as f32, &destroyed, Vec3::ZERO);
+
+        for player in &self.players {
+            if matches!(player.position, Some(pos) if pos.distance(center) <= player.range()) {
+                let _ = player.client.send_packet(packet.clone());
+            }
+        }
+        destroyed.len()
+    }
+
+    /// Moves, resizes or changes the warning distance of the world border,
+    /// telling everyone about what changed.
+    pub fn set_world_border(&mut self, border: WorldBorder) {
+        let old = std::mem::replace(&mut self.world_border, border);
+        if (old.center_x, old.center_z) != (border.center_x, border.center_z) {
+            self.broadcast(packets::world_border_center(
+                border.center_x,
+                border.center_z,
+            ));
+        }
+        if old.radius != border.radius {
+            self.broadcast(packets::world_border_size(border.diameter()));
+        }
+        if old.warning_distance != border.warning_distance {
+            self.broadcast(packets::world_border_warning_distance(
+                border.warning_distance,
+            ));
+        }
+    }
+
+    /// Sets the header and footer of the tab list for everyone, including
+    /// players joining later. Empty components hide them.
+    pub fn set_tab_list(&mut self, header: Chat, footer: Chat) {
+        self.broadcast(packets::tab_list(&header, &footer));
+        self.tab_list_header = header;
+        self.tab_list_footer = footer;
+    }
+
+    /// Returns the packets that recreate the whole scoreboard on a client.
+    fn scoreboard_packets(&self) -> Vec<ResponseBuilder> {
+        let objectives = self.scoreboard.objectives().map(packets::create_objective);
+        let scores = self
+            .scoreboard
+            .scores()
+            .map(|(entity, objective, value)| packets::update_score(entity, objective, value));
+        let displayed = self
+            .scoreboard
+            .displayed()
+            .map(|(slot, objective)| packets::display_scoreboard(slot, objective));
+        objectives.chain(scores).chain(displayed).collect()
+    }
+
+    /// Sends a packet to every player, only building its frame once for all
+    /// of them.
+    fn broadcast(&self, packet: ResponseBuilder) {
+        let packet = Arc::new(PreparedPacket::new(packet));
+        for player in &self.players {
+            let _ = player.client.send_prepared(packet.clone());
+        }
+    }
+
+    /// Sends a chat message to every player whose chat mode lets it through.
+    fn broadcast_chat_message(&self, chat: &Chat, position: ChatPosition, sender: Uuid) {
+        let packet = Arc::new(PreparedPacket::new(packets::chat_message(
+            chat, position, sender,
+        )));
+        for player in &self.players {
+            if player.chat_mode.accepts(position) {
+                let _ = player.client.send_prepared(packet.clone());
+            }
+        }
+    }
+
+    fn send_to(&self, player: Uuid, packet: ResponseBuilder) {
+        if let Some(player) = self.players.iter().find(|p| p.player.uuid == player) {
+            let _ = player.client.send_packet(packet);
+        }
+    }
+
+    fn online_player(&self, username: &str) -> Result<&OnlinePlayer, CommandError> {
+        self.players
+            .iter()
+            .find(|p| p.player.username == username)
+            .ok_or_else(|| CommandError::PlayerNotFound(username.to_string()))
+    }
+
+    fn online_player_mut(&mut self, username: &str) -> Result<&mut OnlinePlayer, CommandError> {
+        self.players
+            .iter_mut()
+            .find(|p| p.player.username == username)
+            .ok_or_else(|| CommandError::PlayerNotFound(username.to_string()))
+    }
+
+    fn is_op(&self, player: &Player) -> bool {
+        self.config.operators.contains(&player.username)
+    }
+}
+
+/// The reason a player is refused from joining the game.
+#[derive(Debug, Error)]
+pub enum LoginError {
+    #[error("You are not on the allowlist of this server")]
+    NotAllowed,
+    #[error("The server is full")]
+    ServerFull,
+    #[error("A player with this name is already online")]
+    AlreadyOnline,
+    #[error("{0}")]
+    Maintenance(String),
+}
+
+/// Basic figures about a running server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServerStats {
+    pub uptime_secs: u64,
+    pub online_players: usize,
+    pub max_players: usize,
+}
+
+/// A player as shown in the player list of the console.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerListEntry {
+    pub username: String,
+    /// The latency of the player, in milliseconds.
+    pub ping: u32,
+}
+
+/// Sends the reply to a request.
+///
+/// The connection that made the request may have closed in the meantime, which
+/// is no reason to stop the server loop, so failures are only logged.
+fn reply<T>(tx: oneshot::Sender<T>, value: T, what: &str) {
+    if tx.send(value).is_err() {
+        debug!(what, "Failed to reply to a request, the connection is gone");
+    }
+}
+
+/// The health players join with.
+const MAX_HEALTH: f32 = 20.0;
+/// The food level and saturation of players, which don't go down as there is
+/// no hunger.
+const FOOD: u32 = 20;
+const SATURATION: f32 = 5.0;
+
+/// How far from their eyes players can reach the entities they attack and the
+/// blocks they dig or place.
+const REACH: f64 = 6.0;
+/// How high the eyes of a standing player are above their feet.
+const EYE_HEIGHT: f64 = 1.62;
+/// How many ticks players can't be hurt for after being hurt, like vanilla.
+const HURT_COOLDOWN: u32 = 10;
+
+/// How often the game ticks, 20 times a second.
+const TICK: Duration = Duration::from_millis(50);
+
+/// Encodes a PNG image as the data URL the server list expects.
+fn favicon_data_url(image: &[u8]) -> String {
+    let mut favicon =
+        String::with_capacity("data:image/png;base64,".len() + image.len() * 4 / 3 + 4);
+    favicon.push_str("data:image/png;base64,");
+    base64::encode_config_buf(image, base64::STANDARD, &mut favicon);
+    favicon
+}
+
+/// How long the connections get to close when the server stops.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The fraction of the event channel that has to be filled for it to count as
+/// near-full.
+const NEAR_FULL_RATIO: f64 = 0.9;
+/// How many events in a row have to be sent to a near-full channel before
+/// warning about it.
+const NEAR_FULL_WARN_STREAK: usize = 100;
+
+/// A handle to the [`Server`], through which connections send it events.
+#[derive(Clone)]
+pub struct ServerHook {
+    tx: mpsc::Sender<ServerEvent>,
+    capacity: usize,
+    /// The number of events sent in a row while the channel was near-full.
+    near_full_streak: Arc<AtomicUsize>,
+}
+
+#[cfg(test)]
+impl ServerHook {
+    /// Creates a hook that isn't connected to a server, returning the events
+    /// sent through it instead.
+    pub(crate) fn stub() -> (Self, mpsc::Receiver<ServerEvent>) {
+        Self::channel(100)
+    }
+}
+
+impl ServerHook {
+    /// Creates a hook, along with the receiving end the [`Server`] reads its
+    /// events from.
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<ServerEvent>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        let hook = Self {
+            tx,
+            capacity,
+            near_full_streak: Arc::new(AtomicUsize::new(0)),
+        };
+        (hook, rx)
+    }
+
+    /// Returns the number of events waiting for the server loop to handle
+    /// them.
+    pub fn depth(&self) -> usize {
+        self.capacity - self.tx.capacity()
+    }
+
+    /// Keeps track of how full the channel is, warning if it stays near-full,
+    /// which means the server loop is lagging behind.
+    fn record_depth(&self) {
+        let depth = self.depth();
+        trace!(
+            depth,
+            capacity = self.capacity,
+            "Server event channel depth"
+        );
+        if (depth as f64) < self.capacity as f64 * NEAR_FULL_RATIO {
+            self.near_full_streak.store(0, Ordering::Relaxed);
+            return;
+        }
+        let streak = self.near_full_streak.fetch_add(1, Ordering::Relaxed) + 1;
+        if streak >= NEAR_FULL_WARN_STREAK {
+            // start counting again, to warn at most once per streak length
+            self.near_full_streak.store(0, Ordering::Relaxed);
+            warn!(
+                depth,
+                capacity = self.capacity,
+                "Server event channel is persistently near-full, the server loop is lagging"
+            );
+        }
+    }
+
+    async fn request(&self, request: Request) -> eyre::Result<()> {
+        self.record_depth();
+        self.tx.send(ServerEvent(Inner::Request(request))).await?;
+        Ok(())
+    }
+    async fn notify(&self, notification: Notification) -> eyre::Result<()> {
+        self.record_depth();
+        self.tx
+            .send(ServerEvent(Inner::Notification(notification)))
+            .await?;
+        Ok(())
+    }
+    /// Sends a notification without waiting for room in the channel, returning
+    /// whether it was sent.
+    fn try_notify(&self, notification: Notification) -> eyre::Result<bool> {
+        self.record_depth();
+        match self
+            .tx
+            .try_send(ServerEvent(Inner::Notification(notification)))
+        {
+            Ok(()) => Ok(true),
+            Err(TrySendError::Full(_)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_server_status(&self) -> eyre::Result<String> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::GetServerStatus { tx }).await?;
+        Ok(rx.await?)
+    }
+    pub async fn get_stats(&self) -> eyre::Result<ServerStats> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::GetStats { tx }).await?;
+        Ok(rx.await?)
+    }
+    pub async fn get_player_list(&self) -> eyre::Result<Vec<PlayerListEntry>> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::GetPlayerList { tx }).await?;
+        Ok(rx.await?)
+    }
+    pub async fn get_dimension_info(&self) -> eyre::Result<Vec<u8>> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::GetDimensionInfo { tx }).await?;
+        Ok(rx.await?)
     }
-
-    #[instrument(skip(self))]
-    pub async fn server_loop(mut self) -> eyre::Result<()> {
-        loop {
-            self.handle_events().await?;
+    /// Checks whether a player may join, before they enter the Play state.
+    pub async fn check_login(&self, player: Player) -> eyre::Result<Result<(), LoginError>> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::CheckLogin { player, tx }).await?;
+        Ok(rx.await?)
+    }
+    /// Adds a player to the game, returning their entity ID.
+    pub async fn join_game(&self, player: Player, client: ClientHook) -> eyre::Result<i32> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::JoinGame { player, client, tx })
+            .await?;
+        Ok(rx.await?)
+    }
+    /// Reports the movement of a player.
+    ///
+    /// Movement is reported many times a second, and a later report
+    /// supersedes this one anyway, so it is dropped if the server can't keep
+    /// up instead of waiting.
+    pub fn player_moved(&self, uuid: Uuid, position: Vec3) -> eyre::Result<()> {
+        if !self.try_notify(Notification::PlayerMoved { uuid, position })? {
+            debug!(?uuid, "Server event channel is full, dropping movement");
         }
+        Ok(())
     }
-
-    #[instrument(skip(self))]
-    pub async fn handle_events(&mut self) -> eyre::Result<()> {
-        while let Some(ServerEvent(req)) = self.rx.recv().await {
-            match req {
-                Inner::GetServerStatus { tx } => {
-                    let mut json = json!({
-                        "version": {
-                            "name": self.version.name,
-                            "protocol": self.version.protocol_version,
-                        },
-                        "players": {
-                            "max": self.config.max_players,
-                            "online": self.players.len(),
-                            "sample": self.players.iter().take(5).collect::<Vec<_>>()
-                        },
-                        "description": {
-                            "text": &self.config.motd
-                        },
-                    });
-                    if let Some(favicon) = &self.favicon {
-                        json["favicon"] = json!(favicon);
-                    }
-
-                    let json = serde_json::to_string(&json)?;
-                    trace!(?json);
-                    tx.send(json)
-                        .map_err(|_| eyre!("failed to send status data"))?;
-                }
-                Inner::GetDimensionInfo { tx } => {
-                    let mut buf = vec![];
-                    nbt::to_writer(&mut buf, &self.dimension_manager, None)?;
-                    nbt::to_writer(&mut buf, &self.dimension_manager.current_dimension(), None)?;
-                    tx.send(buf)
-                        .map_err(|_| eyre!("failed to send dimension info"))?;
-                }
-                Inner::JoinGame(player) => {
-                    debug!(?player, "Player joined");
-                    self.players.push(player);
-                }
+    /// Tells the server that a player's connection is gone.
+    ///
+    /// This doesn't wait, so that it can be called while dropping the
+    /// connection.
+    pub fn player_left(&self, uuid: Uuid) {
+        let notification = Notification::PlayerLeft { uuid };
+        let notification = match self
+            .tx
+            .try_send(ServerEvent(Inner::Notification(notification)))
+        {
+            Ok(()) => return,
+            Err(TrySendError::Full(ServerEvent(notification))) => notification,
+            // the server is gone, so there is no one to tell
+            Err(TrySendError::Closed(_)) => return,
+        };
+        // this must not get lost, so wait for room in the background
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let tx = self.tx.clone();
+                runtime.spawn(async move { tx.send(ServerEvent(notification)).await });
             }
+            Err(_) => warn!(?uuid, "Failed to tell the server that a player left"),
         }
+    }
+    pub async fn chat_message(&self, uuid: Uuid, message: String) -> eyre::Result<()> {
+        self.notify(Notification::ChatMessage { uuid, message })
+            .await?;
         Ok(())
     }
-}
-
-#[derive(Clone)]
-pub struct ServerHook(pub mpsc::Sender<ServerEvent>);
-
-impl ServerHook {
-    pub async fn get_server_status(&self) -> eyre::Result<String> {
+    pub async fn client_settings(
+        &self,
+        uuid: Uuid,
+        view_distance: u8,
+        chat_mode: ChatMode,
+        main_hand: Arm,
+        text_filtering: bool,
+    ) -> eyre::Result<()> {
+        self.notify(Notification::ClientSettings {
+            uuid,
+            view_distance,
+            chat_mode,
+            main_hand,
+            text_filtering,
+        })
+        .await?;
+        Ok(())
+    }
+    pub async fn player_command(&self, uuid: Uuid, command: String) -> eyre::Result<()> {
+        self.notify(Notification::PlayerCommand { uuid, command })
+            .await?;
+        Ok(())
+    }
+    pub async fn swing_arm(&self, uuid: Uuid, hand: Hand) -> eyre::Result<()> {
+        self.notify(Notification::SwingArm { uuid, hand }).await?;
+        Ok(())
+    }
+    /// Reports a player attacking an entity.
+    pub async fn attack(&self, uuid: Uuid, entity_id: i32) -> eyre::Result<()> {
+        self.notify(Notification::Attack { uuid, entity_id })
+            .await?;
+        Ok(())
+    }
+    /// Reports a dead player asking to respawn.
+    pub async fn respawn(&self, uuid: Uuid) -> eyre::Result<()> {
+        self.notify(Notification::Respawn { uuid }).await?;
+        Ok(())
+    }
+    /// Drops one item, or the whole stack, that a player is holding, towards
+    /// where they are looking.
+    pub async fn drop_item(
+        &self,
+        uuid: Uuid,
+        whole_stack: bool,
+        yaw: f32,
+        pitch: f32,
+    ) -> eyre::Result<()> {
+        self.notify(Notification::DropItem {
+            uuid,
+            whole_stack,
+            yaw,
+            pitch,
+        })
+        .await?;
+        Ok(())
+    }
+    pub async fn held_item_changed(&self, uuid: Uuid, slot: u8) -> eyre::Result<()> {
+        self.notify(Notification::HeldItemChanged { uuid, slot })
+            .await?;
+        Ok(())
+    }
+    /// Places the block held by a player against a face of the clicked block,
+    /// returning the position of the placed block if it is a sign that needs
+    /// its text edited.
+    pub async fn place_block(
+        &self,
+        uuid: Uuid,
+        clicked: BlockPos,
+        face: Direction,
+        hand: Hand,
+    ) -> eyre::Result<Option<BlockPos>> {
         let (tx, rx) = oneshot::channel();
-        self.0
-            .send(ServerEvent(Inner::GetServerStatus { tx }))
+        self.request(Request::PlaceBlock {
+            uuid,
+            clicked,
+            face,
+            hand,
+            tx,
+        })
+        .await?;
+        Ok(rx.await?)
+    }
+    /// Sets the text of the sign a player placed and edited.
+    pub async fn update_sign(
+        &self,
+        uuid: Uuid,
+        pos: BlockPos,
+        lines: [String; 4],
+    ) -> eyre::Result<()> {
+        self.notify(Notification::UpdateSign { uuid, pos, lines })
             .await?;
+        Ok(())
+    }
+    /// Updates the world after a player started or finished digging a block,
+    /// returning the block that is there afterwards, and whether they were
+    /// allowed to dig there.
+    pub async fn dig_block(
+        &self,
+        uuid: Uuid,
+        pos: BlockPos,
+        finished: bool,
+    ) -> eyre::Result<(u32, bool)> {
+        let (tx, rx) = oneshot::channel();
+        self.request(Request::DigBlock {
+            uuid,
+            pos,
+            finished,
+            tx,
+        })
+        .await?;
         Ok(rx.await?)
     }
-    pub async fn get_dimension_info(&self) -> eyre::Result<Vec<u8>> {
+    /// Runs a command on behalf of a source, with its permissions, returning
+    /// the feedback for it. The console, RCON and players in chat all run
+    /// commands through here.
+    ///
+    /// Only the kind of source is passed, and the server builds the
+    /// [`CommandSource`](command::CommandSource) when it runs the command, so
+    /// that its permission and position are up to date, and can't be made up
+    /// by the caller.
+    pub async fn run_command(
+        &self,
+        source: SourceKind,
+        command: &str,
+    ) -> eyre::Result<Result<String, CommandError>> {
         let (tx, rx) = oneshot::channel();
-        self.0
-            .send(ServerEvent(Inner::GetDimensionInfo { tx }))
-            .await?;
+        self.request(Request::RunCommand {
+            source,
+            command: command.to_string(),
+            tx,
+        })
+        .await?;
         Ok(rx.await?)
     }
-    pub async fn join_game(&self, player: Player) -> eyre::Result<()> {
-        self.0.send(ServerEvent(Inner::JoinGame(player))).await?;
+    pub async fn audit(&self, entry: AuditEntry) -> eyre::Result<()> {
+        self.notify(Notification::Audit(entry)).await?;
         Ok(())
     }
 }
@@ -127,9 +2013,123 @@ impl ServerHook {
 pub struct ServerEvent(Inner);
 #[derive(Debug)]
 enum Inner {
-    GetServerStatus { tx: oneshot::Sender<String> },
-    GetDimensionInfo { tx: oneshot::Sender<Vec<u8>> },
-    JoinGame(Player),
+    Request(Request),
+    Notification(Notification),
+}
+/// Events the sender waits for the server to reply to.
+#[derive(Debug)]
+enum Request {
+    GetServerStatus {
+        tx: oneshot::Sender<String>,
+    },
+    GetStats {
+        tx: oneshot::Sender<ServerStats>,
+    },
+    GetPlayerList {
+        tx: oneshot::Sender<Vec<PlayerListEntry>>,
+    },
+    GetDimensionInfo {
+        tx: oneshot::Sender<Vec<u8>>,
+    },
+    CheckLogin {
+        player: Player,
+        tx: oneshot::Sender<Result<(), LoginError>>,
+    },
+    JoinGame {
+        player: Player,
+        client: ClientHook,
+        tx: oneshot::Sender<i32>,
+    },
+    PlaceBlock {
+        uuid: Uuid,
+        clicked: BlockPos,
+        face: Direction,
+        hand: Hand,
+        tx: oneshot::Sender<Option<BlockPos>>,
+    },
+    DigBlock {
+        uuid: Uuid,
+        pos: BlockPos,
+        finished: bool,
+        tx: oneshot::Sender<(u32, bool)>,
+    },
+    RunCommand {
+        source: SourceKind,
+        command: String,
+        tx: oneshot::Sender<Result<String, CommandError>>,
+    },
+}
+/// Events the sender doesn't wait for.
+#[derive(Debug)]
+enum Notification {
+    PlayerLeft {
+        uuid: Uuid,
+    },
+    PlayerMoved {
+        uuid: Uuid,
+        position: Vec3,
+    },
+    SwingArm {
+        uuid: Uuid,
+        hand: Hand,
+    },
+    Attack {
+        uuid: Uuid,
+        entity_id: i32,
+    },
+    Respawn {
+        uuid: Uuid,
+    },
+    DropItem {
+        uuid: Uuid,
+        whole_stack: bool,
+        yaw: f32,
+        pitch: f32,
+    },
+    ChatMessage {
+        uuid: Uuid,
+        message: String,
+    },
+    ClientSettings {
+        uuid: Uuid,
+        view_distance: u8,
+        chat_mode: ChatMode,
+        main_hand: Arm,
+        text_filtering: bool,
+    },
+    PlayerCommand {
+        uuid: Uuid,
+        command: String,
+    },
+    HeldItemChanged {
+        uuid: Uuid,
+        slot: u8,
+    },
+    UpdateSign {
+        uuid: Uuid,
+        pos: BlockPos,
+        lines: [String; 4],
+    },
+    Reloaded {
+        source: SourceKind,
+        reloaded: Reloaded,
+    },
+    Audit(AuditEntry),
+}
+
+/// What was read from disk off the server loop, to be swapped in by it.
+#[derive(Debug)]
+enum Reloaded {
+    Favicon(io::Result<String>),
+    Registries(Result<Box<Registries>, RegistryError>),
+}
+
+/// The registries loaded from the reports, which are replaced together.
+#[derive(Debug)]
+struct Registries {
+    blocks: BlockRegistry,
+    items: ItemRegistry,
+    entity_types: EntityTypeRegistry,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -138,7 +2138,7 @@ pub struct Version {
     pub protocol_version: u32,
 }
 impl Version {
-    const CURRENT: Self = Self {
+    pub const CURRENT: Self = Self {
         name: "1.17.1",
         protocol_version: 756,
     };
@@ -160,8 +2160,942 @@ impl Ord for Version {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Describes the build of the server, with the git commit it was built from
+/// if it is known, like `hieronymusv2 0.1.0 (1a2b3c4)`.
+pub fn build_info() -> String {
+    let name = env!("CARGO_PKG_NAME");
+    let version = env!("CARGO_PKG_VERSION");
+    match option_env!("HIERONYMUS_GIT_HASH") {
+        Some(hash) => format!("{} {} ({})", name, version, hash),
+        None => format!("{} {}", name, version),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub username: String,
     pub uuid: Uuid,
 }
+
+/// A [`Player`] that is currently connected to the server.
+#[derive(Debug)]
+struct OnlinePlayer {
+    player: Player,
+    client: ClientHook,
+    entity_id: i32,
+    /// The last position reported by the client, if any.
+    position: Option<Vec3>,
+    inventory: Inventory,
+    /// The selected hotbar slot, from 0 to 8.
+    selected_slot: u8,
+    /// Whether the player is hidden from the tab list of other players and
+    /// from the server list.
+    vanished: bool,
+    /// The view distance the client asked for.
+    requested_view_distance: u8,
+    /// The view distance of the client, clamped to the one of the server.
+    view_distance: u8,
+    /// Which chat messages the player wants to see.
+    chat_mode: ChatMode,
+    /// The hand the player holds items in, as shown to other players.
+    main_hand: Arm,
+    /// Whether the player wants chat messages to be filtered.
+    text_filtering: bool,
+    /// Whether the player is a fake one spawned with `/npc`, without a client.
+    npc: bool,
+    /// The attributes of the player that were changed from their defaults.
+    attributes: Vec<Attribute>,
+    /// The effects the player is under.
+    effects: Vec<Effect>,
+    /// The health of the player, who is dead at 0 until they respawn.
+    health: f32,
+    /// How many ticks are left until the player can be hurt again.
+    hurt_cooldown: u32,
+    /// The sign the player placed and was sent the editor for, which is the
+    /// only one they may set the text of.
+    editing_sign: Option<BlockPos>,
+}
+impl OnlinePlayer {
+    fn spawn_packet(&self, position: Vec3) -> ResponseBuilder {
+        packets::spawn_player(self.entity_id, self.player.uuid, position)
+    }
+
+    /// Returns the Entity Metadata other players need after spawning the
+    /// player.
+    fn metadata_packet(&self) -> ResponseBuilder {
+        packets::player_metadata(self.entity_id, self.main_hand)
+    }
+
+    /// Returns what other players need to be sent after spawning the player:
+    /// their metadata, attributes and effects.
+    fn tracking_packets(&self) -> Vec<ResponseBuilder> {
+        let mut sent = vec![self.metadata_packet()];
+        if !self.attributes.is_empty() {
+            sent.push(packets::update_attributes(self.entity_id, &self.attributes));
+        }
+        sent.extend(
+            self.effects
+                .iter()
+                .map(|effect| packets::entity_effect(self.entity_id, effect)),
+        );
+        sent
+    }
+
+    /// Returns whether `target` is within [reach](REACH) of the player's eyes.
+    /// Players whose position isn't known yet can't reach anything.
+    fn can_reach(&self, target: Vec3) -> bool {
+        self.position.is_some_and(|position| {
+            (position + Vec3::new(0.0, EYE_HEIGHT, 0.0)).distance(target) <= REACH
+        })
+    }
+
+    /// Returns how far away entities and blocks can be for the player to see
+    /// them.
+    fn range(&self) -> f64 {
+        tracking_range(self.view_distance)
+    }
+}
+
+#[cfg(test)]
+impl Server {
+    /// Creates a server that isn't connected to anything, for use in tests.
+    pub(crate) fn for_tests(operators: &[&str]) -> Self {
+        let mut config: Config = toml::from_str("").unwrap();
+        config.operators = operators.iter().map(|op| op.to_string()).collect();
+        config.audit_log_path = std::env::temp_dir().join("hieronymus-test-audit.log");
+        config.seed = Some(0);
+        let audit_log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.audit_log_path)
+            .unwrap();
+        let registries = Registries {
+            blocks: BlockRegistry::new(),
+            items: ItemRegistry::new(),
+            entity_types: EntityTypeRegistry::new(),
+        };
+
+        Self::from_parts(
+            mpsc::channel(1).1,
+            Arc::new(config),
+            None,
+            registries,
+            Allowlist::new(false),
+            AuditWriter::spawn(audit_log).unwrap(),
+        )
+    }
+
+    /// Runs a server in the background, returning a hook to it.
+    pub(crate) fn spawn_for_tests() -> ServerHook {
+        let (hook, rx) = ServerHook::channel(100);
+        let server = Server {
+            rx,
+            ..Self::for_tests(&[])
+        };
+        tokio::spawn(server.server_loop());
+        hook
+    }
+
+    /// Adds a player at the given position, returning the events sent to their
+    /// client from then on.
+    pub(crate) fn join_for_tests(
+        &mut self,
+        username: &str,
+        position: Vec3,
+    ) -> (Player, mpsc::UnboundedReceiver<crate::net::ClientEvent>) {
+        let player = Player {
+            username: username.to_string(),
+            uuid: Uuid::new_v4(),
+        };
+        let (client, mut events) = ClientHook::new();
+        self.check_login(&player).unwrap();
+        self.join_game(player.clone(), client);
+        self.player_moved(player.uuid, position);
+        while events.try_recv().is_ok() {}
+        (player, events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use tokio::sync::{mpsc, oneshot};
+    use uuid::Uuid;
+
+    use super::{
+        ClientHook, Inner, LoginError, Notification, Player, Request, Server, ServerEvent,
+        ServerHook, HURT_COOLDOWN,
+    };
+    use crate::{
+        audit::{AuditEntry, AuditWriter, DisconnectReason},
+        chat::{Chat, ChatMode},
+        data::{Animation, Arm, Identifier, SlotData},
+        math::{BlockPos, Vec3},
+        net::{packets, ClientEvent},
+        registry::{block::AIR, entity::SpawnKind},
+        server::{
+            boss_bar::{BossBar, BossBarColor, BossBarDivision},
+            command::{CommandError, SourceKind},
+            effect::{Effect, EffectFlags},
+            entity::Entity,
+            world::BlockEntity,
+        },
+    };
+
+    #[test]
+    fn test_max_players() {
+        let mut server = Server::for_tests(&["op"]);
+        Arc::get_mut(&mut server.config).unwrap().max_players = 2;
+        let player = |username: &str| Player {
+            username: username.to_string(),
+            uuid: Uuid::new_v4(),
+        };
+
+        server.join_for_tests("first", Vec3::ZERO);
+        assert!(server.check_login(&player("second")).is_ok());
+        assert_eq!(server.player_count(), 2);
+
+        // the slot reserved by the second player counts, even before they join
+        assert!(matches!(
+            server.check_login(&player("third")),
+            Err(LoginError::ServerFull)
+        ));
+        assert_eq!(server.player_count(), 2);
+
+        // operators can join a full server
+        assert!(server.check_login(&player("op")).is_ok());
+        assert_eq!(server.player_count(), 3);
+    }
+
+    #[test]
+    fn test_login_abandoned() {
+        let mut server = Server::for_tests(&[]);
+        Arc::get_mut(&mut server.config).unwrap().max_players = 1;
+        let player = Player {
+            username: "player".into(),
+            uuid: Uuid::new_v4(),
+        };
+        server.check_login(&player).unwrap();
+        assert_eq!(server.player_count(), 1);
+
+        // the connection is dropped before the player joins
+        server.handle_notification(Notification::PlayerLeft { uuid: player.uuid });
+        assert_eq!(server.player_count(), 0);
+        // ...which only gives back the slot once
+        server.handle_notification(Notification::PlayerLeft { uuid: player.uuid });
+        assert_eq!(server.player_count(), 0);
+
+        // the slot can be taken again
+        let (other, _) = server.join_for_tests("other", Vec3::ZERO);
+        assert_eq!(server.player_count(), 1);
+        server.handle_notification(Notification::PlayerLeft { uuid: other.uuid });
+        assert_eq!(server.player_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_motd_placeholders() {
+        let mut server = Server::for_tests(&[]);
+        Arc::get_mut(&mut server.config).unwrap().motd = "{online}/{max} on {version}".into();
+        server.join_for_tests("first", Vec3::ZERO);
+        server.join_for_tests("second", Vec3::ZERO);
+
+        let (tx, rx) = oneshot::channel();
+        server
+            .handle_request(Request::GetServerStatus { tx })
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_str(&rx.await.unwrap()).unwrap();
+        assert_eq!(status["description"]["text"], "2/20 on 1.17.1");
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() {
+        let mut server = Server::for_tests(&["op"]);
+        let player = |username: &str| Player {
+            username: username.to_string(),
+            uuid: Uuid::new_v4(),
+        };
+        server.maintenance = true;
+        match server.check_login(&player("player")) {
+            Err(e @ LoginError::Maintenance(_)) => {
+                assert_eq!(e.to_string(), server.config.maintenance_message)
+            }
+            result => panic!("expected a maintenance kick, got {:?}", result),
+        }
+        assert_eq!(server.player_count(), 0);
+        assert!(server.check_login(&player("op")).is_ok());
+
+        // the server still shows up in the server list, with its own motd
+        Arc::get_mut(&mut server.config).unwrap().maintenance_motd = Some("Back soon".into());
+        let (tx, rx) = oneshot::channel();
+        server
+            .handle_request(Request::GetServerStatus { tx })
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_str(&rx.await.unwrap()).unwrap();
+        assert_eq!(status["description"]["text"], "Back soon");
+
+        server.maintenance = false;
+        assert!(server.check_login(&player("player")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_command() {
+        let hook = Server::spawn_for_tests();
+        let player = SourceKind::Player(Player {
+            username: "player".to_string(),
+            uuid: Uuid::new_v4(),
+        });
+
+        // the console runs operator commands...
+        let feedback = hook
+            .run_command(SourceKind::Console, "maintenance on")
+            .await
+            .unwrap();
+        assert_eq!(feedback.unwrap(), "Only operators can join the server now");
+        // ...which players that aren't operators can't
+        let result = hook.run_command(player.clone(), "maintenance off").await;
+        assert!(matches!(result, Ok(Err(CommandError::NoPermission))));
+        // but both can run the rest, unless it only makes sense for players
+        for source in [SourceKind::Console, player.clone()] {
+            let result = hook.run_command(source, "version").await.unwrap();
+            assert!(result.is_ok());
+        }
+        let result = hook.run_command(SourceKind::Console, "vanish").await;
+        assert!(matches!(result, Ok(Err(CommandError::NotAPlayer))));
+    }
+
+    #[tokio::test]
+    async fn test_stop() {
+        let (hook, rx) = mpsc::channel(10);
+        let mut server = Server {
+            rx,
+            ..Server::for_tests(&[])
+        };
+        let (_, mut events) = server.join_for_tests("player", Vec3::ZERO);
+        server.stop_in(0);
+        let server = tokio::spawn(server.server_loop());
+
+        // the server waits for the player to be kicked before stopping
+        assert!(matches!(events.recv().await, Some(ClientEvent::Kick(..))));
+        drop(events);
+        server.await.unwrap().unwrap();
+        assert!(hook.is_closed());
+    }
+
+    #[test]
+    fn test_duplicate_username() {
+        let mut server = Server::for_tests(&[]);
+        let player = |username: &str| Player {
+            username: username.to_string(),
+            uuid: Uuid::new_v4(),
+        };
+        server.join_for_tests("Notch", Vec3::ZERO);
+
+        // online mode has the session server to sort it out
+        assert!(server.check_login(&player("Notch")).is_ok());
+
+        Arc::get_mut(&mut server.config).unwrap().online_mode = false;
+        let count = server.player_count();
+        for username in ["Notch", "notch"] {
+            assert!(matches!(
+                server.check_login(&player(username)),
+                Err(LoginError::AlreadyOnline)
+            ));
+        }
+        assert_eq!(server.player_count(), count);
+
+        // the name is reserved as soon as the login is accepted...
+        let jeb = player("jeb_");
+        assert!(server.check_login(&jeb).is_ok());
+        assert!(matches!(
+            server.check_login(&player("JEB_")),
+            Err(LoginError::AlreadyOnline)
+        ));
+        assert!(server.spawn_npc("jeb_", Vec3::ZERO).is_err());
+        // ...until the player leaves without joining
+        server.leave_game(jeb.uuid);
+        assert!(server.check_login(&player("jeb_")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_reply() {
+        let (hook, rx) = mpsc::channel(10);
+        let mut server = Server {
+            rx,
+            ..Server::for_tests(&[])
+        };
+
+        // the connections making these requests are gone before the replies
+        let (tx, _) = oneshot::channel();
+        hook.send(ServerEvent(Inner::Request(Request::GetServerStatus { tx })))
+            .await
+            .unwrap();
+        let (tx, _) = oneshot::channel();
+        let player = Player {
+            username: "gone".into(),
+            uuid: Uuid::new_v4(),
+        };
+        hook.send(ServerEvent(Inner::Request(Request::CheckLogin {
+            player,
+            tx,
+        })))
+        .await
+        .unwrap();
+        // ...but this one is still waiting
+        let (tx, status) = oneshot::channel();
+        hook.send(ServerEvent(Inner::Request(Request::GetServerStatus { tx })))
+            .await
+            .unwrap();
+        drop(hook);
+
+        server.handle_events().await.unwrap();
+        assert!(status.await.is_ok());
+        assert_eq!(server.player_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_channel_depth() {
+        let (hook, mut rx) = ServerHook::channel(4);
+        assert_eq!(hook.depth(), 0);
+        hook.audit(AuditEntry::kick(
+            None,
+            "127.0.0.1:25565".parse().unwrap(),
+            DisconnectReason::LoginRefused,
+            "test",
+        ))
+        .await
+        .unwrap();
+        hook.player_moved(Uuid::new_v4(), Vec3::ZERO).unwrap();
+        assert_eq!(hook.depth(), 2);
+
+        // movements are dropped instead of waiting when the channel is full
+        for _ in 0..4 {
+            hook.player_moved(Uuid::new_v4(), Vec3::ZERO).unwrap();
+        }
+        assert_eq!(hook.depth(), 4);
+
+        rx.recv().await.unwrap();
+        assert_eq!(hook.depth(), 3);
+    }
+
+    #[test]
+    fn test_audit_written_off_loop() {
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let written = Shared::default();
+        let mut server = Server {
+            audit_log: AuditWriter::spawn(written.clone()).unwrap(),
+            ..Server::for_tests(&[])
+        };
+        server.handle_notification(Notification::Audit(AuditEntry::kick(
+            None,
+            "127.0.0.1:25565".parse().unwrap(),
+            DisconnectReason::LoginRefused,
+            "test",
+        )));
+
+        // the entry shows up once the writer thread gets to it
+        for _ in 0..100 {
+            if !written.0.lock().unwrap().is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let log = String::from_utf8(written.0.lock().unwrap().clone()).unwrap();
+        assert!(log.ends_with("kick:login_refused <unknown> from 127.0.0.1:25565: test\n"));
+    }
+
+    #[tokio::test]
+    async fn test_event_routing() {
+        let (hook, rx) = ServerHook::channel(10);
+        let mut server = Server {
+            rx,
+            ..Server::for_tests(&[])
+        };
+        let (player, _events) = server.join_for_tests("player", Vec3::ZERO);
+        let pos = BlockPos::new(0, 63, 0);
+        server.world.set_block(pos, 1);
+
+        hook.player_moved(player.uuid, Vec3::new(1.0, 64.0, 2.0))
+            .unwrap();
+        let (tx, state) = oneshot::channel();
+        hook.request(Request::DigBlock {
+            uuid: player.uuid,
+            pos,
+            finished: true,
+            tx,
+        })
+        .await
+        .unwrap();
+        drop(hook);
+        server.handle_events().await.unwrap();
+
+        assert_eq!(
+            server.online_player("player").unwrap().position,
+            Some(Vec3::new(1.0, 64.0, 2.0))
+        );
+        assert_eq!(state.await.unwrap(), (AIR, true));
+    }
+
+    #[test]
+    fn test_update_sign() {
+        let mut server = Server::for_tests(&[]);
+        let (player, _) = server.join_for_tests("player", Vec3::new(0.0, 64.0, 0.0));
+        let (other, _) = server.join_for_tests("other", Vec3::new(0.0, 64.0, 0.0));
+        let sign = BlockPos::new(1, 64, 0);
+        let lines = |text: &str| [text.to_string(), "".into(), "".into(), "".into()];
+        let text = |server: &mut Server| match server.world.block_entity_mut(sign) {
+            Some(BlockEntity::Sign { lines }) => lines[0].clone(),
+            _ => panic!("the sign is gone"),
+        };
+        server.world.set_block_entity(sign, BlockEntity::sign());
+        server.online_player_mut("player").unwrap().editing_sign = Some(sign);
+
+        // only the player who placed it can edit it
+        server.update_sign(other.uuid, sign, lines("other"));
+        assert_eq!(text(&mut server), "");
+        server.update_sign(player.uuid, sign, lines("hello"));
+        assert_eq!(text(&mut server), "hello");
+        // and only once
+        server.update_sign(player.uuid, sign, lines("again"));
+        assert_eq!(text(&mut server), "hello");
+
+        // nor from out of reach
+        server.online_player_mut("player").unwrap().editing_sign = Some(sign);
+        server.player_moved(player.uuid, Vec3::new(20.0, 64.0, 0.0));
+        server.update_sign(player.uuid, sign, lines("far"));
+        assert_eq!(text(&mut server), "hello");
+    }
+
+    #[test]
+    fn test_dig_block() {
+        let mut server = Server::for_tests(&[]);
+        let (player, _events) = server.join_for_tests("player", Vec3::new(0.0, 64.0, 0.0));
+        let near = BlockPos::new(2, 63, 0);
+        let far = BlockPos::new(10, 63, 0);
+        server.world.set_block(near, 1);
+        server.world.set_block(far, 1);
+
+        // starting to dig doesn't change anything yet
+        assert_eq!(server.dig_block(player.uuid, near, false), (1, true));
+        assert_eq!(server.dig_block(player.uuid, near, true), (AIR, true));
+        // blocks out of reach are left alone, and the client is told so
+        assert_eq!(server.dig_block(player.uuid, far, true), (1, false));
+        assert_eq!(server.world.block(far), 1);
+        // as are positions outside of the world, however close
+        let below = BlockPos::new(0, -1, 0);
+        server.player_moved(player.uuid, Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(server.dig_block(player.uuid, below, true), (AIR, false));
+        // and players that aren't online can't dig at all
+        assert!(!server.dig_block(Uuid::new_v4(), near, true).1);
+    }
+
+    #[test]
+    fn test_leave_game() {
+        let mut server = Server::for_tests(&[]);
+        let (leaving, _) = server.join_for_tests("leaving", Vec3::ZERO);
+        let (_, mut events) = server.join_for_tests("staying", Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(server.player_count(), 2);
+
+        server.leave_game(leaving.uuid);
+        assert!(server.online_player("leaving").is_err());
+        assert_eq!(server.player_count(), 1);
+        // the other player gets to know, and stops seeing them
+        let mut received = 0;
+        while events.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, 2);
+
+        // leaving twice is fine
+        server.leave_game(leaving.uuid);
+        assert_eq!(server.player_count(), 1);
+    }
+
+    #[test]
+    fn test_drop_item() {
+        let mut server = Server::for_tests(&[]);
+        let (dropper, mut events) = server.join_for_tests("dropper", Vec3::ZERO);
+        let (_, mut near_events) = server.join_for_tests("near", Vec3::new(5.0, 0.0, 0.0));
+        while events.try_recv().is_ok() {}
+        let stack = |server: &Server| {
+            let slot = server.online_player("dropper").unwrap().inventory.slot(36);
+            slot.as_ref().map(|stack| stack.count())
+        };
+        *server
+            .online_player_mut("dropper")
+            .unwrap()
+            .inventory
+            .slot_mut(36) = Some(SlotData::new(1, 10));
+
+        server.drop_item(dropper.uuid, false, 0.0, 0.0);
+        assert_eq!(stack(&server), Some(9));
+        let (&entity_id, item) = server.items.iter().next().unwrap();
+        assert_eq!(item.item.count(), 1);
+        // the dropper's hotbar is updated, and both players see the item
+        assert!(matches!(events.try_recv(), Ok(ClientEvent::Packet(_))));
+        for events in [&mut events, &mut near_events] {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => {
+                    assert_eq!(packet, packets::spawn_item_entity(item))
+                }
+                event => panic!("expected Spawn Entity, got {:?}", event),
+            }
+            assert!(matches!(events.try_recv(), Ok(ClientEvent::Packet(_))));
+        }
+
+        server.drop_item(dropper.uuid, true, 0.0, 0.0);
+        assert_eq!(stack(&server), None);
+        let item = server.items.values().find(|i| i.entity_id != entity_id);
+        assert_eq!(item.unwrap().item.count(), 9);
+
+        // nothing left to drop
+        server.drop_item(dropper.uuid, true, 0.0, 0.0);
+        assert_eq!(server.items.len(), 2);
+    }
+
+    #[test]
+    fn test_pvp() {
+        let mut server = Server::for_tests(&[]);
+        Arc::get_mut(&mut server.config).unwrap().pvp = false;
+        let (attacker, mut attacker_events) = server.join_for_tests("attacker", Vec3::ZERO);
+        let (_, mut target_events) = server.join_for_tests("target", Vec3::new(2.0, 0.0, 0.0));
+        while attacker_events.try_recv().is_ok() {}
+        let target = server.online_player("target").unwrap().entity_id;
+
+        server.attack(attacker.uuid, target);
+        assert_eq!(server.online_player("target").unwrap().health, 20.0);
+        assert!(target_events.try_recv().is_err());
+        assert!(attacker_events.try_recv().is_err());
+
+        // mobs can be hurt either way
+        let position = Vec3::new(1.0, 0.0, 0.0);
+        let entity_id = server.allocate_entity_id();
+        server.entities.insert(
+            entity_id,
+            Entity {
+                entity_id,
+                uuid: Uuid::new_v4(),
+                entity_type: 107,
+                kind: SpawnKind::Living,
+                position,
+                rotation: (0.0, 0.0),
+            },
+        );
+        server.player_moved(attacker.uuid, Vec3::ZERO);
+        while attacker_events.try_recv().is_ok() {}
+        while target_events.try_recv().is_ok() {}
+        server.attack(attacker.uuid, entity_id);
+        match attacker_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => assert_eq!(
+                packet,
+                packets::entity_animation(entity_id, Animation::TakeDamage)
+            ),
+            event => panic!("expected Entity Animation, got {:?}", event),
+        }
+
+        Arc::get_mut(&mut server.config).unwrap().pvp = true;
+        server.attack(attacker.uuid, target);
+        assert_eq!(server.online_player("target").unwrap().health, 19.0);
+        match target_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::update_health(19.0, 20, 5.0))
+            }
+            event => panic!("expected Update Health, got {:?}", event),
+        }
+        for events in [&mut attacker_events, &mut target_events] {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(
+                    packet,
+                    packets::entity_animation(target, Animation::TakeDamage)
+                ),
+                event => panic!("expected Entity Animation, got {:?}", event),
+            }
+        }
+
+        // the target can't be hurt again right away
+        server.attack(attacker.uuid, target);
+        assert_eq!(server.online_player("target").unwrap().health, 19.0);
+        for _ in 0..HURT_COOLDOWN {
+            server.tick();
+        }
+        server.attack(attacker.uuid, target);
+        assert_eq!(server.online_player("target").unwrap().health, 18.0);
+
+        // nor from too far away, even if they can be seen
+        for _ in 0..HURT_COOLDOWN {
+            server.tick();
+        }
+        server.player_moved(attacker.uuid, Vec3::new(-5.0, 0.0, 0.0));
+        server.attack(attacker.uuid, target);
+        assert_eq!(server.online_player("target").unwrap().health, 18.0);
+    }
+
+    #[test]
+    fn test_respawn() {
+        let mut server = Server::for_tests(&[]);
+        let (player, mut events) = server.join_for_tests("player", Vec3::ZERO);
+
+        // only dead players can respawn
+        server.respawn(player.uuid);
+        assert!(events.try_recv().is_err());
+
+        let mut dimension = vec![];
+        nbt::to_writer(
+            &mut dimension,
+            &server.dimension_manager.current_dimension(),
+            None,
+        )
+        .unwrap();
+        server.online_player_mut("player").unwrap().health = 0.0;
+        server.respawn(player.uuid);
+        assert_eq!(server.online_player("player").unwrap().health, 20.0);
+        match events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, packets::respawn(&dimension)),
+            event => panic!("expected Respawn, got {:?}", event),
+        }
+        match events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::update_health(20.0, 20, 5.0))
+            }
+            event => panic!("expected Update Health, got {:?}", event),
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ClientEvent::Teleport { .. })
+        ));
+    }
+
+    #[test]
+    fn test_collect_item() {
+        let mut server = Server::for_tests(&[]);
+        let (collector, mut events) = server.join_for_tests("collector", Vec3::ZERO);
+        let (far, mut far_events) = server.join_for_tests("far", Vec3::new(5000.0, 0.0, 0.0));
+        while events.try_recv().is_ok() {}
+        let collector_id = server.online_player("collector").unwrap().entity_id;
+        // an item entity only the collector can see
+        let item_id = 1000;
+        server
+            .entity_tracker
+            .track(collector.uuid, item_id, 1.0, 10.0);
+        server.entity_tracker.track(far.uuid, item_id, 5000.0, 10.0);
+
+        server.collect_item(item_id, collector_id, 3);
+        let expected = [
+            packets::collect_item(item_id, collector_id, 3),
+            packets::destroy_entities(&[item_id]),
+        ];
+        for expected in expected {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, expected),
+                event => panic!("expected {:?}, got {:?}", expected, event),
+            }
+        }
+        assert!(far_events.try_recv().is_err());
+        assert!(!server.entity_tracker.is_tracking(collector.uuid, item_id));
+    }
+
+    #[test]
+    fn test_client_view_distance() {
+        let mut server = Server::for_tests(&[]);
+        server.view_distance = 10;
+        let (player, mut events) = server.join_for_tests("player", Vec3::ZERO);
+        let (far, _) = server.join_for_tests("far", Vec3::new(100.0, 0.0, 0.0));
+        while events.try_recv().is_ok() {}
+        assert_eq!(server.online_player("player").unwrap().view_distance, 10);
+
+        let mut settings = |view_distance| {
+            server.handle_notification(Notification::ClientSettings {
+                uuid: player.uuid,
+                view_distance,
+                chat_mode: ChatMode::Enabled,
+                main_hand: Arm::Right,
+                text_filtering: false,
+            });
+            server.online_player("player").unwrap().view_distance
+        };
+        assert_eq!(settings(32), 10);
+        assert_eq!(settings(6), 6);
+        assert_eq!(settings(0), 2);
+
+        // the player no longer sees players beyond their own view distance
+        server.player_moved(player.uuid, Vec3::ZERO);
+        let far_id = server.online_player(&far.username).unwrap().entity_id;
+        match events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::destroy_entities(&[far_id]))
+            }
+            event => panic!("expected Destroy Entities, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_chat_mode() {
+        let mut server = Server::for_tests(&[]);
+        let mut join = |username, chat_mode| {
+            let (player, events) = server.join_for_tests(username, Vec3::ZERO);
+            server.handle_notification(Notification::ClientSettings {
+                uuid: player.uuid,
+                view_distance: 10,
+                chat_mode,
+                main_hand: Arm::Right,
+                text_filtering: false,
+            });
+            (player, events)
+        };
+        let (sender, mut sender_events) = join("sender", ChatMode::Enabled);
+        let (commands, mut commands_events) = join("commands", ChatMode::CommandsOnly);
+        let (hidden, mut hidden_events) = join("hidden", ChatMode::Hidden);
+        let drain = |events: &mut mpsc::UnboundedReceiver<ClientEvent>| {
+            while events.try_recv().is_ok() {}
+        };
+        drain(&mut sender_events);
+        drain(&mut commands_events);
+        drain(&mut hidden_events);
+
+        // only players with chat enabled see what other players say...
+        server.handle_notification(Notification::ChatMessage {
+            uuid: sender.uuid,
+            message: "hello".into(),
+        });
+        assert!(matches!(
+            sender_events.try_recv(),
+            Ok(ClientEvent::Packet(_))
+        ));
+        assert!(commands_events.try_recv().is_err());
+        assert!(hidden_events.try_recv().is_err());
+
+        // ...while messages from the server only reach commands-only players
+        // if they are the feedback of their own commands
+        server.stop_in(10);
+        for events in [&mut sender_events, &mut hidden_events] {
+            assert!(matches!(events.try_recv(), Ok(ClientEvent::Prepared(_))));
+        }
+        assert!(commands_events.try_recv().is_err());
+        for (player, events) in [
+            (&commands, &mut commands_events),
+            (&hidden, &mut hidden_events),
+        ] {
+            server.handle_notification(Notification::PlayerCommand {
+                uuid: player.uuid,
+                command: "uptime".into(),
+            });
+            assert!(matches!(
+                events.try_recv(),
+                Ok(ClientEvent::SystemMessage(_))
+            ));
+        }
+        // nor do whispers
+        server.run_player_command(sender.uuid, "msg commands hi");
+        server.run_player_command(sender.uuid, "msg hidden hi");
+        drain(&mut sender_events);
+        assert!(commands_events.try_recv().is_err());
+        assert!(matches!(
+            hidden_events.try_recv(),
+            Ok(ClientEvent::Packet(_))
+        ));
+    }
+
+    #[test]
+    fn test_main_hand() {
+        let mut server = Server::for_tests(&[]);
+        let (player, mut events) = server.join_for_tests("player", Vec3::ZERO);
+        let (_, mut viewer_events) = server.join_for_tests("viewer", Vec3::ZERO);
+        let entity_id = server.online_player("player").unwrap().entity_id;
+        while events.try_recv().is_ok() {}
+        while viewer_events.try_recv().is_ok() {}
+
+        let mut settings = |main_hand| {
+            server.handle_notification(Notification::ClientSettings {
+                uuid: player.uuid,
+                view_distance: 10,
+                chat_mode: ChatMode::Enabled,
+                main_hand,
+                text_filtering: false,
+            })
+        };
+        // players see each other holding items in the left hand...
+        settings(Arm::Left);
+        let expected = packets::player_metadata(entity_id, Arm::Left);
+        for events in [&mut events, &mut viewer_events] {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, expected),
+                event => panic!("expected Entity Metadata, got {:?}", event),
+            }
+        }
+        // ...and only hear about it again once it changes
+        settings(Arm::Left);
+        assert!(viewer_events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_boss_bar_rejoin() {
+        let mut server = Server::for_tests(&[]);
+        let (player, _) = server.join_for_tests("player", Vec3::ZERO);
+        let bar = BossBar::new(Chat::text("Boss"), BossBarColor::Red, BossBarDivision::None);
+        let add = bar.add_action();
+        let bar_uuid = server.create_boss_bar(bar);
+        server.show_boss_bar(bar_uuid, player.uuid);
+        server.leave_game(player.uuid);
+        assert_eq!(server.boss_bars.get(bar_uuid).unwrap().viewers().count(), 0);
+
+        // coming back with the same UUID shows the bar again
+        let (client, mut events) = ClientHook::new();
+        server.check_login(&player).unwrap();
+        server.join_game(player.clone(), client);
+        while events.try_recv().is_ok() {}
+        server.show_boss_bar(bar_uuid, player.uuid);
+        match events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::boss_bar(bar_uuid, &add))
+            }
+            event => panic!("expected a Boss Bar, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_tracking_state() {
+        let mut server = Server::for_tests(&[]);
+        server.join_for_tests("player", Vec3::ZERO);
+        let (viewer, mut events) = server.join_for_tests("viewer", Vec3::new(1000.0, 0.0, 0.0));
+        let armor = Identifier::minecraft("generic.armor");
+        server.set_attribute("player", &armor, 4.0).unwrap();
+        let effect = Effect {
+            id: 1,
+            amplifier: 0,
+            duration: 200,
+            flags: EffectFlags::SHOW_ICON,
+        };
+        server.add_effect("player", effect).unwrap();
+        assert!(events.try_recv().is_err());
+
+        // players coming close later see the attributes and effects too
+        server.player_moved(viewer.uuid, Vec3::new(1.0, 0.0, 0.0));
+        let player = server.online_player("player").unwrap();
+        let expected = [
+            player.spawn_packet(Vec3::ZERO),
+            player.metadata_packet(),
+            packets::update_attributes(player.entity_id, &player.attributes),
+            packets::entity_effect(player.entity_id, &effect),
+        ];
+        for expected in expected {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, expected),
+                event => panic!("expected {:?}, got {:?}", expected, event),
+            }
+        }
+    }
+}
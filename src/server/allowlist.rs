@@ -0,0 +1,96 @@
+//! The list of players allowed to join the server.
+//!
+//! The allowlist file contains one username per line; empty lines and lines
+//! starting with `#` are ignored. Whether the list is actually enforced is
+//! configured separately, so that it can be turned on and off without editing
+//! the file.
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    usernames: BTreeSet<String>,
+    enforced: bool,
+}
+
+impl Allowlist {
+    pub fn new(enforced: bool) -> Self {
+        Self {
+            usernames: BTreeSet::new(),
+            enforced,
+        }
+    }
+    pub fn load<P: AsRef<Path>>(path: P, enforced: bool) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?, enforced))
+    }
+    pub fn parse(list: &str, enforced: bool) -> Self {
+        let usernames = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        Self {
+            usernames,
+            enforced,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.usernames.len()
+    }
+    pub fn is_enforced(&self) -> bool {
+        self.enforced
+    }
+    pub fn set_enforced(&mut self, enforced: bool) {
+        self.enforced = enforced;
+    }
+    pub fn contains(&self, username: &str) -> bool {
+        self.usernames.contains(username)
+    }
+    /// Returns whether a player may join, which is always the case if the
+    /// allowlist isn't enforced.
+    pub fn allows(&self, username: &str) -> bool {
+        !self.enforced || self.contains(username)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Allowlist;
+    use crate::server::{LoginError, Player, Server};
+
+    #[test]
+    fn test_allowlist() {
+        let list = "# friends\nNotch\n\n  jeb_  \n";
+        let mut allowlist = Allowlist::parse(list, true);
+        assert_eq!(allowlist.len(), 2);
+        assert!(allowlist.allows("Notch"));
+        assert!(allowlist.allows("jeb_"));
+        assert!(!allowlist.allows("Dinnerbone"));
+        assert!(!allowlist.allows("# friends"));
+
+        // the list is kept, but not enforced
+        allowlist.set_enforced(false);
+        assert!(allowlist.allows("Dinnerbone"));
+        assert!(allowlist.contains("Notch"));
+    }
+
+    #[test]
+    fn test_login() {
+        let mut server = Server::for_tests(&[]);
+        server.allowlist = Allowlist::parse("Notch", true);
+        let player = |username: &str| Player {
+            username: username.to_string(),
+            uuid: uuid::Uuid::new_v4(),
+        };
+
+        assert!(server.check_login(&player("Notch")).is_ok());
+        assert!(matches!(
+            server.check_login(&player("Dinnerbone")),
+            Err(LoginError::NotAllowed)
+        ));
+
+        server.allowlist.set_enforced(false);
+        assert!(server.check_login(&player("Dinnerbone")).is_ok());
+    }
+}
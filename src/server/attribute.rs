@@ -0,0 +1,74 @@
+//! Attributes, like the movement speed and maximum health of players, which
+//! are the base values of such properties along with modifiers to them.
+use uuid::Uuid;
+
+use crate::data::Identifier;
+
+/// The attributes of players, with their default base value and the range
+/// base values are clamped to.
+pub const PLAYER_ATTRIBUTES: &[(&str, f64, f64, f64)] = &[
+    ("generic.max_health", 20.0, 1.0, 1024.0),
+    ("generic.knockback_resistance", 0.0, 0.0, 1.0),
+    ("generic.movement_speed", 0.1, 0.0, 1024.0),
+    ("generic.attack_damage", 1.0, 0.0, 2048.0),
+    ("generic.attack_speed", 4.0, 0.0, 1024.0),
+    ("generic.armor", 0.0, 0.0, 30.0),
+    ("generic.armor_toughness", 0.0, 0.0, 20.0),
+    ("generic.luck", 0.0, -1024.0, 1024.0),
+];
+
+/// An attribute of an entity, as sent in Update Attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub key: Identifier,
+    pub base: f64,
+    pub modifiers: Vec<AttributeModifier>,
+}
+
+impl Attribute {
+    /// Returns the attribute of a player with the given key, with its base
+    /// value clamped to the range it can have, if players have it.
+    pub fn player(key: &Identifier, base: f64) -> Option<Self> {
+        let &(_, _, min, max) = player_attribute(key)?;
+        Some(Self {
+            key: key.clone(),
+            base: base.clamp(min, max),
+            modifiers: vec![],
+        })
+    }
+
+    /// Returns the default base value of an attribute of players.
+    pub fn player_default(key: &Identifier) -> Option<f64> {
+        player_attribute(key).map(|&(_, default, ..)| default)
+    }
+}
+
+fn player_attribute(key: &Identifier) -> Option<&'static (&'static str, f64, f64, f64)> {
+    if key.namespace != Identifier::DEFAULT_NAMESPACE {
+        return None;
+    }
+    PLAYER_ATTRIBUTES
+        .iter()
+        .find(|(name, ..)| key.path == *name)
+}
+
+/// A modifier to an attribute, which items and effects add. Each one has a
+/// UUID so that it can be taken off again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeModifier {
+    pub uuid: Uuid,
+    pub amount: f64,
+    pub operation: ModifierOperation,
+}
+
+/// How a modifier changes the value of an attribute. Modifiers are applied
+/// in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifierOperation {
+    /// Adds the amount to the base value.
+    Add = 0,
+    /// Adds the amount multiplied by the base value.
+    MultiplyBase = 1,
+    /// Multiplies the value by one plus the amount.
+    Multiply = 2,
+}
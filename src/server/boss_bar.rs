@@ -0,0 +1,167 @@
+//! Boss bars, the bars shown at the top of the screen.
+use std::collections::{HashMap, HashSet};
+
+use bitflags::bitflags;
+use uuid::Uuid;
+
+use crate::chat::Chat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossBarColor {
+    Pink,
+    Blue,
+    Red,
+    Green,
+    Yellow,
+    Purple,
+    White,
+}
+
+/// How many notches the bar is divided into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BossBarDivision {
+    None,
+    Notches6,
+    Notches10,
+    Notches12,
+    Notches20,
+}
+
+bitflags! {
+    pub struct BossBarFlags: u8 {
+        const DARKEN_SKY = 0x01;
+        const DRAGON_BAR = 0x02;
+        const CREATE_FOG = 0x04;
+    }
+}
+
+/// The actions of the Boss Bar packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BossBarAction {
+    Add {
+        title: Chat,
+        health: f32,
+        color: BossBarColor,
+        division: BossBarDivision,
+        flags: BossBarFlags,
+    },
+    Remove,
+    UpdateHealth(f32),
+    UpdateTitle(Chat),
+    UpdateStyle {
+        color: BossBarColor,
+        division: BossBarDivision,
+    },
+    UpdateFlags(BossBarFlags),
+}
+
+#[derive(Debug, Clone)]
+pub struct BossBar {
+    pub title: Chat,
+    /// From 0 to 1.
+    pub health: f32,
+    pub color: BossBarColor,
+    pub division: BossBarDivision,
+    pub flags: BossBarFlags,
+    viewers: HashSet<Uuid>,
+}
+
+impl BossBar {
+    pub fn new(title: Chat, color: BossBarColor, division: BossBarDivision) -> Self {
+        Self {
+            title,
+            health: 1.0,
+            color,
+            division,
+            flags: BossBarFlags::empty(),
+            viewers: HashSet::new(),
+        }
+    }
+
+    /// Returns the action that shows the bar as it currently is.
+    pub fn add_action(&self) -> BossBarAction {
+        BossBarAction::Add {
+            title: self.title.clone(),
+            health: self.health,
+            color: self.color,
+            division: self.division,
+            flags: self.flags,
+        }
+    }
+
+    /// Applies an update to the bar.
+    ///
+    /// [`BossBarAction::Add`] replaces the whole bar, and
+    /// [`BossBarAction::Remove`] does nothing.
+    pub fn apply(&mut self, action: &BossBarAction) {
+        match action.clone() {
+            BossBarAction::Add {
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => {
+                self.title = title;
+                self.health = health;
+                self.color = color;
+                self.division = division;
+                self.flags = flags;
+            }
+            BossBarAction::Remove => {}
+            BossBarAction::UpdateHealth(health) => self.health = health,
+            BossBarAction::UpdateTitle(title) => self.title = title,
+            BossBarAction::UpdateStyle { color, division } => {
+                self.color = color;
+                self.division = division;
+            }
+            BossBarAction::UpdateFlags(flags) => self.flags = flags,
+        }
+    }
+
+    pub fn viewers(&self) -> impl Iterator<Item = &Uuid> {
+        self.viewers.iter()
+    }
+    /// Returns whether the player wasn't already viewing the bar.
+    pub fn add_viewer(&mut self, player: Uuid) -> bool {
+        self.viewers.insert(player)
+    }
+    /// Returns whether the player was viewing the bar.
+    pub fn remove_viewer(&mut self, player: Uuid) -> bool {
+        self.viewers.remove(&player)
+    }
+}
+
+/// Every boss bar on the server, by UUID.
+#[derive(Debug, Default)]
+pub struct BossBars {
+    bars: HashMap<Uuid, BossBar>,
+}
+
+impl BossBars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a bar, returning the UUID it was given.
+    pub fn add(&mut self, bar: BossBar) -> Uuid {
+        let uuid = Uuid::new_v4();
+        self.bars.insert(uuid, bar);
+        uuid
+    }
+    pub fn get(&self, uuid: Uuid) -> Option<&BossBar> {
+        self.bars.get(&uuid)
+    }
+    pub fn get_mut(&mut self, uuid: Uuid) -> Option<&mut BossBar> {
+        self.bars.get_mut(&uuid)
+    }
+    pub fn remove(&mut self, uuid: Uuid) -> Option<BossBar> {
+        self.bars.remove(&uuid)
+    }
+    /// Takes a player off the viewers of every bar, e.g. once they left.
+    pub fn remove_viewer(&mut self, player: Uuid) {
+        for bar in self.bars.values_mut() {
+            bar.remove_viewer(player);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Server-side filtering of chat messages, for players who turned on text
+//! filtering in their settings.
+//!
+//! Words are matched as substrings regardless of ASCII case, so `heck` also
+//! matches `HECKING`. Matches are either masked with asterisks, or make the
+//! whole message blocked.
+
+#[derive(Debug, Clone, Default)]
+pub struct ChatFilter {
+    /// The banned words, with ASCII letters in lowercase.
+    words: Vec<String>,
+    block: bool,
+}
+
+impl ChatFilter {
+    pub fn new(words: &[String], block: bool) -> Self {
+        let words = words
+            .iter()
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_ascii_lowercase())
+            .collect();
+        Self { words, block }
+    }
+
+    /// Returns the message as players who filter text should see it, or
+    /// `None` if they shouldn't see it at all.
+    pub fn filter(&self, message: &str) -> Option<String> {
+        // lowercasing some characters changes their length, which would make
+        // the positions of matches wrong
+        let lowercase = message.to_ascii_lowercase();
+        let mut masked = message.as_bytes().to_vec();
+        let mut matched = false;
+        for word in &self.words {
+            for (start, _) in lowercase.match_indices(word.as_str()) {
+                matched = true;
+                masked[start..start + word.len()].fill(b'*');
+            }
+        }
+        match (matched, self.block) {
+            (false, _) => Some(message.to_string()),
+            (true, true) => None,
+            // the matches are replaced as a whole, so the rest is still UTF-8
+            (true, false) => Some(String::from_utf8(masked).expect("matches are whole characters")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChatFilter;
+    use crate::{
+        chat::{Chat, ChatPosition},
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::Server,
+    };
+
+    #[test]
+    fn test_filter() {
+        let words = ["heck".to_string(), "darn".to_string()];
+        let filter = ChatFilter::new(&words, false);
+        assert_eq!(
+            filter.filter("what the HECK, darn it").unwrap(),
+            "what the ****, **** it"
+        );
+        assert_eq!(filter.filter("hello there").unwrap(), "hello there");
+
+        let filter = ChatFilter::new(&words, true);
+        assert!(filter.filter("oh heck").is_none());
+        assert_eq!(filter.filter("oh no").unwrap(), "oh no");
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut server = Server::for_tests(&[]);
+        server.chat_filter = ChatFilter::new(&["heck".to_string()], false);
+        let (sender, mut sender_events) = server.join_for_tests("sender", Vec3::ZERO);
+        let (filtering, mut filtering_events) = server.join_for_tests("filtering", Vec3::ZERO);
+        while sender_events.try_recv().is_ok() {}
+        server
+            .online_player_mut(&filtering.username)
+            .unwrap()
+            .text_filtering = true;
+
+        server.broadcast_chat(sender.uuid, "oh heck");
+        let expect = |events: &mut tokio::sync::mpsc::UnboundedReceiver<_>, text: &str| {
+            let expected =
+                packets::chat_message(&Chat::text(text), ChatPosition::Chat, sender.uuid);
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, expected),
+                event => panic!("expected a Chat Message, got {:?}", event),
+            }
+        };
+        // only the player who asked for it gets the filtered message
+        expect(&mut sender_events, "<sender> oh heck");
+        expect(&mut filtering_events, "<sender> oh ****");
+
+        server.broadcast_chat(sender.uuid, "oh well");
+        expect(&mut sender_events, "<sender> oh well");
+        expect(&mut filtering_events, "<sender> oh well");
+    }
+}
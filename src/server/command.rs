@@ -0,0 +1,322 @@
+//! Commands players can run by sending a chat message starting with `/`.
+mod allowlist;
+mod attribute;
+pub mod block;
+pub mod coordinate;
+mod effect;
+mod explode;
+mod give;
+mod help;
+mod maintenance;
+mod me;
+mod msg;
+mod npc;
+mod reload;
+mod seed;
+mod setblock;
+mod spectate;
+mod stop;
+mod summon;
+mod tablist;
+mod tp;
+pub mod uptime;
+mod vanish;
+mod version;
+mod viewdistance;
+mod worldborder;
+
+use thiserror::Error;
+use tracing::debug;
+
+use self::coordinate::Coordinates;
+use super::{Player, Server};
+use crate::{
+    data::{Identifier, ParseIdentifierError},
+    math::Vec3,
+};
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    /// What the command does, in a sentence, for `/help`.
+    pub description: &'static str,
+    pub permission: Permission,
+    /// Runs the command with the given arguments, returning the feedback sent
+    /// back to the sender, if it isn't empty.
+    pub run: fn(&mut Server, &CommandSource, &[&str]) -> Result<String, CommandError>,
+}
+
+/// Whether a command was typed into the console, sent over RCON or sent by a
+/// player in chat.
+#[derive(Debug, Clone)]
+pub enum SourceKind {
+    Console,
+    Rcon,
+    Player(Player),
+}
+
+impl From<&Player> for SourceKind {
+    fn from(player: &Player) -> Self {
+        Self::Player(player.clone())
+    }
+}
+
+/// Who runs a command, along with what the server knows about them when it
+/// runs.
+#[derive(Debug, Clone)]
+pub struct CommandSource {
+    pub kind: SourceKind,
+    /// Where the source is, which relative coordinates are relative to. Only
+    /// players have a position, once their client reported it.
+    pub position: Option<Vec3>,
+    pub permission: Permission,
+}
+
+impl CommandSource {
+    /// Looks up the position and permission level of a source. The console
+    /// and RCON may run every command.
+    pub fn new(server: &Server, kind: SourceKind) -> Self {
+        let (position, permission) = match &kind {
+            SourceKind::Console | SourceKind::Rcon => (None, Permission::Operator),
+            SourceKind::Player(player) => (
+                server
+                    .online_player(&player.username)
+                    .ok()
+                    .and_then(|p| p.position),
+                if server.is_op(player) {
+                    Permission::Operator
+                } else {
+                    Permission::Everyone
+                },
+            ),
+        };
+        Self {
+            kind,
+            position,
+            permission,
+        }
+    }
+
+    /// Returns the name of the source, as shown in logs.
+    pub fn name(&self) -> &str {
+        match &self.kind {
+            SourceKind::Console => "Server",
+            SourceKind::Rcon => "Rcon",
+            SourceKind::Player(player) => &player.username,
+        }
+    }
+
+    /// Returns the player running the command, for commands that only make
+    /// sense for players.
+    pub fn player(&self) -> Result<&Player, CommandError> {
+        match &self.kind {
+            SourceKind::Player(player) => Ok(player),
+            _ => Err(CommandError::NotAPlayer),
+        }
+    }
+
+    pub fn is_op(&self) -> bool {
+        self.permission == Permission::Operator
+    }
+
+    /// Returns the position of the source, if it has one.
+    pub fn position(&self) -> Result<Vec3, CommandError> {
+        match (&self.kind, self.position) {
+            (_, Some(position)) => Ok(position),
+            (SourceKind::Player(player), None) => {
+                Err(CommandError::PositionUnknown(player.username.clone()))
+            }
+            _ => Err(CommandError::NoPosition),
+        }
+    }
+
+    /// Resolves coordinates into a position, with relative coordinates being
+    /// offsets from the position of the source.
+    pub fn resolve(&self, coordinates: Coordinates) -> Result<Vec3, CommandError> {
+        if coordinates.is_absolute() {
+            return Ok(coordinates.resolve(Vec3::ZERO));
+        }
+        Ok(coordinates.resolve(self.position()?))
+    }
+}
+
+/// Who may run a command, from the lowest permission level to the highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+    Everyone,
+    Operator,
+}
+
+pub const COMMANDS: &[Command] = &[
+    allowlist::ALLOWLIST,
+    attribute::ATTRIBUTE,
+    effect::EFFECT,
+    explode::EXPLODE,
+    give::GIVE,
+    help::HELP,
+    maintenance::MAINTENANCE,
+    me::ME,
+    msg::MSG,
+    npc::NPC,
+    reload::RELOAD,
+    seed::SEED,
+    setblock::SETBLOCK,
+    spectate::SPECTATE,
+    stop::STOP,
+    summon::SUMMON,
+    tablist::TABLIST,
+    tp::TP,
+    uptime::UPTIME,
+    vanish::VANISH,
+    version::VERSION,
+    viewdistance::VIEWDISTANCE,
+    worldborder::WORLDBORDER,
+];
+
+/// Returns the names of the commands starting with the given prefix, in
+/// alphabetical order.
+pub fn complete(prefix: &str) -> Vec<&'static str> {
+    COMMANDS
+        .iter()
+        .map(|c| c.name)
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+/// Parses and runs a command line, without the leading slash.
+pub fn dispatch(
+    server: &mut Server,
+    source: impl Into<SourceKind>,
+    line: &str,
+) -> Result<String, CommandError> {
+    let source = CommandSource::new(server, source.into());
+    let mut args = line.split_whitespace();
+    let name = args.next().ok_or(CommandError::Empty)?;
+    let command = COMMANDS
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| CommandError::UnknownCommand(name.to_string()))?;
+
+    if source.permission < command.permission {
+        return Err(CommandError::NoPermission);
+    }
+    let args: Vec<_> = args.collect();
+    debug!(name, ?args, source = source.name(), "Running command");
+    (command.run)(server, &source, &args)
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("Please enter a command")]
+    Empty,
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("You do not have permission to use this command")]
+    NoPermission,
+    #[error("Only players can use this command")]
+    NotAPlayer,
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error("Invalid coordinate: {0}")]
+    InvalidCoordinate(String),
+    #[error("Local coordinates (^) are not supported")]
+    LocalCoordinate,
+    #[error("No player was found with the name {0}")]
+    PlayerNotFound(String),
+    #[error("The position of {0} is not known yet")]
+    PositionUnknown(String),
+    #[error("Relative coordinates (~) can only be used by players")]
+    NoPosition,
+    #[error("Invalid identifier {0}: {1}")]
+    InvalidIdentifier(String, ParseIdentifierError),
+    #[error("Unknown block: {0}")]
+    UnknownBlock(Identifier),
+    #[error("Invalid block state: {0}")]
+    InvalidBlockState(String),
+    #[error("Cannot place blocks outside of the world")]
+    OutOfWorld,
+    #[error("Could not set the block")]
+    BlockUnchanged,
+    #[error("Unknown entity type: {0}")]
+    UnknownEntity(Identifier),
+    #[error("{0} cannot be summoned")]
+    CannotSummon(Identifier),
+    #[error("Unknown attribute: {0}")]
+    UnknownAttribute(Identifier),
+    #[error("Invalid attribute value: {0}")]
+    InvalidAttributeValue(String),
+    #[error("Unknown effect: {0}")]
+    UnknownEffect(Identifier),
+    #[error("Invalid effect duration: {0}")]
+    InvalidDuration(String),
+    #[error("Invalid effect amplifier: {0}")]
+    InvalidAmplifier(String),
+    #[error("{0} doesn't have this effect")]
+    NoSuchEffect(String),
+    #[error("Unknown item: {0}")]
+    UnknownItem(Identifier),
+    #[error("Invalid item count: {0}")]
+    InvalidCount(String),
+    #[error("{0}'s inventory is full")]
+    InventoryFull(String),
+    #[error("The server isn't stopping")]
+    NotStopping,
+    #[error("Debug commands are turned off")]
+    DebugCommandsDisabled,
+    #[error("Invalid username: {0}")]
+    InvalidUsername(String),
+    #[error("{0} is already online")]
+    AlreadyOnline(String),
+    #[error("{0} is not an NPC")]
+    NotAnNpc(String),
+    #[error("{0} is too far away")]
+    TooFarAway(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        coordinate::Coordinates, dispatch, CommandError, CommandSource, Permission, SourceKind,
+    };
+    use crate::{
+        math::{BlockPos, Vec3},
+        server::Server,
+    };
+
+    #[test]
+    fn test_source() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, _events) = server.join_for_tests("op", Vec3::new(10.0, 64.0, -5.0));
+        let (player, _) = server.join_for_tests("player", Vec3::ZERO);
+        let relative = Coordinates::parse("~1", "~", "~-1").unwrap();
+        let absolute = Coordinates::parse("1", "2", "3").unwrap();
+
+        // players resolve relative coordinates from where they are...
+        let source = CommandSource::new(&server, (&op).into());
+        assert_eq!(source.permission, Permission::Operator);
+        assert_eq!(
+            source.resolve(relative).unwrap(),
+            Vec3::new(11.0, 64.0, -6.0)
+        );
+        let source = CommandSource::new(&server, (&player).into());
+        assert_eq!(source.permission, Permission::Everyone);
+        assert_eq!(source.position, Some(Vec3::ZERO));
+
+        // ...while the console has nowhere to resolve them from
+        let console = CommandSource::new(&server, SourceKind::Console);
+        assert_eq!(console.permission, Permission::Operator);
+        assert!(matches!(
+            console.resolve(relative),
+            Err(CommandError::NoPosition)
+        ));
+        assert_eq!(console.resolve(absolute).unwrap(), Vec3::new(1.0, 2.0, 3.0));
+
+        let setblock = "setblock ~ ~-1 ~ stone";
+        assert!(matches!(
+            dispatch(&mut server, SourceKind::Console, setblock),
+            Err(CommandError::NoPosition)
+        ));
+        dispatch(&mut server, &op, setblock).unwrap();
+        assert_eq!(server.world.block(BlockPos::new(10, 63, -5)), 1);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const ALLOWLIST: Command = Command {
+    name: "allowlist",
+    usage: "/allowlist (on|off)",
+    description: "Turns enforcing the allowlist on or off",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let enforced = match args {
+        ["on"] => true,
+        ["off"] => false,
+        _ => return Err(CommandError::Usage(ALLOWLIST.usage)),
+    };
+    server.allowlist.set_enforced(enforced);
+    Ok(if enforced {
+        "The allowlist is now enforced".into()
+    } else {
+        "The allowlist is no longer enforced".into()
+    })
+}
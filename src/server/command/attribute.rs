@@ -0,0 +1,92 @@
+use crate::{
+    data::Identifier,
+    server::{attribute::Attribute, Server},
+};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const ATTRIBUTE: Command = Command {
+    name: "attribute",
+    usage: "/attribute <player> <attribute> [value]",
+    description:
+        "Shows or changes the base value of an attribute of a player, like generic.movement_speed",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let (player, key, value) = match *args {
+        [player, key] => (player, key, None),
+        [player, key, value] => (player, key, Some(value)),
+        _ => return Err(CommandError::Usage(ATTRIBUTE.usage)),
+    };
+    let key = Identifier::parse_with_default_namespace(key)
+        .map_err(|e| CommandError::InvalidIdentifier(key.to_string(), e))?;
+    let value = match value {
+        Some(value) => value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| CommandError::InvalidAttributeValue(value.to_string()))?,
+        None => {
+            let default = Attribute::player_default(&key)
+                .ok_or_else(|| CommandError::UnknownAttribute(key.clone()))?;
+            let base = server
+                .online_player(player)?
+                .attributes
+                .iter()
+                .find(|a| a.key == key)
+                .map_or(default, |a| a.base);
+            return Ok(format!("{} of {} is {}", key, player, base));
+        }
+    };
+    let base = server.set_attribute(player, &key, value)?;
+    Ok(format!("Set {} of {} to {}", key, player, base))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::Identifier,
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::{attribute::Attribute, command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_attribute() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, _) = server.join_for_tests("op", Vec3::ZERO);
+        let (_, mut events) = server.join_for_tests("player", Vec3::ZERO);
+        while events.try_recv().is_ok() {}
+
+        assert_eq!(
+            dispatch(&mut server, &op, "attribute player generic.movement_speed").unwrap(),
+            "minecraft:generic.movement_speed of player is 0.1"
+        );
+        assert!(dispatch(&mut server, &op, "attribute player generic.flying").is_err());
+        assert!(dispatch(&mut server, &op, "attribute player generic.luck fast").is_err());
+
+        // values are clamped to what the attribute can be
+        assert_eq!(
+            dispatch(&mut server, &op, "attribute player generic.armor 50").unwrap(),
+            "Set minecraft:generic.armor of player to 30"
+        );
+        let entity_id = server.online_player("player").unwrap().entity_id;
+        let armor = Identifier::minecraft("generic.armor");
+        match events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => assert_eq!(
+                packet,
+                packets::update_attributes(entity_id, &[Attribute::player(&armor, 30.0).unwrap()])
+            ),
+            event => panic!("expected Update Attributes, got {:?}", event),
+        }
+        assert!(dispatch(&mut server, &op, "attribute player generic.armor")
+            .unwrap()
+            .ends_with("is 30"));
+    }
+}
@@ -0,0 +1,68 @@
+//! Parsing of block states given as command arguments.
+use crate::data::Identifier;
+
+use super::CommandError;
+
+/// A block state, like `stone` or `minecraft:oak_log[axis=x]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockState<'a> {
+    pub block: Identifier,
+    /// The properties that were explicitly set.
+    pub properties: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> BlockState<'a> {
+    /// Parses a block state.
+    ///
+    /// The namespace defaults to `minecraft`, as with the vanilla client.
+    pub fn parse(arg: &'a str) -> Result<Self, CommandError> {
+        let invalid = || CommandError::InvalidBlockState(arg.to_string());
+
+        let (block, properties) = match arg.split_once('[') {
+            Some((block, rest)) => (block, rest.strip_suffix(']').ok_or_else(invalid)?),
+            None => (arg, ""),
+        };
+        let block = Identifier::parse_with_default_namespace(block)
+            .map_err(|e| CommandError::InvalidIdentifier(block.to_string(), e))?;
+        let properties = properties
+            .split(',')
+            .filter(|p| !p.is_empty())
+            .map(|p| p.split_once('=').ok_or_else(invalid))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { block, properties })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockState;
+    use crate::data::Identifier;
+
+    #[test]
+    fn test_parse_block_state() {
+        let parse = |arg| {
+            let state = BlockState::parse(arg).unwrap();
+            (state.block.to_string(), state.properties)
+        };
+        assert_eq!(parse("stone"), ("minecraft:stone".into(), vec![]));
+        assert_eq!(
+            parse("minecraft:stone[]"),
+            ("minecraft:stone".into(), vec![])
+        );
+        assert_eq!(
+            parse("hieronymus:oak_log[axis=x]"),
+            ("hieronymus:oak_log".into(), vec![("axis", "x")])
+        );
+        assert_eq!(
+            BlockState::parse("chest[facing=east,type=left]").unwrap(),
+            BlockState {
+                block: Identifier::minecraft("chest"),
+                properties: vec![("facing", "east"), ("type", "left")],
+            }
+        );
+
+        assert!(BlockState::parse("oak_log[axis=x").is_err());
+        assert!(BlockState::parse("oak_log[axis]").is_err());
+        assert!(BlockState::parse("Stone").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Parsing of Minecraft-style coordinates given as command arguments.
+use crate::math::{
+    AbsOrRel::{self, Absolute, Relative},
+    Vec3,
+};
+
+use super::CommandError;
+
+/// A set of three coordinates, each of which may be relative to the position
+/// of whoever or whatever it is applied to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub x: AbsOrRel<f64>,
+    pub y: AbsOrRel<f64>,
+    pub z: AbsOrRel<f64>,
+}
+
+impl Coordinates {
+    pub fn parse(x: &str, y: &str, z: &str) -> Result<Self, CommandError> {
+        Ok(Self {
+            x: parse_coordinate(x)?,
+            y: parse_coordinate(y)?,
+            z: parse_coordinate(z)?,
+        })
+    }
+
+    pub fn is_absolute(self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|c| matches!(c, Absolute(_)))
+    }
+
+    /// Resolves the coordinates into an absolute position, with relative
+    /// coordinates being offsets from `base`.
+    pub fn resolve(self, base: Vec3) -> Vec3 {
+        Vec3::new(
+            self.x.resolve(base.x),
+            self.y.resolve(base.y),
+            self.z.resolve(base.z),
+        )
+    }
+}
+impl From<Vec3> for Coordinates {
+    fn from(v: Vec3) -> Self {
+        Self {
+            x: Absolute(v.x),
+            y: Absolute(v.y),
+            z: Absolute(v.z),
+        }
+    }
+}
+
+/// Parses a single coordinate.
+///
+/// A coordinate is either a plain number (`12.5`), or an offset relative to
+/// some base position, prefixed with a tilde (`~`, `~-3`). Local coordinates
+/// (`^`), which depend on the direction being faced, are not supported.
+pub fn parse_coordinate(arg: &str) -> Result<AbsOrRel<f64>, CommandError> {
+    if arg.starts_with('^') {
+        return Err(CommandError::LocalCoordinate);
+    }
+    match arg.strip_prefix('~') {
+        Some("") => Ok(Relative(0.0)),
+        Some(offset) => offset.parse().map(Relative),
+        None => arg.parse().map(Absolute),
+    }
+    .map_err(|_| CommandError::InvalidCoordinate(arg.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_coordinate, Coordinates};
+    use crate::{
+        math::{
+            AbsOrRel::{Absolute, Relative},
+            Vec3,
+        },
+        server::command::CommandError,
+    };
+
+    #[test]
+    fn test_parse_coordinate() {
+        assert_eq!(parse_coordinate("69").unwrap(), Absolute(69.0));
+        assert_eq!(parse_coordinate("-4.5").unwrap(), Absolute(-4.5));
+        assert_eq!(parse_coordinate("~").unwrap(), Relative(0.0));
+        assert_eq!(parse_coordinate("~10").unwrap(), Relative(10.0));
+        assert_eq!(parse_coordinate("~-0.5").unwrap(), Relative(-0.5));
+
+        assert!(matches!(
+            parse_coordinate("^1"),
+            Err(CommandError::LocalCoordinate)
+        ));
+        assert!(parse_coordinate("").is_err());
+        assert!(parse_coordinate("~~").is_err());
+        assert!(parse_coordinate("north").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = Vec3::new(100.0, 64.0, -20.0);
+        let resolve = |x, y, z| Coordinates::parse(x, y, z).unwrap().resolve(base);
+
+        assert_eq!(resolve("1", "2", "3"), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(resolve("~", "~", "~"), base);
+        assert_eq!(resolve("~5", "~-4", "~0.5"), Vec3::new(105.0, 60.0, -19.5));
+        assert_eq!(resolve("0", "~1", "-8"), Vec3::new(0.0, 65.0, -8.0));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    data::Identifier,
+    server::{
+        effect::{effect_id, Effect, EffectFlags},
+        Server,
+    },
+};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const EFFECT: Command = Command {
+    name: "effect",
+    usage: "/effect give <player> <effect> [seconds] [amplifier] | /effect clear <player> [effect]",
+    description: "Puts a player under an effect, or takes effects off them",
+    permission: Permission::Operator,
+    run,
+};
+
+/// The most seconds an effect can be given for, as in vanilla.
+const MAX_SECONDS: u32 = 1_000_000;
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    match *args {
+        ["give", player, effect, ref rest @ ..] if rest.len() <= 2 => {
+            let (name, id) = parse_effect(effect)?;
+            let seconds = match rest.first() {
+                Some(seconds) => match seconds.parse::<u32>() {
+                    Ok(seconds) if (1..=MAX_SECONDS).contains(&seconds) => seconds,
+                    _ => return Err(CommandError::InvalidDuration(seconds.to_string())),
+                },
+                None => 30,
+            };
+            let amplifier = match rest.get(1) {
+                Some(amplifier) => amplifier
+                    .parse::<u8>()
+                    .map_err(|_| CommandError::InvalidAmplifier(amplifier.to_string()))?,
+                None => 0,
+            };
+            let effect = Effect {
+                id,
+                amplifier,
+                duration: seconds * 20,
+                flags: EffectFlags::SHOW_PARTICLES | EffectFlags::SHOW_ICON,
+            };
+            server.add_effect(player, effect)?;
+            Ok(format!(
+                "Gave {} to {} for {} seconds",
+                name, player, seconds
+            ))
+        }
+        ["clear", player] => {
+            let cleared = server.clear_effects(player, None)?;
+            Ok(format!("Took {} effects off {}", cleared, player))
+        }
+        ["clear", player, effect] => {
+            let (name, id) = parse_effect(effect)?;
+            server.clear_effects(player, Some(id))?;
+            Ok(format!("Took {} off {}", name, player))
+        }
+        _ => Err(CommandError::Usage(EFFECT.usage)),
+    }
+}
+
+fn parse_effect(effect: &str) -> Result<(Identifier, u8), CommandError> {
+    let effect = Identifier::parse_with_default_namespace(effect)
+        .map_err(|e| CommandError::InvalidIdentifier(effect.to_string(), e))?;
+    let id = effect_id(&effect).ok_or_else(|| CommandError::UnknownEffect(effect.clone()))?;
+    Ok((effect, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use crate::{
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_effect() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, _) = server.join_for_tests("op", Vec3::ZERO);
+        let (_, mut events) = server.join_for_tests("player", Vec3::ZERO);
+        while events.try_recv().is_ok() {}
+        let entity_id = server.online_player("player").unwrap().entity_id;
+        let next_packet =
+            |events: &mut mpsc::UnboundedReceiver<ClientEvent>| match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => packet,
+                event => panic!("expected a packet, got {:?}", event),
+            };
+
+        assert!(dispatch(&mut server, &op, "effect give player flight").is_err());
+        assert!(dispatch(&mut server, &op, "effect give player speed 0").is_err());
+        assert!(dispatch(&mut server, &op, "effect give player speed 1 256").is_err());
+
+        dispatch(&mut server, &op, "effect give player speed 1 2").unwrap();
+        let effect = server.online_player("player").unwrap().effects[0];
+        assert_eq!((effect.id, effect.amplifier, effect.duration), (1, 2, 20));
+        assert_eq!(
+            next_packet(&mut events),
+            packets::entity_effect(entity_id, &effect)
+        );
+
+        // it wears off after a second
+        for _ in 0..19 {
+            server.tick();
+        }
+        assert!(events.try_recv().is_err());
+        server.tick();
+        assert!(server.online_player("player").unwrap().effects.is_empty());
+        assert_eq!(
+            next_packet(&mut events),
+            packets::remove_entity_effect(entity_id, 1)
+        );
+
+        dispatch(&mut server, &op, "effect give player glowing").unwrap();
+        dispatch(&mut server, &op, "effect give player minecraft:luck").unwrap();
+        next_packet(&mut events);
+        next_packet(&mut events);
+        assert_eq!(
+            dispatch(&mut server, &op, "effect clear player").unwrap(),
+            "Took 2 effects off player"
+        );
+        assert_eq!(
+            next_packet(&mut events),
+            packets::remove_entity_effect(entity_id, 24)
+        );
+        assert_eq!(
+            next_packet(&mut events),
+            packets::remove_entity_effect(entity_id, 26)
+        );
+
+        // there is nothing to take off anymore
+        assert!(dispatch(&mut server, &op, "effect clear player luck").is_err());
+        assert!(events.try_recv().is_err());
+    }
+}
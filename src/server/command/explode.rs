@@ -0,0 +1,68 @@
+use crate::server::{world::MAX_EXPLOSION_RADIUS, Server};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const EXPLODE: Command = Command {
+    name: "explode",
+    usage: "/explode <0-16>",
+    description: "Makes an explosion of the given radius where you stand",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let radius = match args {
+        [radius] => radius
+            .parse()
+            .ok()
+            .filter(|r| (0.0..=MAX_EXPLOSION_RADIUS).contains(r))
+            .ok_or(CommandError::Usage(EXPLODE.usage))?,
+        _ => return Err(CommandError::Usage(EXPLODE.usage)),
+    };
+    let destroyed = server.explode(source.position()?, radius);
+    Ok(format!("Destroyed {} blocks", destroyed))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::{BlockPos, Vec3},
+        net::ClientEvent,
+        registry::block::AIR,
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_explode() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut events) = server.join_for_tests("op", Vec3::new(0.5, 64.5, 0.5));
+        for x in -40..=40 {
+            server.world.set_block(BlockPos::new(x, 64, 0), 1);
+        }
+
+        assert!(dispatch(&mut server, &op, "explode").is_err());
+        assert!(dispatch(&mut server, &op, "explode -1").is_err());
+        assert!(dispatch(&mut server, &op, "explode 17").is_err());
+        assert!(dispatch(&mut server, &op, "explode NaN").is_err());
+        assert!(events.try_recv().is_err());
+
+        assert_eq!(
+            dispatch(&mut server, &op, "explode 2").unwrap(),
+            "Destroyed 5 blocks"
+        );
+        assert_eq!(server.world.block(BlockPos::new(2, 64, 0)), AIR);
+        assert_eq!(server.world.block(BlockPos::new(3, 64, 0)), 1);
+        assert!(matches!(events.try_recv(), Ok(ClientEvent::Packet(_))));
+    }
+
+    #[test]
+    fn test_explode_radius_clamped() {
+        let mut server = Server::for_tests(&[]);
+        for x in -40..=40 {
+            server.world.set_block(BlockPos::new(x, 64, 0), 1);
+        }
+        // the offsets of the destroyed blocks have to fit in a byte
+        assert_eq!(server.explode(Vec3::new(0.5, 64.5, 0.5), 1000.0), 33);
+        assert_eq!(server.world.block(BlockPos::new(17, 64, 0)), 1);
+    }
+}
@@ -0,0 +1,107 @@
+use tracing::warn;
+
+use crate::{data::Identifier, net::packets, registry::item::max_stack_size, server::Server};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const GIVE: Command = Command {
+    name: "give",
+    usage: "/give <player> <item> [count]",
+    description: "Gives a stack of items to a player",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let (player, item, count) = match *args {
+        [player, item] => (player, item, "1"),
+        [player, item, count] => (player, item, count),
+        _ => return Err(CommandError::Usage(GIVE.usage)),
+    };
+    let item = Identifier::parse_with_default_namespace(item)
+        .map_err(|e| CommandError::InvalidIdentifier(item.to_string(), e))?;
+    let id = server
+        .item_registry
+        .id(&item)
+        .ok_or_else(|| CommandError::UnknownItem(item.clone()))?;
+
+    // at most one stack can be given at once
+    let max_stack_size = max_stack_size(&item);
+    let count = match count.parse::<u32>() {
+        Ok(count) if count > 0 => count.min(max_stack_size.into()) as u8,
+        _ => return Err(CommandError::InvalidCount(count.to_string())),
+    };
+
+    let target = server.online_player_mut(player)?;
+    let (changed, remaining) = target.inventory.add_item(id, count, max_stack_size);
+    if remaining == count {
+        return Err(CommandError::InventoryFull(player.to_string()));
+    }
+    for index in changed {
+        match packets::set_slot(index as i16, target.inventory.slot(index)) {
+            Ok(packet) => {
+                let _ = target.client.send_packet(packet);
+            }
+            Err(e) => warn!(?e, index, "Failed to serialize slot"),
+        }
+    }
+    Ok(format!("Gave {} {} to {}", count - remaining, item, player))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        net::{packets, ClientEvent},
+        registry::item::ItemRegistry,
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_give() {
+        let mut server = Server::for_tests(&["op"]);
+        server.item_registry = ItemRegistry::from_report(
+            r#"{
+                "minecraft:item": {
+                    "entries": {
+                        "minecraft:stone": { "protocol_id": 1 },
+                        "minecraft:diamond_sword": { "protocol_id": 598 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let (op, mut events) = server.join_for_tests("op", Vec3::ZERO);
+        let slot_count = |server: &Server, index| {
+            let inventory = &server.online_player("op").unwrap().inventory;
+            inventory.slot(index).as_ref().map(|s| (s.id(), s.count()))
+        };
+
+        dispatch(&mut server, &op, "give op stone 10").unwrap();
+        assert_eq!(slot_count(&server, 36), Some((1, 10)));
+        match events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => assert_eq!(
+                packet,
+                packets::set_slot(36, server.online_player("op").unwrap().inventory.slot(36))
+                    .unwrap()
+            ),
+            event => panic!("expected a Set Slot, got {:?}", event),
+        }
+
+        // counts are clamped to the stack size
+        dispatch(&mut server, &op, "give op minecraft:stone 100").unwrap();
+        assert_eq!(slot_count(&server, 36), Some((1, 64)));
+        assert_eq!(slot_count(&server, 37), Some((1, 10)));
+        dispatch(&mut server, &op, "give op diamond_sword 5").unwrap();
+        assert_eq!(slot_count(&server, 38), Some((598, 1)));
+        assert_eq!(slot_count(&server, 39), None);
+
+        assert!(dispatch(&mut server, &op, "give op stone 0").is_err());
+        assert!(dispatch(&mut server, &op, "give op dirt").is_err());
+        assert!(dispatch(&mut server, &op, "give nobody stone").is_err());
+    }
+}
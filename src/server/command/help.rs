@@ -0,0 +1,77 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission, COMMANDS};
+
+pub const HELP: Command = Command {
+    name: "help",
+    usage: "/help [command]",
+    description: "Lists the commands you can use, or explains one of them",
+    permission: Permission::Everyone,
+    run,
+};
+
+fn run(
+    _server: &mut Server,
+    source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    match *args {
+        [] => Ok(COMMANDS
+            .iter()
+            .filter(|c| source.permission >= c.permission)
+            .map(|c| c.usage)
+            .collect::<Vec<_>>()
+            .join("\n")),
+        [name] => {
+            let name = name.trim_start_matches('/');
+            let command = COMMANDS
+                .iter()
+                .find(|c| c.name == name)
+                .ok_or_else(|| CommandError::UnknownCommand(name.to_string()))?;
+            if source.permission < command.permission {
+                return Err(CommandError::NoPermission);
+            }
+            Ok(format!("{}\n{}", command.usage, command.description))
+        }
+        _ => Err(CommandError::Usage(HELP.usage)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_help() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, _) = server.join_for_tests("op", Vec3::ZERO);
+        let (player, _) = server.join_for_tests("player", Vec3::ZERO);
+
+        let help = dispatch(&mut server, &op, "help").unwrap();
+        assert!(
+            help.lines().any(|l| l == "/stop [seconds|cancel]"),
+            "{}",
+            help
+        );
+        assert!(help.lines().any(|l| l == "/help [command]"), "{}", help);
+
+        // players only see what they can run
+        let help = dispatch(&mut server, &player, "help").unwrap();
+        assert!(
+            help.lines().any(|l| l == "/msg <player> <message>"),
+            "{}",
+            help
+        );
+        assert!(!help.contains("/stop"), "{}", help);
+        assert!(dispatch(&mut server, &player, "help stop").is_err());
+
+        assert_eq!(
+            dispatch(&mut server, &player, "help /msg").unwrap(),
+            "/msg <player> <message>\nSends a private message to a player"
+        );
+        assert!(dispatch(&mut server, &player, "help nonexistent").is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const MAINTENANCE: Command = Command {
+    name: "maintenance",
+    usage: "/maintenance (on|off)",
+    description: "Turns maintenance mode on or off, in which only operators can join",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    server.maintenance = match args {
+        ["on"] => true,
+        ["off"] => false,
+        _ => return Err(CommandError::Usage(MAINTENANCE.usage)),
+    };
+    Ok(if server.maintenance {
+        "Only operators can join the server now".into()
+    } else {
+        "Everyone can join the server again".into()
+    })
+}
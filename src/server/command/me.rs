@@ -0,0 +1,62 @@
+use crate::{
+    chat::{Chat, ChatPosition},
+    server::Server,
+};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const ME: Command = Command {
+    name: "me",
+    usage: "/me <action>",
+    description: "Tells everyone about something you are doing",
+    permission: Permission::Everyone,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let sender = source.player()?;
+    if args.is_empty() {
+        return Err(CommandError::Usage(ME.usage));
+    }
+    let message = Chat::text(format!("* {} {}", sender.username, args.join(" ")));
+    server.broadcast_chat_message(&message, ChatPosition::Chat, sender.uuid);
+    // the sender sees the emote like everyone else
+    Ok(String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chat::{Chat, ChatPosition},
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_me() {
+        let mut server = Server::for_tests(&[]);
+        let (sender, mut sender_events) = server.join_for_tests("sender", Vec3::ZERO);
+        let (_, mut other_events) = server.join_for_tests("other", Vec3::ZERO);
+        while sender_events.try_recv().is_ok() {}
+
+        assert_eq!(
+            dispatch(&mut server, &sender, "me waves  hello").unwrap(),
+            ""
+        );
+        let expected = packets::chat_message(
+            &Chat::text("* sender waves hello"),
+            ChatPosition::Chat,
+            sender.uuid,
+        );
+        for events in [&mut sender_events, &mut other_events] {
+            match events.try_recv() {
+                Ok(ClientEvent::Prepared(packet)) => {
+                    assert_eq!(packet.frame(None).unwrap(), expected.to_frame())
+                }
+                event => panic!("expected a Chat Message, got {:?}", event),
+            }
+        }
+        assert!(dispatch(&mut server, &sender, "me").is_err());
+    }
+}
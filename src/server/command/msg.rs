@@ -0,0 +1,82 @@
+use crate::{
+    chat::{Chat, ChatColor, ChatPosition},
+    net::packets,
+    server::Server,
+};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const MSG: Command = Command {
+    name: "msg",
+    usage: "/msg <player> <message>",
+    description: "Sends a private message to a player",
+    permission: Permission::Everyone,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let sender = source.player()?;
+    let (target, message) = match args {
+        [target, message @ ..] if !message.is_empty() => (*target, message.join(" ")),
+        _ => return Err(CommandError::Usage(MSG.usage)),
+    };
+    let target = server.online_player(target)?;
+    let whisper = Chat::text(format!("{} whispers to you: {}", sender.username, message))
+        .color(ChatColor::Gray)
+        .italic();
+    if target.chat_mode.accepts(ChatPosition::System) {
+        // if this fails, the target is disconnecting anyway
+        let _ = target.client.send_packet(packets::chat_message(
+            &whisper,
+            ChatPosition::System,
+            sender.uuid,
+        ));
+    }
+    Ok(format!(
+        "You whisper to {}: {}",
+        target.player.username, message
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        net::ClientEvent,
+        server::{
+            command::{dispatch, CommandError},
+            Server,
+        },
+    };
+
+    #[test]
+    fn test_msg() {
+        let mut server = Server::for_tests(&[]);
+        let (sender, mut sender_events) = server.join_for_tests("sender", Vec3::ZERO);
+        let (_, mut target_events) = server.join_for_tests("target", Vec3::ZERO);
+        let (_, mut other_events) = server.join_for_tests("other", Vec3::ZERO);
+        while sender_events.try_recv().is_ok() {}
+        while target_events.try_recv().is_ok() {}
+
+        // the sender gets the echo as feedback, and only the target is told
+        assert_eq!(
+            dispatch(&mut server, &sender, "msg target psst, over here").unwrap(),
+            "You whisper to target: psst, over here"
+        );
+        match target_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                let packet = String::from_utf8_lossy(&packet.to_frame()).into_owned();
+                assert!(packet.contains("sender whispers to you: psst, over here"));
+            }
+            event => panic!("expected a Chat Message, got {:?}", event),
+        }
+        assert!(sender_events.try_recv().is_err());
+        assert!(other_events.try_recv().is_err());
+
+        assert!(matches!(
+            dispatch(&mut server, &sender, "msg nobody hi"),
+            Err(CommandError::PlayerNotFound(name)) if name == "nobody"
+        ));
+        assert!(dispatch(&mut server, &sender, "msg target").is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const NPC: Command = Command {
+    name: "npc",
+    usage: "/npc (spawn|remove) <name>",
+    description: "Spawns or removes a fake player, for testing",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    if !server.config.debug_commands {
+        return Err(CommandError::DebugCommandsDisabled);
+    }
+    match *args {
+        ["spawn", name] => {
+            let position = source.position()?;
+            let entity_id = server.spawn_npc(name, position)?;
+            Ok(format!("Spawned {} with entity ID {}", name, entity_id))
+        }
+        ["remove", name] => {
+            server.remove_npc(name)?;
+            Ok(format!("Removed {}", name))
+        }
+        _ => Err(CommandError::Usage(NPC.usage)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_npc() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut events) = server.join_for_tests("op", Vec3::new(1.0, 64.0, 2.0));
+        assert!(dispatch(&mut server, &op, "npc spawn Steve").is_err());
+
+        Arc::get_mut(&mut server.config).unwrap().debug_commands = true;
+        assert!(dispatch(&mut server, &op, "npc spawn not-a-name").is_err());
+        assert!(dispatch(&mut server, &op, "npc spawn OP").is_err());
+        dispatch(&mut server, &op, "npc spawn Steve").unwrap();
+
+        // the NPC is in the game, next to the op...
+        let npc = server.online_player("Steve").unwrap();
+        assert_eq!(npc.position, Some(Vec3::new(1.0, 64.0, 2.0)));
+        assert!(server.player_list().iter().any(|p| p.username == "Steve"));
+        assert_eq!(server.player_count(), 2);
+        // ...who sees it in the tab list, and spawned in front of them
+        let expected = [
+            packets::player_info_add([&npc.player].into_iter()),
+            npc.spawn_packet(Vec3::new(1.0, 64.0, 2.0)),
+        ];
+        for expected in expected {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, expected),
+                event => panic!("expected {:?}, got {:?}", expected, event),
+            }
+        }
+
+        // only NPCs can be removed
+        assert!(dispatch(&mut server, &op, "npc remove op").is_err());
+        dispatch(&mut server, &op, "npc remove Steve").unwrap();
+        assert!(server.online_player("Steve").is_err());
+        assert_eq!(server.player_count(), 1);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const RELOAD: Command = Command {
+    name: "reload",
+    usage: "/reload (favicon|registries)",
+    description: "Reads the favicon or the registry reports again",
+    permission: Permission::Operator,
+    run,
+};
+
+/// Starts reloading, which happens in the background, as reading the files
+/// would hold up the server. The sender is told how it went once it's done.
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    match args {
+        ["favicon"] => {
+            server.reload_favicon(source.kind.clone());
+            Ok("Reloading the favicon".into())
+        }
+        ["registries"] => {
+            server.reload_registries(source.kind.clone());
+            Ok("Reloading the registries".into())
+        }
+        _ => Err(CommandError::Usage(RELOAD.usage)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc};
+
+    use tokio::sync::mpsc;
+
+    use crate::{
+        data::Identifier,
+        math::Vec3,
+        net::ClientEvent,
+        server::{
+            command::{dispatch, CommandError},
+            Player, Server,
+        },
+    };
+
+    const BLOCKS_REPORT: &str = r#"{
+        "minecraft:air": { "states": [{ "id": 0, "default": true }] },
+        "minecraft:stone": { "states": [{ "id": 1, "default": true }] }
+    }"#;
+    const REGISTRIES_REPORT: &str = r#"{
+        "minecraft:item": {
+            "entries": { "minecraft:stone": { "protocol_id": 1 } }
+        },
+        "minecraft:entity_type": {
+            "entries": { "minecraft:pig": { "protocol_id": 64 } }
+        }
+    }"#;
+
+    fn id(s: &str) -> Identifier {
+        Identifier::try_from(s).unwrap()
+    }
+
+    /// Points the config at files in the temporary directory, named after the
+    /// test.
+    fn use_temp_files(server: &mut Server, test: &str) {
+        let config = Arc::get_mut(&mut server.config).unwrap();
+        let dir = std::env::temp_dir();
+        config.favicon_path = dir.join(format!("hieronymus-test-{}-favicon.png", test));
+        config.blocks_report_path = dir.join(format!("hieronymus-test-{}-blocks.json", test));
+        config.registries_report_path =
+            dir.join(format!("hieronymus-test-{}-registries.json", test));
+        fs::write(&config.blocks_report_path, BLOCKS_REPORT).unwrap();
+        fs::write(&config.registries_report_path, REGISTRIES_REPORT).unwrap();
+    }
+
+    fn remove_temp_files(server: &Server) {
+        let config = &server.config;
+        for path in [
+            &config.favicon_path,
+            &config.blocks_report_path,
+            &config.registries_report_path,
+        ] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Runs a reload command, waits for it to finish loading and swaps in
+    /// what it loaded, returning what the sender was told.
+    async fn reload(
+        server: &mut Server,
+        op: &Player,
+        events: &mut mpsc::UnboundedReceiver<ClientEvent>,
+        what: &str,
+    ) -> String {
+        dispatch(server, op, &format!("reload {}", what)).unwrap();
+        let notification = server.loopback_rx.recv().await.unwrap();
+        server.handle_notification(notification);
+        match events.try_recv() {
+            Ok(ClientEvent::SystemMessage(message)) => message,
+            event => panic!("expected a system message, got {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_favicon() {
+        let mut server = Server::for_tests(&["op"]);
+        use_temp_files(&mut server, "reload-favicon");
+        let (op, mut events) = server.join_for_tests("op", Vec3::ZERO);
+
+        // the favicon is only replaced if there is one to replace it with
+        let _ = fs::remove_file(&server.config.favicon_path);
+        let message = reload(&mut server, &op, &mut events, "favicon").await;
+        assert!(message.starts_with("Could not reload the favicon"));
+        assert_eq!(server.favicon, None);
+
+        fs::write(&server.config.favicon_path, b"\x89PNG").unwrap();
+        assert_eq!(
+            reload(&mut server, &op, &mut events, "favicon").await,
+            "Reloaded the favicon"
+        );
+        assert_eq!(
+            server.favicon.as_deref(),
+            Some("data:image/png;base64,iVBORw==")
+        );
+        // the registries are left alone
+        assert_eq!(server.item_registry.id(&id("minecraft:stone")), None);
+        assert_eq!(server.entity_type_registry.id(&id("minecraft:pig")), None);
+        remove_temp_files(&server);
+    }
+
+    #[tokio::test]
+    async fn test_reload_registries() {
+        let mut server = Server::for_tests(&["op"]);
+        use_temp_files(&mut server, "reload-registries");
+        fs::write(&server.config.favicon_path, b"\x89PNG").unwrap();
+        let (op, mut events) = server.join_for_tests("op", Vec3::ZERO);
+
+        assert_eq!(
+            reload(&mut server, &op, &mut events, "registries").await,
+            "Reloaded the registries"
+        );
+        assert_eq!(server.item_registry.id(&id("minecraft:stone")), Some(1));
+        assert_eq!(
+            server.entity_type_registry.id(&id("minecraft:pig")),
+            Some(64)
+        );
+        assert_eq!(
+            server.block_registry.default_state(&id("minecraft:stone")),
+            Some(1)
+        );
+        // the favicon is left alone
+        assert_eq!(server.favicon, None);
+
+        // a broken report leaves every registry as it was
+        fs::write(&server.config.registries_report_path, "{").unwrap();
+        let message = reload(&mut server, &op, &mut events, "registries").await;
+        assert!(message.starts_with("Could not reload the registries"));
+        assert_eq!(server.item_registry.id(&id("minecraft:stone")), Some(1));
+
+        assert!(matches!(
+            dispatch(&mut server, &op, "reload"),
+            Err(CommandError::Usage(_))
+        ));
+        remove_temp_files(&server);
+    }
+}
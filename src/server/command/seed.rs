@@ -0,0 +1,59 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+/// Everyone may run this command, but only operators can see the seed unless
+/// it is made public in the config.
+pub const SEED: Command = Command {
+    name: "seed",
+    usage: "/seed",
+    description: "Shows the seed of the world",
+    permission: Permission::Everyone,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    if !args.is_empty() {
+        return Err(CommandError::Usage(SEED.usage));
+    }
+    if !server.config.public_seed && !source.is_op() {
+        return Err(CommandError::NoPermission);
+    }
+    Ok(format!("Seed: {}", server.seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        math::Vec3,
+        server::{
+            command::{dispatch, CommandError},
+            Server,
+        },
+    };
+
+    #[test]
+    fn test_seed() {
+        let mut server = Server::for_tests(&["op"]);
+        server.seed = -4172144997902289642;
+        let (op, _) = server.join_for_tests("op", Vec3::ZERO);
+        let (other, _) = server.join_for_tests("other", Vec3::ZERO);
+
+        assert_eq!(
+            dispatch(&mut server, &op, "seed").unwrap(),
+            "Seed: -4172144997902289642"
+        );
+        assert!(matches!(
+            dispatch(&mut server, &other, "seed"),
+            Err(CommandError::NoPermission)
+        ));
+
+        Arc::get_mut(&mut server.config).unwrap().public_seed = true;
+        assert_eq!(
+            dispatch(&mut server, &other, "seed").unwrap(),
+            "Seed: -4172144997902289642"
+        );
+    }
+}
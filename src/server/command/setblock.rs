@@ -0,0 +1,84 @@
+use crate::server::Server;
+
+use super::{
+    block::BlockState, coordinate::Coordinates, Command, CommandError, CommandSource, Permission,
+};
+
+pub const SETBLOCK: Command = Command {
+    name: "setblock",
+    usage: "/setblock <x> <y> <z> <block>[<property>=<value>,...]",
+    description: "Changes a block",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let (x, y, z, block_state) = match *args {
+        [x, y, z, block_state] => (x, y, z, block_state),
+        _ => return Err(CommandError::Usage(SETBLOCK.usage)),
+    };
+    let BlockState { block, properties } = BlockState::parse(block_state)?;
+    let registry = &server.block_registry;
+    if registry.default_state(&block).is_none() {
+        return Err(CommandError::UnknownBlock(block));
+    }
+    let state = registry
+        .state(&block, &properties)
+        .ok_or_else(|| CommandError::InvalidBlockState(block_state.to_string()))?;
+
+    // relative coordinates are relative to the sender
+    let pos = source.resolve(Coordinates::parse(x, y, z)?)?.block_pos();
+
+    if !server.world.contains(pos) {
+        return Err(CommandError::OutOfWorld);
+    }
+    if server.world.block(pos) == state {
+        return Err(CommandError::BlockUnchanged);
+    }
+
+    server.set_block(pos, state);
+    Ok(format!(
+        "Changed the block at {} {} {} to {}",
+        pos.x, pos.y, pos.z, block_state
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::{BlockPos, Vec3},
+        net::{packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_setblock() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut op_events) = server.join_for_tests("op", Vec3::new(0.0, 64.0, 0.0));
+        let (far, mut far_events) = server.join_for_tests("far", Vec3::new(5000.0, 64.0, 0.0));
+        // the op is told about the other player joining
+        while op_events.try_recv().is_ok() {}
+
+        dispatch(&mut server, &op, "setblock ~1 ~-1 2 minecraft:stone").unwrap();
+        let pos = BlockPos::new(1, 63, 2);
+        assert_eq!(server.world.block(pos), 1);
+
+        match op_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, packets::block_change(pos, 1)),
+            event => panic!("expected a Block Change, got {:?}", event),
+        }
+        // too far away to see it
+        assert!(far_events.try_recv().is_err());
+
+        // the namespace defaults to `minecraft`
+        dispatch(&mut server, &op, "setblock 1 63 2 dirt").unwrap();
+        assert_eq!(server.world.block(pos), 10);
+
+        assert!(dispatch(&mut server, &op, "setblock 1 63 2 dirt").is_err());
+        assert!(dispatch(&mut server, &op, "setblock 1 63 2 nonexistent").is_err());
+        assert!(dispatch(&mut server, &op, "setblock 1 63 2 dirt[snowy=true]").is_err());
+        assert!(dispatch(&mut server, &op, "setblock 1 -1 2 stone").is_err());
+        assert!(dispatch(&mut server, &far, "setblock 1 63 2 air").is_err());
+        assert_eq!(server.world.block(pos), 10);
+    }
+}
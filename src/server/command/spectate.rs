@@ -0,0 +1,75 @@
+use crate::{net::packets, server::Server};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const SPECTATE: Command = Command {
+    name: "spectate",
+    usage: "/spectate [player]",
+    description: "Views the world from the eyes of another player, in spectator mode",
+    permission: Permission::Operator,
+    run,
+};
+
+/// Views the world from the eyes of another player, or from one's own again
+/// without a target. This only works in spectator mode.
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let sender = source.player()?;
+    let spectator = server.online_player(&sender.username)?;
+    let (target, feedback) = match *args {
+        [] => (
+            spectator.entity_id,
+            "You are no longer spectating anyone".to_string(),
+        ),
+        [target] => {
+            let target = server.online_player(target)?;
+            // the client ignores entities it doesn't know about
+            if target.player.uuid != sender.uuid
+                && !server
+                    .entity_tracker
+                    .is_tracking(sender.uuid, target.entity_id)
+            {
+                return Err(CommandError::TooFarAway(target.player.username.clone()));
+            }
+            (
+                target.entity_id,
+                format!("You are now spectating {}", target.player.username),
+            )
+        }
+        _ => return Err(CommandError::Usage(SPECTATE.usage)),
+    };
+    let _ = spectator.client.send_packet(packets::camera(target));
+    Ok(feedback)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_spectate() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut events) = server.join_for_tests("op", Vec3::ZERO);
+        server.join_for_tests("near", Vec3::new(10.0, 0.0, 0.0));
+        server.join_for_tests("far", Vec3::new(5000.0, 0.0, 0.0));
+        while events.try_recv().is_ok() {}
+
+        assert!(dispatch(&mut server, &op, "spectate nobody").is_err());
+        assert!(dispatch(&mut server, &op, "spectate far").is_err());
+        assert!(events.try_recv().is_err());
+
+        let near = server.online_player("near").unwrap().entity_id;
+        let own = server.online_player("op").unwrap().entity_id;
+        dispatch(&mut server, &op, "spectate near").unwrap();
+        dispatch(&mut server, &op, "spectate").unwrap();
+        for entity_id in [near, own] {
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, packets::camera(entity_id)),
+                event => panic!("expected Camera, got {:?}", event),
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const STOP: Command = Command {
+    name: "stop",
+    usage: "/stop [seconds|cancel]",
+    description: "Stops the server, after a countdown if a number of seconds is given",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let seconds = match args {
+        [] => 0,
+        ["cancel"] => {
+            return if server.cancel_stop() {
+                // everyone was told already
+                Ok(String::new())
+            } else {
+                Err(CommandError::NotStopping)
+            };
+        }
+        [seconds] => seconds
+            .parse()
+            .map_err(|_| CommandError::Usage(STOP.usage))?,
+        _ => return Err(CommandError::Usage(STOP.usage)),
+    };
+    server.stop_in(seconds);
+    Ok(if seconds == 0 {
+        "Stopping the server".into()
+    } else {
+        // everyone was told already
+        String::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        math::Vec3,
+        net::ClientEvent,
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_stop() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut events) = server.join_for_tests("op", Vec3::ZERO);
+        let mut received = || {
+            let mut received = 0;
+            while let Ok(event) = events.try_recv() {
+                assert!(matches!(event, ClientEvent::Prepared(_)));
+                received += 1;
+            }
+            received
+        };
+
+        assert!(dispatch(&mut server, &op, "stop cancel").is_err());
+        assert!(dispatch(&mut server, &op, "stop soon").is_err());
+
+        dispatch(&mut server, &op, "stop 10").unwrap();
+        assert_eq!(received(), 1);
+        let start = Instant::now();
+        assert!(!server.count_down(start + Duration::from_secs(5)));
+        assert_eq!(received(), 1);
+
+        // cancelling tells everyone, and there is nothing left to count down
+        dispatch(&mut server, &op, "stop cancel").unwrap();
+        assert_eq!(received(), 1);
+        assert!(!server.count_down(start + Duration::from_secs(60)));
+        assert_eq!(received(), 0);
+
+        dispatch(&mut server, &op, "stop 1").unwrap();
+        assert_eq!(received(), 1);
+        assert!(server.count_down(start + Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{data::Identifier, server::Server};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const SUMMON: Command = Command {
+    name: "summon",
+    usage: "/summon <entity>",
+    description: "Spawns an entity where you stand",
+    permission: Permission::Operator,
+    run,
+};
+
+/// Spawns an entity where the sender stands. It won't move, as the server
+/// doesn't simulate entities.
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let entity_type = match *args {
+        [entity_type] => entity_type,
+        _ => return Err(CommandError::Usage(SUMMON.usage)),
+    };
+    let entity_type = Identifier::parse_with_default_namespace(entity_type)
+        .map_err(|e| CommandError::InvalidIdentifier(entity_type.to_string(), e))?;
+    let entity_id = server.summon(source.position()?, &entity_type)?;
+    Ok(format!(
+        "Summoned {} with entity ID {}",
+        entity_type, entity_id
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        net::ClientEvent,
+        registry::entity::{EntityTypeRegistry, SpawnKind},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_summon() {
+        let mut server = Server::for_tests(&["op"]);
+        server.entity_type_registry = EntityTypeRegistry::from_report(
+            r#"{
+                "minecraft:entity_type": {
+                    "entries": {
+                        "minecraft:boat": { "protocol_id": 7 },
+                        "minecraft:painting": { "protocol_id": 60 },
+                        "minecraft:zombie": { "protocol_id": 107 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let (op, mut events) = server.join_for_tests("op", Vec3::new(1.0, 64.0, 2.0));
+        let (_, mut far_events) = server.join_for_tests("far", Vec3::new(5000.0, 64.0, 0.0));
+        while events.try_recv().is_ok() {}
+        while far_events.try_recv().is_ok() {}
+
+        assert!(dispatch(&mut server, &op, "summon").is_err());
+        assert!(dispatch(&mut server, &op, "summon creeper").is_err());
+        assert!(dispatch(&mut server, &op, "summon painting").is_err());
+        assert!(server.entities.is_empty());
+
+        dispatch(&mut server, &op, "summon zombie").unwrap();
+        dispatch(&mut server, &op, "summon minecraft:boat").unwrap();
+        for (entity_type, kind) in [(107, SpawnKind::Living), (7, SpawnKind::Object)] {
+            let entity = server
+                .entities
+                .values()
+                .find(|e| e.entity_type == entity_type)
+                .unwrap();
+            assert_eq!(entity.kind, kind);
+            assert_eq!(entity.position, Vec3::new(1.0, 64.0, 2.0));
+            match events.try_recv() {
+                Ok(ClientEvent::Packet(packet)) => assert_eq!(packet, entity.spawn_packet()),
+                event => panic!("expected a spawn packet, got {:?}", event),
+            }
+        }
+        // only players in range see them
+        assert!(far_events.try_recv().is_err());
+
+        // and players that come close later on too
+        server.player_moved(
+            server.online_player("far").unwrap().player.uuid,
+            Vec3::new(1.0, 64.0, 0.0),
+        );
+        let mut spawned = 0;
+        while let Ok(ClientEvent::Packet(packet)) = far_events.try_recv() {
+            if server.entities.values().any(|e| e.spawn_packet() == packet) {
+                spawned += 1;
+            }
+        }
+        assert_eq!(spawned, 2);
+    }
+}
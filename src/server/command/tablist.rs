@@ -0,0 +1,82 @@
+use crate::{chat::Chat, server::Server};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const TABLIST: Command = Command {
+    name: "tablist",
+    usage: "/tablist (header|footer) [text] | /tablist clear",
+    description: "Changes the header or footer of the tab list",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let (header, footer) = (
+        server.tab_list_header.clone(),
+        server.tab_list_footer.clone(),
+    );
+    match args {
+        ["header", text @ ..] => {
+            server.set_tab_list(Chat::text(text.join(" ")), footer);
+            Ok("Set the tab list header".into())
+        }
+        ["footer", text @ ..] => {
+            server.set_tab_list(header, Chat::text(text.join(" ")));
+            Ok("Set the tab list footer".into())
+        }
+        ["clear"] => {
+            server.set_tab_list(Chat::default(), Chat::default());
+            Ok("Cleared the tab list header and footer".into())
+        }
+        _ => Err(CommandError::Usage(TABLIST.usage)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chat::Chat,
+        math::Vec3,
+        net::{compression::PreparedPacket, packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_tablist() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut op_events) = server.join_for_tests("op", Vec3::ZERO);
+        let (_, mut other_events) = server.join_for_tests("other", Vec3::new(100.0, 0.0, 0.0));
+        while op_events.try_recv().is_ok() {}
+
+        assert!(dispatch(&mut server, &op, "tablist sidebar").is_err());
+        assert!(op_events.try_recv().is_err());
+
+        let mut expect = |header: &str, footer: &str| {
+            let expected = PreparedPacket::new(packets::tab_list(
+                &Chat::text(header.to_string()),
+                &Chat::text(footer.to_string()),
+            ));
+            for events in [&mut op_events, &mut other_events] {
+                match events.try_recv() {
+                    Ok(ClientEvent::Prepared(packet)) => assert_eq!(packet.data(), expected.data()),
+                    event => panic!("expected Player List Header And Footer, got {:?}", event),
+                }
+            }
+        };
+        dispatch(&mut server, &op, "tablist header Welcome to the server").unwrap();
+        expect("Welcome to the server", "");
+        dispatch(&mut server, &op, "tablist footer Have fun").unwrap();
+        expect("Welcome to the server", "Have fun");
+        assert_eq!(
+            dispatch(&mut server, &op, "tablist clear").unwrap(),
+            "Cleared the tab list header and footer"
+        );
+        expect("", "");
+        assert_eq!(server.tab_list_header, Chat::default());
+        assert_eq!(server.tab_list_footer, Chat::default());
+    }
+}
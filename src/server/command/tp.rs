@@ -0,0 +1,120 @@
+use crate::server::Server;
+
+use super::{coordinate::Coordinates, Command, CommandError, CommandSource, Permission};
+
+pub const TP: Command = Command {
+    name: "tp",
+    usage: "/tp [player] <x> <y> <z> | /tp <player> <target>",
+    description: "Teleports a player to coordinates or to another player",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    match *args {
+        // relative to where the sender is
+        [x, y, z] => {
+            let player = source.player()?.username.clone();
+            let position = source.resolve(Coordinates::parse(x, y, z)?)?;
+            teleport(server, &player, position.into())?;
+            Ok(format!(
+                "Teleported {} to {} {} {}",
+                player, position.x, position.y, position.z
+            ))
+        }
+        // relative to where the player being teleported is
+        [player, x, y, z] => {
+            teleport(server, player, Coordinates::parse(x, y, z)?)?;
+            Ok(format!("Teleported {} to {} {} {}", player, x, y, z))
+        }
+        [player, target] => {
+            let position = server
+                .online_player(target)?
+                .position
+                .ok_or_else(|| CommandError::PositionUnknown(target.to_string()))?;
+            teleport(server, player, position.into())?;
+            Ok(format!("Teleported {} to {}", player, target))
+        }
+        _ => Err(CommandError::Usage(TP.usage)),
+    }
+}
+
+fn teleport(server: &Server, player: &str, to: Coordinates) -> Result<(), CommandError> {
+    let player = server.online_player(player)?;
+    // if this fails, the player is disconnecting anyway
+    let _ = player.client.teleport(to.x, to.y, to.z);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::{AbsOrRel, Vec3},
+        net::{packets, ClientEvent},
+        server::{
+            command::{dispatch, CommandError},
+            Server,
+        },
+    };
+
+    #[test]
+    fn test_tp() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut op_events) = server.join_for_tests("op", Vec3::ZERO);
+        let (player, mut events) = server.join_for_tests("player", Vec3::new(5.0, 64.0, 5.0));
+        while op_events.try_recv().is_ok() {}
+
+        assert_eq!(
+            dispatch(&mut server, &op, "tp player 10 ~ -3").unwrap(),
+            "Teleported player to 10 ~ -3"
+        );
+        match events.try_recv() {
+            Ok(ClientEvent::Teleport { x, y, z }) => assert_eq!(
+                (x, y, z),
+                (
+                    AbsOrRel::Absolute(10.0),
+                    AbsOrRel::Relative(0.0),
+                    AbsOrRel::Absolute(-3.0)
+                )
+            ),
+            event => panic!("expected a teleport, got {:?}", event),
+        }
+        // nobody else moves
+        assert!(op_events.try_recv().is_err());
+
+        // once the client moved there, the op sees it move
+        let position = Vec3::new(10.0, 64.0, -3.0);
+        server.player_moved(player.uuid, position);
+        let entity_id = server.online_player("player").unwrap().entity_id;
+        match op_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::entity_teleport(entity_id, position, false))
+            }
+            event => panic!("expected Entity Teleport, got {:?}", event),
+        }
+
+        // to another player
+        dispatch(&mut server, &op, "tp op player").unwrap();
+        match op_events.try_recv() {
+            Ok(ClientEvent::Teleport { x, y, z }) => assert_eq!(
+                (x, y, z),
+                (
+                    AbsOrRel::Absolute(10.0),
+                    AbsOrRel::Absolute(64.0),
+                    AbsOrRel::Absolute(-3.0)
+                )
+            ),
+            event => panic!("expected a teleport, got {:?}", event),
+        }
+
+        assert!(matches!(
+            dispatch(&mut server, &op, "tp nobody 0 64 0"),
+            Err(CommandError::PlayerNotFound(name)) if name == "nobody"
+        ));
+        assert!(matches!(
+            dispatch(&mut server, &op, "tp player nobody"),
+            Err(CommandError::PlayerNotFound(_))
+        ));
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const UPTIME: Command = Command {
+    name: "uptime",
+    usage: "/uptime",
+    description: "Shows how long the server has been running",
+    permission: Permission::Everyone,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    if !args.is_empty() {
+        return Err(CommandError::Usage(UPTIME.usage));
+    }
+    Ok(format!(
+        "The server has been up for {}",
+        format_uptime(server.uptime())
+    ))
+}
+
+/// Formats an uptime like `1h 2m 5s`, leaving out the fractions of a second.
+pub fn format_uptime(uptime: Duration) -> String {
+    humantime::format_duration(Duration::from_secs(uptime.as_secs())).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        math::Vec3,
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_uptime() {
+        let mut server = Server::for_tests(&[]);
+        let (player, _) = server.join_for_tests("player", Vec3::ZERO);
+        let before = server.uptime();
+        assert!(before < Duration::from_secs(60));
+
+        // as if the server had been started an hour ago
+        server.started = Instant::now() - Duration::from_secs(3725);
+        assert!(server.uptime() > before);
+        assert_eq!(server.stats().uptime_secs, 3725);
+        assert_eq!(
+            dispatch(&mut server, &player, "uptime").unwrap(),
+            "The server has been up for 1h 2m 5s"
+        );
+    }
+}
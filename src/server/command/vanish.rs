@@ -0,0 +1,70 @@
+use crate::server::Server;
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const VANISH: Command = Command {
+    name: "vanish",
+    usage: "/vanish",
+    description: "Hides you from the tab list of other players, or shows you again",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(server: &mut Server, source: &CommandSource, args: &[&str]) -> Result<String, CommandError> {
+    let sender = source.player()?;
+    if !args.is_empty() {
+        return Err(CommandError::Usage(VANISH.usage));
+    }
+    let vanished = !server.online_player(&sender.username)?.vanished;
+    server.set_vanished(&sender.username, vanished)?;
+    Ok(if vanished {
+        "You are now hidden from other players".into()
+    } else {
+        "You are now visible to other players".into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        net::{packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_vanish() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, _op_events) = server.join_for_tests("op", Vec3::ZERO);
+        let (other, mut other_events) = server.join_for_tests("other", Vec3::ZERO);
+
+        dispatch(&mut server, &op, "vanish").unwrap();
+        match other_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::player_info_remove(&[op.uuid]))
+            }
+            event => panic!("expected a Player Info, got {:?}", event),
+        }
+
+        // the vanished player is left out of everyone's roster but their own
+        let names = |server: &Server, viewer| {
+            let players = server.visible_players(viewer);
+            players
+                .iter()
+                .map(|p| p.username.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&server, other.uuid), ["other"]);
+        assert_eq!(names(&server, op.uuid), ["op", "other"]);
+        assert!(dispatch(&mut server, &other, "vanish").is_err());
+
+        dispatch(&mut server, &op, "vanish").unwrap();
+        match other_events.try_recv() {
+            Ok(ClientEvent::Packet(packet)) => {
+                assert_eq!(packet, packets::player_info_add([&op].into_iter()))
+            }
+            event => panic!("expected a Player Info, got {:?}", event),
+        }
+        assert_eq!(names(&server, other.uuid), ["op", "other"]);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::server::{build_info, Server, Version};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const VERSION: Command = Command {
+    name: "version",
+    usage: "/version",
+    description: "Shows the version of the server",
+    permission: Permission::Everyone,
+    run,
+};
+
+fn run(
+    _server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    if !args.is_empty() {
+        return Err(CommandError::Usage(VERSION.usage));
+    }
+    Ok(format!(
+        "This server runs {}, for Minecraft {} (protocol {})",
+        build_info(),
+        Version::CURRENT.name,
+        Version::CURRENT.protocol_version
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_version() {
+        let mut server = Server::for_tests(&[]);
+        let (player, _) = server.join_for_tests("player", Vec3::ZERO);
+        let version = dispatch(&mut server, &player, "version").unwrap();
+        assert!(version.contains("1.17.1 (protocol 756)"), "{}", version);
+        assert!(version.contains(env!("CARGO_PKG_VERSION")), "{}", version);
+    }
+}
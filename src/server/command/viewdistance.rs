@@ -0,0 +1,73 @@
+use crate::server::{
+    tracker::{MAX_VIEW_DISTANCE, MIN_VIEW_DISTANCE},
+    Server,
+};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const VIEWDISTANCE: Command = Command {
+    name: "viewdistance",
+    usage: "/viewdistance <2-32>",
+    description: "Changes the view distance of the server",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let view_distance = match args {
+        [distance] => distance
+            .parse()
+            .ok()
+            .filter(|d| (MIN_VIEW_DISTANCE..=MAX_VIEW_DISTANCE).contains(d))
+            .ok_or(CommandError::Usage(VIEWDISTANCE.usage))?,
+        _ => return Err(CommandError::Usage(VIEWDISTANCE.usage)),
+    };
+    server.set_view_distance(view_distance);
+    Ok(format!("Set the view distance to {} chunks", view_distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        math::Vec3,
+        net::{compression::PreparedPacket, packets, ClientEvent},
+        server::{command::dispatch, Server},
+    };
+
+    #[test]
+    fn test_viewdistance() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut op_events) = server.join_for_tests("op", Vec3::ZERO);
+        let (_, mut other_events) = server.join_for_tests("other", Vec3::new(100.0, 0.0, 0.0));
+        while op_events.try_recv().is_ok() {}
+
+        assert!(dispatch(&mut server, &op, "viewdistance 1").is_err());
+        assert!(dispatch(&mut server, &op, "viewdistance 33").is_err());
+
+        // the same distance changes nothing
+        let distance = server.view_distance;
+        dispatch(&mut server, &op, &format!("viewdistance {}", distance)).unwrap();
+        assert!(other_events.try_recv().is_err());
+
+        dispatch(&mut server, &op, "viewdistance 4").unwrap();
+        for events in [&mut op_events, &mut other_events] {
+            match events.try_recv() {
+                Ok(ClientEvent::Prepared(packet)) => {
+                    let expected = PreparedPacket::new(packets::update_view_distance(4));
+                    assert_eq!(packet.data(), expected.data())
+                }
+                event => panic!("expected Update View Distance, got {:?}", event),
+            }
+        }
+        // the players are now out of each other's range
+        assert!(matches!(
+            other_events.try_recv(),
+            Ok(ClientEvent::Packet(_))
+        ));
+        assert_eq!(server.online_player("other").unwrap().view_distance, 4);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::server::{world_border::MAX_RADIUS, Server};
+
+use super::{Command, CommandError, CommandSource, Permission};
+
+pub const WORLDBORDER: Command = Command {
+    name: "worldborder",
+    usage: "/worldborder [center <x> <z> | radius <radius> | warning <distance>]",
+    description: "Shows or changes the world border",
+    permission: Permission::Operator,
+    run,
+};
+
+fn run(
+    server: &mut Server,
+    _source: &CommandSource,
+    args: &[&str],
+) -> Result<String, CommandError> {
+    let usage = || CommandError::Usage(WORLDBORDER.usage);
+    let mut border = server.world_border;
+    let feedback = match *args {
+        [] => {
+            return Ok(format!(
+                "The world border is centered on {} {}, with a radius of {} blocks",
+                border.center_x, border.center_z, border.radius
+            ))
+        }
+        ["center", x, z] => {
+            let parse = |c: &str| c.parse().ok().filter(|c: &f64| c.abs() <= MAX_RADIUS);
+            border.center_x = parse(x).ok_or_else(usage)?;
+            border.center_z = parse(z).ok_or_else(usage)?;
+            format!(
+                "Set the center of the world border to {} {}",
+                border.center_x, border.center_z
+            )
+        }
+        ["radius", radius] => {
+            border.radius = radius
+                .parse()
+                .ok()
+                .filter(|r| (1.0..=MAX_RADIUS).contains(r))
+                .ok_or_else(usage)?;
+            format!(
+                "Set the radius of the world border to {} blocks",
+                border.radius
+            )
+        }
+        ["warning", distance] => {
+            border.warning_distance = distance.parse().map_err(|_| usage())?;
+            format!(
+                "Set the world border warning distance to {} blocks",
+                border.warning_distance
+            )
+        }
+        _ => return Err(usage()),
+    };
+    server.set_world_border(border);
+    Ok(feedback)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{
+        math::Vec3,
+        net::{compression::PreparedPacket, packets, ClientEvent, ClientHook},
+        server::{command::dispatch, Player, Server},
+    };
+
+    #[test]
+    fn test_worldborder() {
+        let mut server = Server::for_tests(&["op"]);
+        let (op, mut op_events) = server.join_for_tests("op", Vec3::ZERO);
+        while op_events.try_recv().is_ok() {}
+
+        assert!(dispatch(&mut server, &op, "worldborder radius 0").is_err());
+        assert!(dispatch(&mut server, &op, "worldborder center 1").is_err());
+        assert!(dispatch(&mut server, &op, "worldborder warning -1").is_err());
+        assert!(op_events.try_recv().is_err());
+
+        let mut expect = |expected| match op_events.try_recv() {
+            Ok(ClientEvent::Prepared(packet)) => {
+                assert_eq!(packet.data(), PreparedPacket::new(expected).data())
+            }
+            event => panic!("expected a world border packet, got {:?}", event),
+        };
+        dispatch(&mut server, &op, "worldborder radius 100").unwrap();
+        expect(packets::world_border_size(200.0));
+        dispatch(&mut server, &op, "worldborder center 10.5 -20").unwrap();
+        expect(packets::world_border_center(10.5, -20.0));
+        dispatch(&mut server, &op, "worldborder warning 10").unwrap();
+        expect(packets::world_border_warning_distance(10));
+        assert_eq!(
+            dispatch(&mut server, &op, "worldborder").unwrap(),
+            "The world border is centered on 10.5 -20, with a radius of 100 blocks"
+        );
+
+        // players joining later get the whole border
+        let (client, mut events) = ClientHook::new();
+        let player = Player {
+            username: "late".into(),
+            uuid: Uuid::new_v4(),
+        };
+        server.join_game(player, client);
+        let expected = packets::initialize_world_border(&server.world_border);
+        assert!(
+            matches!(events.try_recv(), Ok(ClientEvent::Batch(batch)) if batch.contains(&expected))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Status effects, like the ones potions give.
+use bitflags::bitflags;
+
+use crate::data::Identifier;
+
+/// The effects, in the order of their IDs, which start at 1.
+const EFFECTS: &[&str] = &[
+    "speed",
+    "slowness",
+    "haste",
+    "mining_fatigue",
+    "strength",
+    "instant_health",
+    "instant_damage",
+    "jump_boost",
+    "nausea",
+    "regeneration",
+    "resistance",
+    "fire_resistance",
+    "water_breathing",
+    "invisibility",
+    "blindness",
+    "night_vision",
+    "hunger",
+    "weakness",
+    "poison",
+    "wither",
+    "health_boost",
+    "absorption",
+    "saturation",
+    "glowing",
+    "levitation",
+    "luck",
+    "unluck",
+    "slow_falling",
+    "conduit_power",
+    "dolphins_grace",
+    "bad_omen",
+    "hero_of_the_village",
+];
+
+/// Returns the ID of an effect.
+pub fn effect_id(effect: &Identifier) -> Option<u8> {
+    if effect.namespace != Identifier::DEFAULT_NAMESPACE {
+        return None;
+    }
+    let index = EFFECTS.iter().position(|&e| e == effect.path)?;
+    Some(index as u8 + 1)
+}
+
+bitflags! {
+    pub struct EffectFlags: u8 {
+        /// Whether the effect comes from a beacon or a conduit, which makes
+        /// its particles fainter.
+        const AMBIENT = 0x01;
+        const SHOW_PARTICLES = 0x02;
+        const SHOW_ICON = 0x04;
+    }
+}
+
+/// An effect an entity is under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Effect {
+    pub id: u8,
+    /// How much stronger than level I the effect is.
+    pub amplifier: u8,
+    /// How many ticks the effect has left.
+    pub duration: u32,
+    pub flags: EffectFlags,
+}
+
+impl Effect {
+    /// Counts down the duration by a tick, returning whether the effect is
+    /// still on afterwards.
+    pub fn tick(&mut self) -> bool {
+        self.duration = self.duration.saturating_sub(1);
+        self.duration > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{effect_id, Effect, EffectFlags};
+    use crate::data::Identifier;
+
+    #[test]
+    fn test_effect() {
+        assert_eq!(effect_id(&Identifier::minecraft("speed")), Some(1));
+        assert_eq!(
+            effect_id(&Identifier::minecraft("hero_of_the_village")),
+            Some(32)
+        );
+        assert_eq!(effect_id(&Identifier::minecraft("flight")), None);
+
+        let mut effect = Effect {
+            id: 1,
+            amplifier: 0,
+            duration: 2,
+            flags: EffectFlags::SHOW_PARTICLES,
+        };
+        assert!(effect.tick());
+        assert!(!effect.tick());
+        assert!(!effect.tick());
+    }
+}
@@ -0,0 +1,73 @@
+//! Entities other than players and item entities, like the ones spawned with
+//! `/summon`.
+use uuid::Uuid;
+
+use crate::{
+    data::Angle,
+    math::Vec3,
+    net::{packets, ResponseBuilder},
+    registry::entity::SpawnKind,
+};
+
+/// An entity that stands still, as the server doesn't simulate any AI or
+/// physics.
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub entity_id: i32,
+    pub uuid: Uuid,
+    pub entity_type: u32,
+    /// Which packet spawns the entity, which has to be either
+    /// [`SpawnKind::Object`] or [`SpawnKind::Living`].
+    pub kind: SpawnKind,
+    pub position: Vec3,
+    /// The yaw and pitch of the entity, in degrees.
+    pub rotation: (f32, f32),
+}
+
+impl Entity {
+    /// Returns the packet spawning the entity on a client.
+    pub fn spawn_packet(&self) -> ResponseBuilder {
+        let (yaw, pitch) = self.rotation;
+        let rotation = (to_angle(yaw), to_angle(pitch));
+        match self.kind {
+            SpawnKind::Living => packets::spawn_living_entity(
+                self.entity_id,
+                self.uuid,
+                self.entity_type,
+                self.position,
+                rotation,
+                rotation.0,
+                Vec3::ZERO,
+            ),
+            _ => packets::spawn_entity(
+                self.entity_id,
+                self.uuid,
+                self.entity_type,
+                self.position,
+                rotation,
+                0,
+                Vec3::ZERO,
+            ),
+        }
+    }
+}
+
+/// Converts an angle in degrees to steps of 1/256 of a full turn, wrapping
+/// negative angles around.
+fn to_angle(degrees: f32) -> Angle {
+    Angle((degrees.rem_euclid(360.0) / 360.0 * 256.0).round() as u32 as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_angle;
+    use crate::data::Angle;
+
+    #[test]
+    fn test_to_angle() {
+        assert_eq!(to_angle(0.0), Angle(0));
+        assert_eq!(to_angle(90.0), Angle(64));
+        assert_eq!(to_angle(-90.0), Angle(192));
+        assert_eq!(to_angle(359.9), Angle(0));
+    }
+}
@@ -0,0 +1,76 @@
+//! Allocation of entity IDs, which are shared by players and every other
+//! entity.
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// How long an entity ID is kept unused after its entity is gone, so that
+/// packets still in flight about the old entity, like the Destroy Entities
+/// removing it, can't be mistaken for ones about a new entity.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Hands out entity IDs, reusing the ones of entities that are gone once
+/// their grace period is over.
+#[derive(Debug, Default)]
+pub struct EntityIds {
+    next: i32,
+    /// The IDs that were released, and when, oldest first.
+    released: VecDeque<(i32, Instant)>,
+}
+
+impl EntityIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an ID that isn't in use, preferring ones that were released
+    /// long enough ago.
+    pub fn allocate(&mut self, now: Instant) -> i32 {
+        if let Some(&(id, released)) = self.released.front() {
+            if now.duration_since(released) >= GRACE_PERIOD {
+                self.released.pop_front();
+                return id;
+            }
+        }
+        let id = self.next;
+        self.next = self.next.checked_add(1).expect("ran out of entity IDs");
+        id
+    }
+
+    /// Marks an ID as no longer in use, so that it can be reused once the
+    /// grace period is over.
+    pub fn release(&mut self, id: i32, now: Instant) {
+        self.released.push_back((id, now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{EntityIds, GRACE_PERIOD};
+
+    #[test]
+    fn test_reuse() {
+        let start = Instant::now();
+        let mut ids = EntityIds::new();
+        assert_eq!(ids.allocate(start), 0);
+        assert_eq!(ids.allocate(start), 1);
+
+        ids.release(0, start);
+        // clients might still know about the old entity for a while
+        assert_eq!(ids.allocate(start), 2);
+        assert_eq!(ids.allocate(start + GRACE_PERIOD / 2), 3);
+        assert_eq!(ids.allocate(start + GRACE_PERIOD), 0);
+        assert_eq!(ids.allocate(start + GRACE_PERIOD), 4);
+
+        // oldest first
+        ids.release(3, start);
+        ids.release(1, start + GRACE_PERIOD);
+        let later = start + GRACE_PERIOD * 3;
+        assert_eq!(ids.allocate(later), 3);
+        assert_eq!(ids.allocate(later), 1);
+        assert_eq!(ids.allocate(later), 5);
+    }
+}
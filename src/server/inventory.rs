@@ -0,0 +1,104 @@
+//! Player inventories.
+use crate::data::{Slot, SlotData};
+
+/// The number of slots in the player inventory window, counting the crafting
+/// grid, armor and offhand slots.
+pub const SLOT_COUNT: usize = 46;
+
+/// Slots that items picked up or given go to, in the order they are filled:
+/// the hotbar first, then the main inventory.
+const STORAGE_SLOTS: [std::ops::Range<usize>; 2] = [36..45, 9..36];
+
+/// The contents of a player's inventory, indexed like the slots of the player
+/// inventory window.
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    slots: Vec<Slot>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![None; SLOT_COUNT],
+        }
+    }
+
+    pub fn slot(&self, index: usize) -> &Slot {
+        &self.slots[index]
+    }
+    pub fn slot_mut(&mut self, index: usize) -> &mut Slot {
+        &mut self.slots[index]
+    }
+
+    /// Adds some amount of an item, first to the stacks of that item that
+    /// aren't full yet, then to empty slots.
+    ///
+    /// Returns the indices of the slots that changed, and how many items
+    /// didn't fit.
+    pub fn add_item(&mut self, item: u32, mut count: u8, max_stack_size: u8) -> (Vec<usize>, u8) {
+        let mut changed = vec![];
+        let storage = || STORAGE_SLOTS.iter().cloned().flatten();
+
+        for index in storage() {
+            if count == 0 {
+                break;
+            }
+            if let Some(stack) = &mut self.slots[index] {
+                if stack.id() == item && stack.count() < max_stack_size {
+                    let added = count.min(max_stack_size - stack.count());
+                    stack.set_count(stack.count() + added);
+                    count -= added;
+                    changed.push(index);
+                }
+            }
+        }
+        for index in storage() {
+            if count == 0 {
+                break;
+            }
+            if self.slots[index].is_none() {
+                let added = count.min(max_stack_size);
+                self.slots[index] = Some(SlotData::new(item, added));
+                count -= added;
+                changed.push(index);
+            }
+        }
+        (changed, count)
+    }
+}
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Inventory;
+
+    #[test]
+    fn test_add_item() {
+        let mut inventory = Inventory::new();
+        let count =
+            |inventory: &Inventory, index| inventory.slot(index).as_ref().map(|s| s.count());
+
+        // fills the first hotbar slot...
+        assert_eq!(inventory.add_item(1, 40, 64), (vec![36], 0));
+        // ...then stacks onto it before using the next one
+        assert_eq!(inventory.add_item(1, 40, 64), (vec![36, 37], 0));
+        assert_eq!(count(&inventory, 36), Some(64));
+        assert_eq!(count(&inventory, 37), Some(16));
+
+        // other items don't stack with it
+        assert_eq!(inventory.add_item(2, 1, 1), (vec![38], 0));
+
+        // the main inventory is used once the hotbar is full
+        assert_eq!(inventory.add_item(3, 6, 1).0, [39, 40, 41, 42, 43, 44]);
+        assert_eq!(inventory.add_item(3, 1, 1), (vec![9], 0));
+
+        // leftovers are returned once everything is full
+        let (changed, remaining) = inventory.add_item(3, 30, 1);
+        assert_eq!(changed.len(), 26);
+        assert_eq!(remaining, 4);
+    }
+}
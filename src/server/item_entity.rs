@@ -0,0 +1,67 @@
+//! Items lying around in the world, like the ones dropped by players.
+use uuid::Uuid;
+
+use crate::{data::SlotData, math::Vec3};
+
+/// The ID of the `minecraft:item` entity type.
+pub const ITEM_ENTITY_TYPE: u32 = 41;
+
+/// How far above the feet of a player their eyes are, when standing.
+const EYE_HEIGHT: f64 = 1.62;
+
+/// An item entity, holding a stack of items.
+#[derive(Debug, Clone)]
+pub struct ItemEntity {
+    pub entity_id: i32,
+    pub uuid: Uuid,
+    pub item: SlotData,
+    pub position: Vec3,
+    /// The velocity the item was spawned with, in blocks per tick. The server
+    /// doesn't simulate item physics, so the item stays where it was spawned
+    /// as far as the server is concerned, while clients make it fly and fall.
+    pub velocity: Vec3,
+}
+
+impl ItemEntity {
+    /// Returns an item thrown by a player standing at `position` and looking
+    /// in the direction given by `yaw` and `pitch`, in degrees.
+    pub fn dropped(entity_id: i32, item: SlotData, position: Vec3, yaw: f32, pitch: f32) -> Self {
+        let (yaw, pitch) = (f64::from(yaw).to_radians(), f64::from(pitch).to_radians());
+        // the same as the vanilla server, without the random spread
+        let speed = 0.3;
+        let velocity = Vec3::new(
+            -yaw.sin() * pitch.cos() * speed,
+            -pitch.sin() * speed + 0.1,
+            yaw.cos() * pitch.cos() * speed,
+        );
+        Self {
+            entity_id,
+            uuid: Uuid::new_v4(),
+            item,
+            // from just below the eyes
+            position: position + Vec3::new(0.0, EYE_HEIGHT - 0.3, 0.0),
+            velocity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ItemEntity;
+    use crate::{data::SlotData, math::Vec3};
+
+    #[test]
+    fn test_dropped() {
+        // looking straight south, along +Z
+        let item = ItemEntity::dropped(1, SlotData::new(1, 1), Vec3::ZERO, 0.0, 0.0);
+        assert!((item.position.y - 1.32).abs() < 1e-9);
+        assert!(item.velocity.x.abs() < 1e-9);
+        assert!((item.velocity.y - 0.1).abs() < 1e-9);
+        assert!((item.velocity.z - 0.3).abs() < 1e-9);
+
+        // looking east, along +X, and straight up
+        let item = ItemEntity::dropped(1, SlotData::new(1, 1), Vec3::ZERO, -90.0, -90.0);
+        assert!(item.velocity.x.abs() < 1e-9);
+        assert!((item.velocity.y - 0.4).abs() < 1e-9);
+    }
+}
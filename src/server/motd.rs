@@ -0,0 +1,59 @@
+//! Placeholders in the message of the day, like `{online}`, which are filled
+//! in every time the server list asks for the status of the server.
+
+/// Replaces the placeholders in `motd` with what `value` returns for their
+/// name.
+///
+/// Placeholders `value` doesn't know about are left as they are, and `{{` and
+/// `}}` stand for literal braces, so that `{{online}}` is shown as `{online}`.
+pub fn substitute(motd: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut substituted = String::with_capacity(motd.len());
+    let mut rest = motd;
+    while let Some(i) = rest.find(['{', '}']) {
+        substituted.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            substituted.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let placeholder = rest
+            .strip_prefix('{')
+            .and_then(|r| r.split_once('}'))
+            .and_then(|(name, after)| Some((value(name)?, after)));
+        match placeholder {
+            Some((value, after)) => {
+                substituted.push_str(&value);
+                rest = after;
+            }
+            None => {
+                substituted.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::substitute;
+
+    #[test]
+    fn test_substitute() {
+        let value = |name: &str| match name {
+            "online" => Some("5".to_string()),
+            "max" => Some("20".to_string()),
+            _ => None,
+        };
+        assert_eq!(substitute("Online: {online}/{max}", value), "Online: 5/20");
+        assert_eq!(substitute("{online}{online}", value), "55");
+        assert_eq!(substitute("No placeholders", value), "No placeholders");
+
+        // anything that isn't a known placeholder stays as it is
+        assert_eq!(substitute("{unknown} {online", value), "{unknown} {online");
+        assert_eq!(substitute("}{", value), "}{");
+        assert_eq!(substitute("{{online}} {{{online}}}", value), "{online} {5}");
+    }
+}
@@ -0,0 +1,134 @@
+//! Scoreboard objectives and scores.
+use std::collections::{BTreeMap, HashMap};
+
+use crate::chat::Chat;
+
+/// How the scores of an objective are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderType {
+    Integer,
+    Hearts,
+}
+
+/// Where an objective can be displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DisplaySlot {
+    List,
+    Sidebar,
+    BelowName,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    /// The unique name of the objective, at most 16 characters long.
+    pub name: String,
+    pub display_name: Chat,
+    pub render_type: RenderType,
+}
+
+/// Every objective and score on the server.
+///
+/// Scores are kept by entity name, which is the username for players.
+#[derive(Debug, Default)]
+pub struct Scoreboard {
+    objectives: BTreeMap<String, Objective>,
+    scores: HashMap<(String, String), i32>,
+    displayed: HashMap<DisplaySlot, String>,
+}
+
+impl Scoreboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn objectives(&self) -> impl Iterator<Item = &Objective> {
+        self.objectives.values()
+    }
+    pub fn objective(&self, name: &str) -> Option<&Objective> {
+        self.objectives.get(name)
+    }
+    /// Adds an objective, or replaces the one with the same name.
+    pub fn add_objective(&mut self, objective: Objective) {
+        self.objectives.insert(objective.name.clone(), objective);
+    }
+    /// Removes an objective, along with its scores and display slots.
+    pub fn remove_objective(&mut self, name: &str) -> Option<Objective> {
+        let objective = self.objectives.remove(name)?;
+        self.scores.retain(|(_, o), _| o != name);
+        self.displayed.retain(|_, o| o != name);
+        Some(objective)
+    }
+
+    /// Returns every score as `(entity, objective, value)`.
+    pub fn scores(&self) -> impl Iterator<Item = (&str, &str, i32)> {
+        self.scores
+            .iter()
+            .map(|((entity, objective), &value)| (entity.as_str(), objective.as_str(), value))
+    }
+    pub fn score(&self, entity: &str, objective: &str) -> Option<i32> {
+        self.scores
+            .get(&(entity.to_string(), objective.to_string()))
+            .copied()
+    }
+    /// Sets a score, returning false if the objective doesn't exist.
+    pub fn set_score(&mut self, entity: &str, objective: &str, value: i32) -> bool {
+        if !self.objectives.contains_key(objective) {
+            return false;
+        }
+        self.scores
+            .insert((entity.to_string(), objective.to_string()), value);
+        true
+    }
+    pub fn reset_score(&mut self, entity: &str, objective: &str) -> Option<i32> {
+        self.scores
+            .remove(&(entity.to_string(), objective.to_string()))
+    }
+
+    pub fn displayed(&self) -> impl Iterator<Item = (DisplaySlot, &str)> {
+        self.displayed
+            .iter()
+            .map(|(&slot, name)| (slot, name.as_str()))
+    }
+    /// Displays an objective in a slot, or clears the slot if `objective` is
+    /// [`None`]. Returns false if the objective doesn't exist.
+    pub fn set_displayed(&mut self, slot: DisplaySlot, objective: Option<&str>) -> bool {
+        match objective {
+            Some(name) if !self.objectives.contains_key(name) => false,
+            Some(name) => {
+                self.displayed.insert(slot, name.to_string());
+                true
+            }
+            None => {
+                self.displayed.remove(&slot);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DisplaySlot, Objective, RenderType, Scoreboard};
+    use crate::chat::Chat;
+
+    #[test]
+    fn test_scoreboard() {
+        let mut scoreboard = Scoreboard::new();
+        assert!(!scoreboard.set_score("pluie", "kills", 1));
+
+        scoreboard.add_objective(Objective {
+            name: "kills".into(),
+            display_name: Chat::text("Kills"),
+            render_type: RenderType::Integer,
+        });
+        assert!(scoreboard.set_score("pluie", "kills", 3));
+        assert!(scoreboard.set_displayed(DisplaySlot::Sidebar, Some("kills")));
+        assert!(!scoreboard.set_displayed(DisplaySlot::List, Some("deaths")));
+        assert_eq!(scoreboard.score("pluie", "kills"), Some(3));
+
+        // removing an objective takes its scores and slots with it
+        scoreboard.remove_objective("kills").unwrap();
+        assert_eq!(scoreboard.score("pluie", "kills"), None);
+        assert_eq!(scoreboard.displayed().count(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+//! Countdowns to stopping the server, during which players are warned every
+//! so often.
+use std::time::{Duration, Instant};
+
+/// How many seconds before stopping the players are warned.
+const WARNINGS: &[u64] = &[300, 120, 60, 30, 10, 5, 4, 3, 2, 1];
+
+/// What a countdown is up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// The players should be warned that the server stops in that many
+    /// seconds.
+    Warn(u64),
+    /// The server should stop now.
+    Stop,
+}
+
+#[derive(Debug)]
+pub struct Countdown {
+    deadline: Instant,
+    /// The warnings that are yet to be given, latest last.
+    warnings: Vec<u64>,
+}
+
+impl Countdown {
+    /// Starts counting down from `seconds`, which players are expected to be
+    /// told about right away.
+    pub fn new(now: Instant, seconds: u64) -> Self {
+        Self {
+            deadline: now + Duration::from_secs(seconds),
+            warnings: WARNINGS.iter().copied().filter(|&w| w < seconds).collect(),
+        }
+    }
+
+    /// Returns when the next step is due.
+    pub fn next_step_at(&self) -> Instant {
+        match self.warnings.first() {
+            Some(&seconds) => self.deadline - Duration::from_secs(seconds),
+            None => self.deadline,
+        }
+    }
+
+    /// Returns the step that is due by `now`, if any.
+    ///
+    /// Warnings that were missed, because nobody asked for a while, are
+    /// skipped in favour of the latest one.
+    pub fn poll(&mut self, now: Instant) -> Option<Step> {
+        if now < self.next_step_at() {
+            return None;
+        }
+        let mut due = None;
+        while !self.warnings.is_empty() && now >= self.next_step_at() {
+            due = Some(Step::Warn(self.warnings.remove(0)));
+        }
+        due.or(Some(Step::Stop))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Countdown, Step};
+
+    #[test]
+    fn test_countdown() {
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+        let mut countdown = Countdown::new(start, 60);
+        assert_eq!(countdown.next_step_at(), at(30));
+        assert_eq!(countdown.poll(at(29)), None);
+
+        let mut steps = vec![];
+        for second in 0..=60 {
+            if let Some(step) = countdown.poll(at(second)) {
+                steps.push((second, step));
+            }
+        }
+        assert_eq!(
+            steps,
+            [
+                (30, Step::Warn(30)),
+                (50, Step::Warn(10)),
+                (55, Step::Warn(5)),
+                (56, Step::Warn(4)),
+                (57, Step::Warn(3)),
+                (58, Step::Warn(2)),
+                (59, Step::Warn(1)),
+                (60, Step::Stop),
+            ]
+        );
+
+        // missed warnings are skipped
+        let mut countdown = Countdown::new(start, 10);
+        assert_eq!(countdown.poll(at(7)), Some(Step::Warn(3)));
+        assert_eq!(countdown.poll(at(11)), Some(Step::Warn(1)));
+        assert_eq!(countdown.poll(at(11)), Some(Step::Stop));
+
+        // stopping right away
+        assert_eq!(Countdown::new(start, 0).poll(start), Some(Step::Stop));
+    }
+}
@@ -0,0 +1,234 @@
+//! Teams, which control name colors, collisions and friendly fire between
+//! players.
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitflags::bitflags;
+
+use crate::chat::{Chat, ChatColor};
+
+bitflags! {
+    pub struct TeamFlags: u8 {
+        const FRIENDLY_FIRE = 0x01;
+        /// Whether members can see invisible members of the same team.
+        const SEE_INVISIBLE = 0x02;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameTagVisibility {
+    Always,
+    HideForOtherTeams,
+    HideForOwnTeam,
+    Never,
+}
+impl NameTagVisibility {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::HideForOtherTeams => "hideForOtherTeams",
+            Self::HideForOwnTeam => "hideForOwnTeam",
+            Self::Never => "never",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionRule {
+    Always,
+    PushOtherTeams,
+    PushOwnTeam,
+    Never,
+}
+impl CollisionRule {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::PushOtherTeams => "pushOtherTeams",
+            Self::PushOwnTeam => "pushOwnTeam",
+            Self::Never => "never",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Team {
+    /// The unique name of the team, at most 16 characters long.
+    pub name: String,
+    pub display_name: Chat,
+    pub flags: TeamFlags,
+    pub name_tag_visibility: NameTagVisibility,
+    pub collision_rule: CollisionRule,
+    pub color: ChatColor,
+    /// Displayed before the names of members.
+    pub prefix: Chat,
+    /// Displayed after the names of members.
+    pub suffix: Chat,
+    /// The members of the team, by entity name, which is the username for
+    /// players.
+    pub members: BTreeSet<String>,
+}
+
+impl Team {
+    /// Creates a team with the default settings of the vanilla server.
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            display_name: Chat::text(name.clone()),
+            name,
+            flags: TeamFlags::FRIENDLY_FIRE | TeamFlags::SEE_INVISIBLE,
+            name_tag_visibility: NameTagVisibility::Always,
+            collision_rule: CollisionRule::Always,
+            color: ChatColor::Reset,
+            prefix: Chat::default(),
+            suffix: Chat::default(),
+            members: BTreeSet::new(),
+        }
+    }
+}
+
+/// The modes of the Teams packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeamAction {
+    Create(Team),
+    Remove,
+    /// Updates everything but the members.
+    UpdateInfo(Team),
+    AddMembers(Vec<String>),
+    RemoveMembers(Vec<String>),
+}
+
+/// Every team on the server.
+///
+/// An entity can only be on one team at a time.
+#[derive(Debug, Default)]
+pub struct Teams {
+    teams: BTreeMap<String, Team>,
+}
+
+impl Teams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn teams(&self) -> impl Iterator<Item = &Team> {
+        self.teams.values()
+    }
+    pub fn get(&self, name: &str) -> Option<&Team> {
+        self.teams.get(name)
+    }
+    /// Returns the team an entity is on, if any.
+    pub fn team_of(&self, member: &str) -> Option<&Team> {
+        self.teams.values().find(|t| t.members.contains(member))
+    }
+
+    /// Adds a team, or replaces the info of the team with the same name while
+    /// keeping its members.
+    ///
+    /// Returns the actions to send to clients, along with the names of the
+    /// teams they apply to.
+    pub fn add(&mut self, mut team: Team) -> Vec<(String, TeamAction)> {
+        if let Some(existing) = self.teams.get_mut(&team.name) {
+            team.members = std::mem::take(&mut existing.members);
+            *existing = team.clone();
+            return vec![(team.name.clone(), TeamAction::UpdateInfo(team))];
+        }
+        let mut actions = self.leave_other_teams(&team.name, &team.members);
+        self.teams.insert(team.name.clone(), team.clone());
+        actions.push((team.name.clone(), TeamAction::Create(team)));
+        actions
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Team> {
+        self.teams.remove(name)
+    }
+
+    /// Adds members to a team, taking them off their previous team.
+    ///
+    /// Returns the actions to send to clients, along with the names of the
+    /// teams they apply to, or nothing if the team doesn't exist.
+    pub fn add_members(&mut self, name: &str, members: &[String]) -> Vec<(String, TeamAction)> {
+        if !self.teams.contains_key(name) {
+            return vec![];
+        }
+        let mut actions = self.leave_other_teams(name, members);
+        let team = self.teams.get_mut(name).unwrap();
+        let joining: Vec<_> = members
+            .iter()
+            .filter(|m| team.members.insert(m.to_string()))
+            .cloned()
+            .collect();
+        if !joining.is_empty() {
+            actions.push((name.to_string(), TeamAction::AddMembers(joining)));
+        }
+        actions
+    }
+
+    fn leave_other_teams<'a>(
+        &mut self,
+        name: &str,
+        members: impl IntoIterator<Item = &'a String> + Copy,
+    ) -> Vec<(String, TeamAction)> {
+        let mut actions = vec![];
+        for team in self.teams.values_mut().filter(|t| t.name != name) {
+            let leaving: Vec<_> = members
+                .into_iter()
+                .filter(|m| team.members.remove(*m))
+                .cloned()
+                .collect();
+            if !leaving.is_empty() {
+                actions.push((team.name.clone(), TeamAction::RemoveMembers(leaving)));
+            }
+        }
+        actions
+    }
+
+    /// Removes members from a team, returning the ones that were on it.
+    pub fn remove_members(&mut self, name: &str, members: &[String]) -> Vec<String> {
+        match self.teams.get_mut(name) {
+            Some(team) => members
+                .iter()
+                .filter(|m| team.members.remove(*m))
+                .cloned()
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Team, TeamAction, Teams};
+
+    #[test]
+    fn test_membership() {
+        let mut teams = Teams::new();
+        let mut red = Team::new("red");
+        red.members.insert("pluie".into());
+        teams.add(red);
+
+        // joining another team means leaving the previous one
+        teams.add(Team::new("blue"));
+        let actions = teams.add_members("blue", &["pluie".into(), "lumi".into()]);
+        assert_eq!(
+            actions,
+            [
+                (
+                    "red".into(),
+                    TeamAction::RemoveMembers(vec!["pluie".into()])
+                ),
+                (
+                    "blue".into(),
+                    TeamAction::AddMembers(vec!["pluie".into(), "lumi".into()])
+                ),
+            ]
+        );
+        assert_eq!(teams.team_of("pluie").unwrap().name, "blue");
+        assert!(teams.get("red").unwrap().members.is_empty());
+
+        // updating a team keeps its members
+        let mut blue = Team::new("blue");
+        blue.prefix = "[B] ".into();
+        teams.add(blue);
+        assert_eq!(teams.get("blue").unwrap().members.len(), 2);
+    }
+}
@@ -0,0 +1,176 @@
+//! Distance-based entity visibility.
+//!
+//! Clients are only told about entities within their tracking range, so that
+//! every entity move doesn't have to be broadcast to every single player.
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+/// The smallest view distance a client can ask for.
+pub const MIN_VIEW_DISTANCE: u8 = 2;
+/// The largest view distance a client can ask for.
+pub const MAX_VIEW_DISTANCE: u8 = 32;
+
+/// Clamps the view distance asked for by a client between
+/// [`MIN_VIEW_DISTANCE`] and the view distance of the server.
+///
+/// Clients are only supposed to send values from 2 to 32, but nothing stops a
+/// modified one from sending 0 or 255.
+pub fn clamp_view_distance(requested: u8, max: u8) -> u8 {
+    requested.clamp(MIN_VIEW_DISTANCE, max.max(MIN_VIEW_DISTANCE))
+}
+
+/// Returns the tracking range, in blocks, for the given view distance.
+pub fn tracking_range(view_distance: u8) -> f64 {
+    f64::from(view_distance) * 16.0
+}
+
+/// What has to be sent to a viewer after an entity or the viewer moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tracking {
+    /// The entity came into range, and has to be spawned.
+    Spawn,
+    /// The entity is still in range, and its new position has to be sent.
+    Move,
+    /// The entity went out of range, and has to be destroyed.
+    Destroy,
+}
+
+/// Keeps track of which viewers currently have which entities spawned.
+#[derive(Debug, Default)]
+pub struct EntityTracker {
+    visible: HashSet<(Uuid, i32)>,
+}
+
+impl EntityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates whether `viewer` can see `entity`, given the distance between
+    /// them.
+    ///
+    /// Returns [`None`] if the entity was and still is out of range.
+    pub fn track(
+        &mut self,
+        viewer: Uuid,
+        entity: i32,
+        distance: f64,
+        range: f64,
+    ) -> Option<Tracking> {
+        let in_range = distance <= range;
+        match (self.visible.contains(&(viewer, entity)), in_range) {
+            (false, true) => {
+                self.visible.insert((viewer, entity));
+                Some(Tracking::Spawn)
+            }
+            (true, true) => Some(Tracking::Move),
+            (true, false) => {
+                self.visible.remove(&(viewer, entity));
+                Some(Tracking::Destroy)
+            }
+            (false, false) => None,
+        }
+    }
+
+    /// Returns whether `viewer` currently has `entity` spawned.
+    pub fn is_tracking(&self, viewer: Uuid, entity: i32) -> bool {
+        self.visible.contains(&(viewer, entity))
+    }
+
+    /// Forgets about a viewer and its own entity, returning the viewers that
+    /// had the entity spawned.
+    pub fn remove(&mut self, viewer: Uuid, entity: i32) -> Vec<Uuid> {
+        let mut viewers = vec![];
+        self.visible.retain(|&(v, e)| {
+            if e == entity {
+                viewers.push(v);
+                false
+            } else {
+                v != viewer
+            }
+        });
+        viewers
+    }
+
+    /// Forgets an entity that isn't a viewer, like an item, returning the
+    /// viewers that could see it.
+    pub fn remove_entity(&mut self, entity: i32) -> Vec<Uuid> {
+        let mut viewers = vec![];
+        self.visible.retain(|&(v, e)| {
+            if e == entity {
+                viewers.push(v);
+            }
+            e != entity
+        });
+        viewers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{clamp_view_distance, tracking_range, EntityTracker, Tracking};
+    use crate::math::Vec3;
+
+    #[test]
+    fn test_tracking() {
+        let range = tracking_range(2);
+        assert_eq!(range, 32.0);
+
+        let mut tracker = EntityTracker::new();
+        let near = Uuid::from_u128(1);
+        let far = Uuid::from_u128(2);
+        let (near_pos, far_pos) = (Vec3::new(0.0, 64.0, 0.0), Vec3::new(500.0, 64.0, 0.0));
+
+        // an entity next to `near` is only spawned for `near`
+        let entity = Vec3::new(10.0, 64.0, 0.0);
+        let track = |tracker: &mut EntityTracker, viewer, viewer_pos: Vec3, entity: Vec3| {
+            tracker.track(viewer, 0, viewer_pos.distance(entity), range)
+        };
+        assert_eq!(
+            track(&mut tracker, near, near_pos, entity),
+            Some(Tracking::Spawn)
+        );
+        assert_eq!(track(&mut tracker, far, far_pos, entity), None);
+
+        // it keeps moving near `near`
+        let entity = Vec3::new(20.0, 64.0, 0.0);
+        assert_eq!(
+            track(&mut tracker, near, near_pos, entity),
+            Some(Tracking::Move)
+        );
+        assert_eq!(track(&mut tracker, far, far_pos, entity), None);
+
+        // it crosses into the range of `far` and out of the range of `near`
+        let entity = Vec3::new(480.0, 64.0, 0.0);
+        assert_eq!(
+            track(&mut tracker, near, near_pos, entity),
+            Some(Tracking::Destroy)
+        );
+        assert_eq!(
+            track(&mut tracker, far, far_pos, entity),
+            Some(Tracking::Spawn)
+        );
+
+        // and stays out of the range of `near`
+        assert_eq!(track(&mut tracker, near, near_pos, entity), None);
+    }
+
+    #[test]
+    fn test_clamp_view_distance() {
+        // below the minimum
+        assert_eq!(clamp_view_distance(0, 10), 2);
+        assert_eq!(clamp_view_distance(1, 10), 2);
+        // above the server's view distance
+        assert_eq!(clamp_view_distance(32, 10), 10);
+        assert_eq!(clamp_view_distance(255, 10), 10);
+        // in range
+        assert_eq!(clamp_view_distance(2, 10), 2);
+        assert_eq!(clamp_view_distance(6, 10), 6);
+        assert_eq!(clamp_view_distance(10, 10), 10);
+        // even a server configured below the minimum allows it
+        assert_eq!(clamp_view_distance(6, 1), 2);
+    }
+}
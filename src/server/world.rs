@@ -0,0 +1,191 @@
+//! The blocks making up the world.
+use std::{collections::HashMap, ops::Range};
+
+use tracing::warn;
+
+use super::dimension::DimensionType;
+use crate::{
+    math::{BlockPos, Vec3},
+    registry::block::AIR,
+};
+
+/// The largest radius explosions can have, like vanilla. The offsets of the
+/// blocks an explosion destroys are sent as bytes, so much larger ones would
+/// overflow them.
+pub const MAX_EXPLOSION_RADIUS: f64 = 16.0;
+
+/// A sparse store of the blocks in the world, as block state IDs.
+///
+/// Only blocks that have been explicitly set are stored; every other block is
+/// air.
+#[derive(Debug)]
+pub struct World {
+    blocks: HashMap<BlockPos, u32>,
+    block_entities: HashMap<BlockPos, BlockEntity>,
+    /// The Y coordinates blocks can be at, from the dimension's `min_y` up to
+    /// `min_y + height`, exclusive.
+    heights: Range<i32>,
+}
+
+/// Extra data attached to some blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockEntity {
+    Sign { lines: [String; 4] },
+}
+impl BlockEntity {
+    pub fn sign() -> Self {
+        Self::Sign {
+            lines: Default::default(),
+        }
+    }
+}
+
+impl World {
+    pub fn new(dimension: &DimensionType) -> Self {
+        Self {
+            blocks: HashMap::new(),
+            block_entities: HashMap::new(),
+            heights: dimension.min_y..dimension.min_y + dimension.height,
+        }
+    }
+
+    /// Returns whether `pos` is within the height of the world, where blocks
+    /// can be.
+    pub fn contains(&self, pos: BlockPos) -> bool {
+        self.heights.contains(&pos.y)
+    }
+
+    pub fn block(&self, pos: BlockPos) -> u32 {
+        self.blocks.get(&pos).copied().unwrap_or(AIR)
+    }
+
+    /// Sets the block at `pos`, returning the block that was there before, or
+    /// `None` if `pos` is outside the world, in which case nothing changes.
+    ///
+    /// Any block entity at `pos` is removed.
+    pub fn set_block(&mut self, pos: BlockPos, state: u32) -> Option<u32> {
+        if !self.contains(pos) {
+            warn!(?pos, "Ignoring a block set outside the world");
+            return None;
+        }
+        self.block_entities.remove(&pos);
+        let old = if state == AIR {
+            self.blocks.remove(&pos)
+        } else {
+            self.blocks.insert(pos, state)
+        };
+        Some(old.unwrap_or(AIR))
+    }
+
+    pub fn block_entity_mut(&mut self, pos: BlockPos) -> Option<&mut BlockEntity> {
+        self.block_entities.get_mut(&pos)
+    }
+    /// Attaches a block entity to the block at `pos`.
+    pub fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
+        if !self.contains(pos) {
+            warn!(?pos, "Ignoring a block entity outside the world");
+            return;
+        }
+        self.block_entities.insert(pos, block_entity);
+    }
+
+    /// Destroys every block within `radius` of `center`, returning the
+    /// positions of the blocks that were destroyed.
+    pub fn explode(&mut self, center: Vec3, radius: f64) -> Vec<BlockPos> {
+        let min = (center - Vec3::new(radius, radius, radius)).block_pos();
+        let max = (center + Vec3::new(radius, radius, radius)).block_pos();
+
+        // the part of the explosion outside the world has nothing to destroy
+        let min_y = min.y.max(self.heights.start);
+        let max_y = max.y.min(self.heights.end - 1);
+
+        let mut destroyed = vec![];
+        for x in min.x..=max.x {
+            for y in min_y..=max_y {
+                for z in min.z..=max.z {
+                    let pos = BlockPos::new(x, y, z);
+                    if pos.center().distance(center) <= radius
+                        && self.set_block(pos, AIR).unwrap_or(AIR) != AIR
+                    {
+                        destroyed.push(pos);
+                    }
+                }
+            }
+        }
+        destroyed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockEntity, World};
+    use crate::{
+        math::{BlockPos, Vec3},
+        server::dimension::DimensionManager,
+    };
+
+    fn world() -> World {
+        World::new(DimensionManager::new().current_dimension())
+    }
+
+    #[test]
+    fn test_explode() {
+        let mut world = world();
+        for x in -5..=5 {
+            world.set_block(BlockPos::new(x, 64, 0), 1);
+        }
+        let destroyed = world.explode(Vec3::new(0.5, 64.5, 0.5), 2.0);
+        assert_eq!(
+            destroyed,
+            (-2..=2)
+                .map(|x| BlockPos::new(x, 64, 0))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(world.block(BlockPos::new(0, 64, 0)), 0);
+        assert_eq!(world.block(BlockPos::new(3, 64, 0)), 1);
+        assert_eq!(world.block(BlockPos::new(-3, 64, 0)), 1);
+    }
+
+    #[test]
+    fn test_block_entities() {
+        let mut world = world();
+        let pos = BlockPos::new(0, 64, 0);
+        world.set_block(pos, 1);
+        world.set_block_entity(pos, BlockEntity::sign());
+
+        let BlockEntity::Sign { lines } = world.block_entity_mut(pos).unwrap();
+        lines[0] = "hello".into();
+        assert_eq!(
+            world.block_entity_mut(pos).cloned(),
+            Some(BlockEntity::Sign {
+                lines: ["hello".into(), "".into(), "".into(), "".into()]
+            })
+        );
+
+        // replacing the block removes its block entity
+        world.set_block(pos, 2);
+        assert_eq!(world.block_entity_mut(pos), None);
+    }
+
+    #[test]
+    fn test_height() {
+        let mut world = world();
+        let (bottom, top) = (BlockPos::new(0, 0, 0), BlockPos::new(0, 255, 0));
+        assert_eq!(world.set_block(bottom, 1), Some(0));
+        assert_eq!(world.set_block(top, 1), Some(0));
+        assert_eq!(world.block(top), 1);
+
+        // nothing can be set below or above the world
+        for y in [-1, 256] {
+            let pos = BlockPos::new(0, y, 0);
+            assert!(!world.contains(pos));
+            assert_eq!(world.set_block(pos, 1), None);
+            world.set_block_entity(pos, BlockEntity::sign());
+            assert_eq!(world.block(pos), 0);
+            assert_eq!(world.block_entity_mut(pos), None);
+        }
+
+        // explosions reaching outside the world only destroy what's inside it
+        assert_eq!(world.explode(Vec3::new(0.5, 0.5, 0.5), 1.0), vec![bottom]);
+    }
+}
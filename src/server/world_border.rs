@@ -0,0 +1,36 @@
+//! The world border, the edge of the world players can't go past.
+
+/// The largest radius the border can have, which is also how far from the
+/// center of the world portals can take players.
+pub const MAX_RADIUS: f64 = 29_999_984.0;
+
+/// A square border around a center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBorder {
+    pub center_x: f64,
+    pub center_z: f64,
+    /// The distance from the center to each side of the border, in blocks.
+    pub radius: f64,
+    /// How close to the border, in blocks, players' screens start turning red.
+    pub warning_distance: u32,
+    /// How many seconds before a shrinking border reaches players their screens
+    /// start turning red.
+    pub warning_time: u32,
+}
+impl WorldBorder {
+    /// Returns the side length of the border, which is what the protocol uses.
+    pub fn diameter(&self) -> f64 {
+        self.radius * 2.0
+    }
+}
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self {
+            center_x: 0.0,
+            center_z: 0.0,
+            radius: MAX_RADIUS,
+            warning_distance: 5,
+            warning_time: 15,
+        }
+    }
+}
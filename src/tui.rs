@@ -1,133 +1,390 @@
-use std::io::{stdout, Stdout};
+use std::{
+    io::{stdout, Stdout},
+    time::Duration,
+};
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
-use tracing::trace;
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+};
+use tokio::time;
+use tracing::{debug, info, trace, warn};
 use tui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Paragraph},
+    widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table},
     Frame, Terminal,
 };
-use tui_logger::TuiLoggerWidget;
+use tui_logger::{TuiLoggerWidget, TuiWidgetEvent, TuiWidgetState};
 use unicode_width::{UnicodeWidthStr, UnicodeWidthChar};
 
+use crate::server::{
+    build_info,
+    command::{self, uptime::format_uptime, SourceKind},
+    PlayerListEntry, ServerHook, ServerStats,
+};
+
 pub struct Tui {
     terminal: Terminal<Backend>,
     inner: TuiInner,
+    /// Whether the terminal currently sends us mouse events.
+    mouse_captured: bool,
+    server: ServerHook,
 }
 struct TuiInner {
     input_mode: InputMode,
     input: InputField,
+    /// The players in the game, as of the last tick.
+    players: Vec<PlayerListEntry>,
+    /// How the server is doing, as of the last tick.
+    stats: Option<ServerStats>,
+    /// Which part of the log history is shown.
+    log_state: TuiWidgetState,
+    /// The size of the terminal as of the last draw, for working out which
+    /// pane got clicked.
+    area: Rect,
+    /// The new size of the terminal, if it was resized since the last tick.
+    resized: Option<Rect>,
+    /// Whether mouse events should be captured, which stops the terminal from
+    /// selecting text with the mouse. Toggled with `m`.
+    mouse_capture: bool,
 }
 
 type Backend = CrosstermBackend<Stdout>;
 
+/// How long to wait for input before drawing again, which is how often the
+/// player list gets updated.
+const TICK: Duration = Duration::from_millis(250);
+
+/// How long to wait for the server to send the player list and stats, before
+/// drawing the previous ones again so that the TUI stays responsive.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(1);
+
 impl Tui {
-    pub fn new() -> eyre::Result<Self> {
+    pub fn new(mouse_capture: bool, server: ServerHook) -> eyre::Result<Self> {
+        prepare_console();
+        restore_terminal_on_panic();
         crossterm::terminal::enable_raw_mode()?;
+        crossterm::execute!(stdout(), EnterAlternateScreen)?;
         let backend = CrosstermBackend::new(stdout());
         let mut terminal = Terminal::new(backend)?;
 
         terminal.clear()?;
         terminal.show_cursor()?;
 
-        Ok(Self {
+        let mut tui = Self {
             terminal,
-            inner: TuiInner::new(),
-        })
+            inner: TuiInner::new(mouse_capture),
+            mouse_captured: false,
+            server,
+        };
+        tui.sync_mouse_capture()?;
+        Ok(tui)
     }
 
-    pub fn tick(&mut self) -> eyre::Result<ControlFlow> {
+    pub async fn tick(&mut self) -> eyre::Result<ControlFlow> {
+        let update = time::timeout(SERVER_TIMEOUT, async {
+            let players = self.server.get_player_list().await?;
+            let stats = self.server.get_stats().await?;
+            Ok::<_, eyre::Report>((players, stats))
+        });
+        match update.await {
+            Ok(Ok((players, stats))) => {
+                self.inner.players = players;
+                self.inner.stats = Some(stats);
+            }
+            Ok(Err(e)) => {
+                debug!(?e, "Failed to get the player list, the server stopped");
+                return Ok(ControlFlow::ServerStopped);
+            }
+            Err(_) => debug!("The server is busy, showing the previous player list"),
+        }
         self.terminal.draw(|f| self.inner.ui(f))?;
 
-        self.inner.handle_events()
+        // waiting for input blocks, which would hold up the tasks sharing
+        // this thread
+        let event = tokio::task::spawn_blocking(|| {
+            if event::poll(TICK)? {
+                event::read().map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+        .await??;
+        let event = match event {
+            Some(event) => event,
+            None => return Ok(ControlFlow::Continue),
+        };
+        let flow = self.inner.handle_event(event);
+        if let Some(line) = self.inner.input.submitted.take() {
+            self.run_command(&line).await;
+        }
+        if let Some(area) = self.inner.resized.take() {
+            // clears the screen, so that everything gets redrawn in its new
+            // place on the next tick
+            self.terminal.resize(area)?;
+        }
+        self.sync_mouse_capture()?;
+        Ok(flow)
+    }
+
+    /// Runs a command typed into the console, logging its feedback.
+    async fn run_command(&self, line: &str) {
+        let line = line.trim().trim_start_matches('/');
+        if line.is_empty() {
+            return;
+        }
+        match self.server.run_command(SourceKind::Console, line).await {
+            Ok(Ok(feedback)) if feedback.is_empty() => {}
+            Ok(Ok(feedback)) => info!("{}", feedback),
+            Ok(Err(e)) => warn!("{}", e),
+            // the next tick notices that the server stopped
+            Err(e) => debug!(?e, "Failed to run a command, the server stopped"),
+        }
+    }
+
+    fn sync_mouse_capture(&mut self) -> eyre::Result<()> {
+        if self.inner.mouse_capture != self.mouse_captured {
+            if self.inner.mouse_capture {
+                crossterm::execute!(stdout(), EnableMouseCapture)?;
+            } else {
+                crossterm::execute!(stdout(), DisableMouseCapture)?;
+            }
+            self.mouse_captured = self.inner.mouse_capture;
+        }
+        Ok(())
     }
 
     pub fn cleanup(mut self) -> eyre::Result<()> {
         self.terminal.clear()?;
-        crossterm::terminal::disable_raw_mode()?;
+        self.terminal.show_cursor()?;
+        restore_terminal()?;
 
         Ok(())
     }
 }
 
+/// Puts the terminal back the way it was before the TUI took it over.
+fn restore_terminal() -> crossterm::Result<()> {
+    crossterm::execute!(stdout(), DisableMouseCapture, LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()
+}
+
+/// Restores the terminal before the panic message gets printed, which would
+/// otherwise be lost on the alternate screen, and leave the terminal in raw
+/// mode.
+///
+/// Only panics on the thread the TUI runs on bring it down. Other tasks, like
+/// connections, can panic without the server stopping, so the terminal is left
+/// alone for them.
+fn restore_terminal_on_panic() {
+    let tui_thread = std::thread::current().id();
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().id() == tui_thread {
+            let _ = restore_terminal();
+        }
+        hook(info);
+    }));
+}
+
+/// Makes sure the console understands the escape codes the TUI is drawn with.
+///
+/// Windows 10 consoles do, but only once virtual terminal processing is turned
+/// on. Older ones don't at all, in which case crossterm falls back to the
+/// console API, which is slower and flickers.
+#[cfg(windows)]
+fn prepare_console() {
+    if !crossterm::ansi_support::supports_ansi() {
+        tracing::warn!("This console doesn't support escape codes, the TUI may flicker");
+    }
+}
+
+/// Makes sure the console understands the escape codes the TUI is drawn with,
+/// which every terminal does outside of Windows.
+#[cfg(not(windows))]
+fn prepare_console() {}
+
 impl TuiInner {
-    fn new() -> Self {
+    fn new(mouse_capture: bool) -> Self {
         Self {
             input_mode: InputMode::Normal,
             input: InputField::new(),
+            players: vec![],
+            stats: None,
+            log_state: TuiWidgetState::new(),
+            area: Rect::default(),
+            resized: None,
+            mouse_capture,
         }
     }
 
-    fn handle_events(&mut self) -> eyre::Result<ControlFlow> {
-        if let Event::Key(key) = event::read()? {
-            match self.input_mode {
-                InputMode::Normal => match key {
-                    KeyEvent {
-                        code: KeyCode::Char('c'),
-                        modifiers: KeyModifiers::CONTROL,
-                    } => return Ok(ControlFlow::Halt),
-                    KeyEvent {
-                        code: KeyCode::Char('/'),
-                        ..
-                    } => {
-                        self.input_mode = InputMode::Input;
-                        self.input.begin();
-                    }
-                    _ => {}
-                },
-                InputMode::Input => match key {
-                    KeyEvent {
-                        code: KeyCode::Esc, ..
-                    } => self.input_mode = InputMode::Normal,
-                    k => self.input.handle_events(k),
-                },
-                InputMode::Log => match key {
-                    KeyEvent {
-                        code: KeyCode::Esc, ..
-                    } => self.input_mode = InputMode::Normal,
-                    _ => {}
-                },
+    fn handle_event(&mut self, event: Event) -> ControlFlow {
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                self.handle_mouse(mouse);
+                return ControlFlow::Continue;
             }
+            Event::Resize(width, height) => {
+                self.resized = Some(Rect::new(0, 0, width, height));
+                self.area = Rect::new(0, 0, width, height);
+                return ControlFlow::Continue;
+            }
+        };
+        match self.input_mode {
+            InputMode::Normal => match key {
+                KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                } => return ControlFlow::Halt,
+                KeyEvent {
+                    code: KeyCode::Char('/'),
+                    ..
+                } => {
+                    self.input_mode = InputMode::Input;
+                    self.input.begin();
+                }
+                KeyEvent {
+                    code: KeyCode::Char('m'),
+                    ..
+                } => self.mouse_capture = !self.mouse_capture,
+                KeyEvent {
+                    code: KeyCode::PageUp,
+                    ..
+                } => self.scroll_log(TuiWidgetEvent::PrevPageKey),
+                _ => {}
+            },
+            InputMode::Input => match key {
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => self.input_mode = InputMode::Normal,
+                k => self.input.handle_events(k),
+            },
+            InputMode::Log => match key {
+                KeyEvent {
+                    code: KeyCode::Esc, ..
+                } => {
+                    // jump back to the latest messages
+                    self.log_state.transition(&TuiWidgetEvent::EscapeKey);
+                    self.input_mode = InputMode::Normal;
+                }
+                KeyEvent {
+                    code: KeyCode::PageUp,
+                    ..
+                } => self.scroll_log(TuiWidgetEvent::PrevPageKey),
+                KeyEvent {
+                    code: KeyCode::PageDown,
+                    ..
+                } => self.scroll_log(TuiWidgetEvent::NextPageKey),
+                _ => {}
+            },
         }
-        Ok(ControlFlow::Continue)
+        ControlFlow::Continue
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let panes = Panes::new(self.area);
+        let at = Rect::new(mouse.column, mouse.row, 1, 1);
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.scroll_log(TuiWidgetEvent::PrevPageKey),
+            MouseEventKind::ScrollDown => self.scroll_log(TuiWidgetEvent::NextPageKey),
+            MouseEventKind::Down(MouseButton::Left)
+                if panes.input.intersects(at) && !matches!(self.input_mode, InputMode::Input) =>
+            {
+                self.input_mode = InputMode::Input;
+                self.input.begin();
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves through the log history, which leaves it where it is until Esc is
+    /// pressed.
+    fn scroll_log(&mut self, event: TuiWidgetEvent) {
+        self.input_mode = InputMode::Log;
+        self.log_state.transition(&event);
     }
 
     fn ui(&mut self, f: &mut Frame<Backend>) {
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .margin(1)
-            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
-            .split(f.size());
-        let log_and_input = Layout::default()
-            .direction(Direction::Vertical)
-            // .margin(1)
-            .constraints([Constraint::Min(10), Constraint::Length(3)].as_ref())
-            .split(chunks[1]);
+        self.area = f.size();
+        let panes = Panes::new(self.area);
 
         let input = Spans::from(vec![Span::raw("/"), Span::raw(self.input.current())]);
+        let title = match self.input.candidates() {
+            [] => "Input".to_string(),
+            candidates => format!("Input ({})", candidates.join(" ")),
+        };
         let input = Paragraph::new(input).block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Input")
+                .title(title)
                 .border_type(BorderType::Rounded),
         );
 
-        f.render_widget(Self::logger(), log_and_input[0]);
-        f.render_widget(input, log_and_input[1]);
+        let mut logger = Self::logger();
+        logger.state(&self.log_state);
+        f.render_widget(logger, panes.log);
+        f.render_widget(Self::stats(self.stats.as_ref()), panes.stats);
+        f.render_widget(Self::player_list(&self.players), panes.players);
+        f.render_widget(input, panes.input);
 
         match self.input_mode {
             InputMode::Normal => {}
             InputMode::Input => f.set_cursor(
-                log_and_input[1].x + self.input.apparent_cursor() as u16 + 2,
-                log_and_input[1].y + 1,
+                panes.input.x + self.input.apparent_cursor() as u16 + 2,
+                panes.input.y + 1,
             ),
             InputMode::Log => {}
         }
     }
 
+    fn stats(stats: Option<&ServerStats>) -> Paragraph<'static> {
+        let text = match stats {
+            Some(stats) => vec![
+                Spans::from(format!("Version  {}", build_info())),
+                Spans::from(format!(
+                    "Uptime   {}",
+                    format_uptime(Duration::from_secs(stats.uptime_secs))
+                )),
+                Spans::from(format!(
+                    "Players  {}/{}",
+                    stats.online_players, stats.max_players
+                )),
+            ],
+            None => vec![Spans::from("Starting...")],
+        };
+        Paragraph::new(text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Server")
+                .border_type(BorderType::Rounded),
+        )
+    }
+
+    fn player_list(players: &[PlayerListEntry]) -> Table<'_> {
+        let rows = players.iter().map(|p| {
+            Row::new(vec![
+                Cell::from(p.username.as_str()),
+                Cell::from(format!("{} ms", p.ping)),
+            ])
+        });
+        Table::new(rows)
+            .widths(&[Constraint::Length(16), Constraint::Length(8)])
+            .column_spacing(1)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Players ({})", players.len()))
+                    .border_type(BorderType::Rounded),
+            )
+    }
+
     fn logger() -> TuiLoggerWidget<'static> {
         TuiLoggerWidget::default()
             .style_error(Style::default().fg(Color::Red))
@@ -144,11 +401,51 @@ impl TuiInner {
     }
 }
 
+/// Where each pane goes on the screen, which is worked out again on every
+/// draw so that it follows the size of the terminal.
+#[derive(Debug, PartialEq, Eq)]
+struct Panes {
+    stats: Rect,
+    players: Rect,
+    log: Rect,
+    input: Rect,
+}
+
+impl Panes {
+    fn new(area: Rect) -> Self {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(1)
+            // wide enough for the longest usernames and their ping
+            .constraints([Constraint::Length(28), Constraint::Min(0)].as_ref())
+            .split(area);
+        let stats_and_players = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(5), Constraint::Min(0)].as_ref())
+            .split(chunks[0]);
+        let log_and_input = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(10), Constraint::Length(3)].as_ref())
+            .split(chunks[1]);
+        Self {
+            stats: stats_and_players[0],
+            players: stats_and_players[1],
+            log: log_and_input[0],
+            input: log_and_input[1],
+        }
+    }
+}
+
 struct InputField {
     input: String,
     cursor: usize,
     history: Vec<String>,
     history_preview: Option<usize>,
+    /// The command names the input was completed against, if there were
+    /// several of them, and the one it was completed to.
+    completion: Option<(Vec<&'static str>, usize)>,
+    /// The line entered with Enter, until the command in it is run.
+    submitted: Option<String>,
 }
 
 impl InputField {
@@ -158,6 +455,8 @@ impl InputField {
             history: vec![],
             history_preview: None,
             cursor: 0,
+            completion: None,
+            submitted: None,
         }
     }
     fn current(&self) -> &str {
@@ -169,8 +468,47 @@ impl InputField {
         self.cursor.min(self.current().width())
     }
     fn begin(&mut self) {}
+    /// Returns the command names that Tab cycles through, if it is ambiguous.
+    fn candidates(&self) -> &[&'static str] {
+        self.completion
+            .as_ref()
+            .map_or(&[], |(candidates, _)| candidates)
+    }
+    /// Completes the command name being typed, or goes on to the next one if
+    /// it was already completed and there were several.
+    fn complete(&mut self) {
+        let name = match &mut self.completion {
+            Some((candidates, selected)) => {
+                *selected = (*selected + 1) % candidates.len();
+                candidates[*selected].to_string()
+            }
+            None => {
+                let current = self.current();
+                if current.contains(' ') {
+                    // only command names can be completed
+                    return;
+                }
+                match command::complete(current).as_slice() {
+                    [] => return,
+                    [name] => format!("{} ", name),
+                    candidates => {
+                        let name = candidates[0].to_string();
+                        self.completion = Some((candidates.to_vec(), 0));
+                        name
+                    }
+                }
+            }
+        };
+        self.history_preview = None;
+        self.cursor = name.width();
+        self.input = name;
+    }
     fn handle_events(&mut self, event: KeyEvent) {
+        if event.code != KeyCode::Tab {
+            self.completion = None;
+        }
         match event.code {
+            KeyCode::Tab => self.complete(),
             KeyCode::Char(ch) => {
                 self.history_preview = None;
                 self.cursor = self.apparent_cursor(); // resync cursors.
@@ -187,8 +525,9 @@ impl InputField {
                 trace!(self.cursor);
             }
             KeyCode::Enter => {
-                //TODO
-                self.history.push(self.current().to_string());
+                let line = self.current().to_string();
+                self.submitted = Some(line.clone());
+                self.history.push(line);
                 self.history_preview = None;
                 self.input.clear();
                 self.cursor = 0;
@@ -219,6 +558,8 @@ impl InputField {
 pub enum ControlFlow {
     Halt,
     Continue,
+    /// The server isn't running anymore, because it was stopped or it failed.
+    ServerStopped,
 }
 
 enum InputMode {
@@ -226,3 +567,156 @@ enum InputMode {
     Input,
     Log,
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    };
+    use tui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+    use super::{ControlFlow, InputField, InputMode, Panes, TuiInner};
+    use crate::server::{command, PlayerListEntry};
+
+    #[test]
+    fn test_resize() {
+        let mut tui = TuiInner::new(false);
+        assert!(matches!(
+            tui.handle_event(Event::Resize(120, 40)),
+            ControlFlow::Continue
+        ));
+        assert_eq!(tui.resized, Some(Rect::new(0, 0, 120, 40)));
+
+        // the panes fill whatever size the terminal has, with the input box
+        // right below the logs
+        for area in [Rect::new(0, 0, 120, 40), Rect::new(0, 0, 60, 20)] {
+            let panes = Panes::new(area);
+            assert_eq!(panes.input.height, 3);
+            assert_eq!(panes.input.y, panes.log.y + panes.log.height);
+            assert_eq!(panes.input.bottom(), area.bottom() - 1);
+            assert_eq!(panes.log.width, panes.input.width);
+            assert_eq!(panes.log.right(), area.right() - 1);
+            // the server stats sit right above the player list
+            assert_eq!(panes.players.y, panes.stats.bottom());
+            assert_eq!(panes.players.bottom(), area.bottom() - 1);
+        }
+        assert_ne!(
+            Panes::new(Rect::new(0, 0, 120, 40)),
+            Panes::new(Rect::new(0, 0, 60, 20))
+        );
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn test_mouse() {
+        let mut tui = TuiInner::new(true);
+        tui.handle_event(Event::Resize(120, 40));
+        let panes = Panes::new(tui.area);
+
+        // scrolling anywhere moves through the log history
+        tui.handle_event(mouse(MouseEventKind::ScrollUp, 0, 0));
+        assert!(matches!(tui.input_mode, InputMode::Log));
+        tui.handle_event(mouse(MouseEventKind::ScrollDown, 0, 0));
+        assert!(matches!(tui.input_mode, InputMode::Log));
+        tui.handle_event(Event::Key(KeyEvent::from(KeyCode::Esc)));
+        assert!(matches!(tui.input_mode, InputMode::Normal));
+
+        // clicking the logs does nothing, but clicking the input box focuses it
+        let click = MouseEventKind::Down(MouseButton::Left);
+        tui.handle_event(mouse(click, panes.log.x + 1, panes.log.y + 1));
+        assert!(matches!(tui.input_mode, InputMode::Normal));
+        tui.handle_event(mouse(click, panes.input.x + 1, panes.input.y + 1));
+        assert!(matches!(tui.input_mode, InputMode::Input));
+
+        // mouse capture can be turned off to select text again
+        tui.handle_event(Event::Key(KeyEvent::from(KeyCode::Esc)));
+        tui.handle_event(Event::Key(KeyEvent::from(KeyCode::Char('m'))));
+        assert!(!tui.mouse_capture);
+    }
+
+    #[test]
+    fn test_player_list() {
+        let players = [
+            PlayerListEntry {
+                username: "Notch".into(),
+                ping: 42,
+            },
+            PlayerListEntry {
+                username: "jeb_".into(),
+                ping: 0,
+            },
+        ];
+        let area = Rect::new(0, 0, 28, 6);
+        let mut buf = Buffer::empty(area);
+        TuiInner::player_list(&players).render(area, &mut buf);
+
+        let lines: Vec<String> = buf
+            .content
+            .chunks(area.width as usize)
+            .map(|line| line.iter().map(|cell| cell.symbol.as_str()).collect())
+            .collect();
+        assert!(lines[0].contains("Players (2)"));
+        assert_eq!(lines[1], "│Notch            42 ms    │");
+        assert_eq!(lines[2], "│jeb_             0 ms     │");
+        assert_eq!(lines[3].trim_matches('│').trim(), "");
+    }
+
+    fn type_in(input: &mut InputField, keys: &str) {
+        for ch in keys.chars() {
+            input.handle_events(KeyEvent::from(KeyCode::Char(ch)));
+        }
+    }
+
+    #[test]
+    fn test_complete_unique() {
+        let mut input = InputField::new();
+        type_in(&mut input, "al");
+        input.handle_events(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(input.current(), "allowlist ");
+        assert_eq!(input.apparent_cursor(), "allowlist ".len());
+        assert!(input.candidates().is_empty());
+
+        // arguments aren't completed
+        input.handle_events(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(input.current(), "allowlist ");
+
+        let mut input = InputField::new();
+        type_in(&mut input, "nope");
+        input.handle_events(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(input.current(), "nope");
+    }
+
+    #[test]
+    fn test_complete_cycle() {
+        // /seed and /setblock at least
+        let candidates = command::complete("se");
+        assert!(candidates.len() >= 2);
+        assert!(candidates.iter().all(|name| name.starts_with("se")));
+
+        let mut input = InputField::new();
+        type_in(&mut input, "se");
+        input.handle_events(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(input.current(), candidates[0]);
+        assert_eq!(input.candidates(), candidates);
+        for name in candidates.iter().skip(1) {
+            input.handle_events(KeyEvent::from(KeyCode::Tab));
+            assert_eq!(input.current(), *name);
+        }
+        // back to the first one
+        input.handle_events(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(input.current(), candidates[0]);
+
+        // typing anything else settles on the current one
+        type_in(&mut input, " ");
+        assert_eq!(input.current(), format!("{} ", candidates[0]));
+        assert!(input.candidates().is_empty());
+    }
+}